@@ -0,0 +1,1016 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+use tokio::time::timeout;
+use tracing::debug;
+
+use crate::config::{
+    CropRect, LoudnessTarget, Resolution, CROP_DETECT_FILTER, CROP_DETECT_MIN_BAR, DOMINANT_COLOR_SAMPLE, FFMPEG_EXECUTABLE, LOUDNESS_FLOOR_LUFS, REFRAME_MIN_MOTION,
+    REFRAME_MOTION_THRESHOLD, REFRAME_SAMPLE_FPS, REFRAME_SAMPLE_WIDTH, REFRAME_SMOOTHING, REFRAME_TOLERANCE, REFRAME_WINDOW,
+};
+use crate::error::{FfmpegError, FfmpegResult};
+use crate::ffmpeg::timeout_for_duration;
+use crate::utils::{format_seconds, TimeRange};
+
+/// Запускает аналитический проход FFmpeg (`-f null -`) и возвращает его stderr
+///
+/// Фильтры вроде `showinfo` пишут результаты в лог, поэтому вывод разбирается
+/// отдельными парсерами для каждого прохода.
+pub async fn run_analysis_pass(pass: &str, args: &[String], media_duration: f64) -> FfmpegResult<String> {
+    let output = run_pass(pass, args, media_duration, Stdio::null()).await?;
+    Ok(String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+/// Запускает проход FFmpeg, который пишет кадры в stdout (`-f rawvideo -`), и возвращает эти байты
+pub async fn run_capture_pass(pass: &str, args: &[String], media_duration: f64) -> FfmpegResult<Vec<u8>> {
+    let output = run_pass(pass, args, media_duration, Stdio::piped()).await?;
+    Ok(output.stdout)
+}
+
+/// Запускает проход FFmpeg, который пишет кадры в stdout, и отдает их по одному размером `frame_size`
+///
+/// В отличие от `run_capture_pass` поток не накапливается: в памяти только текущий кадр.
+/// Неполный последний кадр отбрасывается.
+pub async fn run_frame_pass(
+    pass: &str,
+    args: &[String],
+    media_duration: f64,
+    frame_size: usize,
+    mut on_frame: impl FnMut(&[u8]),
+) -> FfmpegResult<()> {
+    debug!("Running {} pass: {} {}", pass, FFMPEG_EXECUTABLE, args.join(" "));
+    
+    let mut child = Command::new(FFMPEG_EXECUTABLE)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|_| FfmpegError::CannotSpawnProcess)?;
+    let mut stdout = child.stdout.take().ok_or(FfmpegError::CannotSpawnProcess)?;
+    let mut stderr = child.stderr.take().ok_or(FfmpegError::CannotSpawnProcess)?;
+    
+    // stderr читается параллельно, иначе FFmpeg встанет на заполненном канале
+    let stderr = tokio::spawn(async move {
+        let mut output = Vec::new();
+        let _ = stderr.read_to_end(&mut output).await;
+        output
+    });
+    
+    let read_frames = async {
+        let mut frame = vec![0u8; frame_size.max(1)];
+        loop {
+            match stdout.read_exact(&mut frame).await {
+                Ok(_) => on_frame(&frame),
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(_) => return Err(FfmpegError::CannotSpawnProcess),
+            }
+        }
+        child.wait().await.map_err(|_| FfmpegError::CannotSpawnProcess)
+    };
+    
+    let limit = timeout_for_duration(media_duration);
+    let status = match timeout(limit, read_frames).await {
+        Ok(status) => status?,
+        Err(_) => return Err(FfmpegError::timeout(limit.as_secs())),
+    };
+    
+    if !status.success() {
+        let stderr = stderr.await.unwrap_or_default();
+        return Err(pass_failure(pass, &stderr));
+    }
+    
+    Ok(())
+}
+
+/// Ошибка прохода с последней строкой stderr FFmpeg в качестве причины
+fn pass_failure(pass: &str, stderr: &[u8]) -> FfmpegError {
+    let stderr = String::from_utf8_lossy(stderr);
+    let reason = stderr.lines().last().unwrap_or("unknown error").trim().to_string();
+    FfmpegError::analysis_failed(pass, reason)
+}
+
+/// Общая часть проходов: запуск с таймаутом и проверка кода завершения
+async fn run_pass(
+    pass: &str,
+    args: &[String],
+    media_duration: f64,
+    stdout: Stdio,
+) -> FfmpegResult<std::process::Output> {
+    debug!("Running {} pass: {} {}", pass, FFMPEG_EXECUTABLE, args.join(" "));
+    
+    let child = Command::new(FFMPEG_EXECUTABLE)
+        .args(args)
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|_| FfmpegError::CannotSpawnProcess)?;
+    
+    // Проход декодирует весь файл, поэтому таймаут считается так же, как для рендера
+    let limit = timeout_for_duration(media_duration);
+    let output = match timeout(limit, child.wait_with_output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(_)) => return Err(FfmpegError::CannotSpawnProcess),
+        Err(_) => return Err(FfmpegError::timeout(limit.as_secs())),
+    };
+    
+    if !output.status.success() {
+        return Err(pass_failure(pass, &output.stderr));
+    }
+    
+    Ok(output)
+}
+
+/// Находит моменты смены сцен во входном файле
+pub async fn detect_scene_changes(input: &Path, threshold: f64, media_duration: f64) -> FfmpegResult<Vec<f64>> {
+    let args = build_scene_detection_args(input, threshold);
+    let stderr = run_analysis_pass("scene detection", &args, media_duration).await?;
+    
+    Ok(parse_showinfo_timestamps(&stderr))
+}
+
+/// Строит аргументы прохода поиска сцен: `select='gt(scene,X)'` пропускает только
+/// кадры со сменой сцены, а `showinfo` печатает их `pts_time` в stderr
+fn build_scene_detection_args(input: &Path, threshold: f64) -> Vec<String> {
+    vec![
+        "-hide_banner".to_string(),
+        "-nostats".to_string(),
+        "-i".to_string(),
+        input.to_string_lossy().to_string(),
+        "-an".to_string(),
+        "-sn".to_string(),
+        "-filter:v".to_string(),
+        format!("select='gt(scene,{})',showinfo", threshold),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ]
+}
+
+/// Извлекает `pts_time` кадров из вывода фильтра `showinfo`
+///
+/// Метки возвращаются отсортированными и без повторов.
+pub fn parse_showinfo_timestamps(stderr: &str) -> Vec<f64> {
+    let mut timestamps: Vec<f64> = stderr
+        .lines()
+        .filter(|line| line.contains("Parsed_showinfo"))
+        .filter_map(|line| {
+            let value = line.split("pts_time:").nth(1)?;
+            value.split_whitespace().next()?.parse::<f64>().ok()
+        })
+        .filter(|time| time.is_finite() && *time >= 0.0)
+        .collect();
+    
+    timestamps.sort_by(|a, b| a.total_cmp(b));
+    timestamps.dedup();
+    timestamps
+}
+
+/// Находит участки тишины в звуковой дорожке входного файла
+pub async fn detect_silences(
+    input: &Path,
+    noise_db: f64,
+    min_silence: f64,
+    media_duration: f64,
+) -> FfmpegResult<Vec<TimeRange>> {
+    let args = build_silence_detection_args(input, noise_db, min_silence);
+    let stderr = run_analysis_pass("silence detection", &args, media_duration).await?;
+    
+    Ok(parse_silencedetect(&stderr, media_duration))
+}
+
+/// Строит аргументы прохода `silencedetect` (только звук, видео не декодируется)
+fn build_silence_detection_args(input: &Path, noise_db: f64, min_silence: f64) -> Vec<String> {
+    vec![
+        "-hide_banner".to_string(),
+        "-nostats".to_string(),
+        "-i".to_string(),
+        input.to_string_lossy().to_string(),
+        "-vn".to_string(),
+        "-sn".to_string(),
+        "-af".to_string(),
+        format!("silencedetect=noise={}dB:d={}", noise_db, min_silence),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ]
+}
+
+/// Извлекает участки тишины из вывода фильтра `silencedetect`
+///
+/// Тишина, которая началась и не закончилась до конца файла, продлевается
+/// до `media_duration`.
+pub fn parse_silencedetect(stderr: &str, media_duration: f64) -> Vec<TimeRange> {
+    let mut silences = Vec::new();
+    let mut current_start: Option<f64> = None;
+    
+    for line in stderr.lines().filter(|line| line.contains("silencedetect")) {
+        if let Some(start) = value_after(line, "silence_start:") {
+            current_start = Some(start.max(0.0));
+        } else if let Some(end) = value_after(line, "silence_end:") {
+            if let Some(start) = current_start.take() {
+                if end > start {
+                    silences.push(TimeRange::new(start, end));
+                }
+            }
+        }
+    }
+    
+    if let Some(start) = current_start {
+        if media_duration > start {
+            silences.push(TimeRange::new(start, media_duration));
+        }
+    }
+    
+    silences
+}
+
+/// Отсчет громкости из фильтра `ebur128`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessSample {
+    /// Время отсчета в секундах
+    pub time: f64,
+    /// Кратковременная (momentary, окно 400 мс) громкость в LUFS
+    pub momentary: f64,
+}
+
+/// Строит временную шкалу громкости звуковой дорожки входного файла
+pub async fn measure_loudness(input: &Path, media_duration: f64) -> FfmpegResult<Vec<LoudnessSample>> {
+    let args = build_loudness_args(input);
+    let stderr = run_analysis_pass("loudness analysis", &args, media_duration).await?;
+    
+    Ok(parse_ebur128_timeline(&stderr))
+}
+
+/// Строит аргументы прохода `ebur128`: фильтр печатает громкость каждые 100 мс
+fn build_loudness_args(input: &Path) -> Vec<String> {
+    vec![
+        "-hide_banner".to_string(),
+        "-nostats".to_string(),
+        "-i".to_string(),
+        input.to_string_lossy().to_string(),
+        "-vn".to_string(),
+        "-sn".to_string(),
+        "-af".to_string(),
+        "ebur128=framelog=info".to_string(),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ]
+}
+
+/// Извлекает отсчеты `t:` / `M:` из покадрового лога фильтра `ebur128`
+///
+/// Итоговая сводка фильтра (`Integrated loudness`) пропускается, `-inf` в тишине
+/// заменяется на `LOUDNESS_FLOOR_LUFS`.
+pub fn parse_ebur128_timeline(stderr: &str) -> Vec<LoudnessSample> {
+    stderr
+        .lines()
+        .filter(|line| line.contains("Parsed_ebur128"))
+        .filter_map(|line| {
+            let time = value_after(line, " t:")?;
+            let momentary = line
+                .split(" M:")
+                .nth(1)?
+                .split_whitespace()
+                .next()?
+                .parse::<f64>()
+                .ok()?;
+            let momentary = if momentary.is_finite() {
+                momentary.max(LOUDNESS_FLOOR_LUFS)
+            } else {
+                LOUDNESS_FLOOR_LUFS
+            };
+            Some(LoudnessSample { time, momentary })
+        })
+        .collect()
+}
+
+/// Статистика фильтра `loudnorm` с `print_format=json`
+///
+/// В проходе измерения `input_*` - громкость исходника; в проходе рендера
+/// `output_*` - фактическая громкость результата.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnormStats {
+    pub input_i: f64,
+    pub input_tp: f64,
+    pub input_lra: f64,
+    pub input_thresh: f64,
+    pub output_i: f64,
+    pub output_tp: f64,
+    pub target_offset: f64,
+}
+
+/// Громкость клипа до и после нормализации - попадает в финальный отчет
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessResult {
+    /// Интегральная громкость исходного фрагмента (LUFS)
+    pub measured_i: f64,
+    /// Истинный пик исходного фрагмента (dBTP)
+    pub measured_tp: f64,
+    /// Интегральная громкость результата (LUFS)
+    pub final_i: f64,
+    /// Истинный пик результата (dBTP)
+    pub final_tp: f64,
+}
+
+impl LoudnessResult {
+    /// Сводит замер первого прохода и статистику, напечатанную при рендере
+    ///
+    /// Если рендер статистику не напечатал, итогом считается прогноз первого прохода.
+    pub fn from_passes(measured: &LoudnormStats, rendered: Option<&LoudnormStats>) -> Self {
+        let result = rendered.unwrap_or(measured);
+        Self {
+            measured_i: measured.input_i,
+            measured_tp: measured.input_tp,
+            final_i: result.output_i,
+            final_tp: result.output_tp,
+        }
+    }
+}
+
+/// Находит черные полосы клипа быстрым проходом `cropdetect` по ключевым кадрам
+///
+/// Прямоугольники отдельных кадров сводятся в один по медианам краев, поэтому
+/// темные сцены и титры на черном фоне не обрезают картинку. `None` - полос нет.
+pub async fn detect_crop(
+    input: &Path,
+    segment: Option<TimeRange>,
+    source: Resolution,
+    media_duration: f64,
+) -> FfmpegResult<Option<CropRect>> {
+    let args = build_crop_detection_args(input, segment);
+    let clip_duration = segment.map_or(media_duration, |segment| segment.duration());
+    let stderr = run_analysis_pass("crop detection", &args, clip_duration).await?;
+    
+    Ok(aggregate_crop(&parse_cropdetect(&stderr), source))
+}
+
+/// Строит аргументы поиска полос: декодируются только ключевые кадры фрагмента
+fn build_crop_detection_args(input: &Path, segment: Option<TimeRange>) -> Vec<String> {
+    let mut args = vec![
+        "-hide_banner".to_string(),
+        "-nostats".to_string(),
+        "-skip_frame".to_string(),
+        "nokey".to_string(),
+    ];
+    
+    if let Some(segment) = segment {
+        args.extend([
+            "-ss".to_string(),
+            format_seconds(segment.start),
+            "-t".to_string(),
+            format_seconds(segment.duration()),
+        ]);
+    }
+    
+    args.extend([
+        "-i".to_string(),
+        input.to_string_lossy().to_string(),
+        "-an".to_string(),
+        "-sn".to_string(),
+        "-vf".to_string(),
+        CROP_DETECT_FILTER.to_string(),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ]);
+    args
+}
+
+/// Извлекает прямоугольники `crop=W:H:X:Y` из вывода `cropdetect`
+///
+/// Полностью черные кадры дают прямоугольник с отрицательным размером - такие пропускаются.
+pub fn parse_cropdetect(stderr: &str) -> Vec<CropRect> {
+    stderr
+        .lines()
+        .filter(|line| line.contains("Parsed_cropdetect"))
+        .filter_map(|line| {
+            let value = line.split("crop=").nth(1)?.split_whitespace().next()?;
+            let numbers: Vec<u32> = value.split(':').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+            match numbers[..] {
+                [width, height, x, y] if width > 0 && height > 0 => Some(CropRect { width, height, x, y }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Сводит прямоугольники кадров в один: каждый край - медиана по кадрам
+///
+/// Полосы уже `CROP_DETECT_MIN_BAR` стороны кадра не срезаются, размеры
+/// округляются вниз до четных. `None`, если срезать нечего.
+pub fn aggregate_crop(samples: &[CropRect], source: Resolution) -> Option<CropRect> {
+    if samples.is_empty() {
+        return None;
+    }
+    
+    let median = |bar: &dyn Fn(&CropRect) -> u32| {
+        let mut values: Vec<u32> = samples.iter().map(bar).collect();
+        values.sort_unstable();
+        values[values.len() / 2]
+    };
+    let trim = |bar: u32, side: u32| if (bar as f64) < side as f64 * CROP_DETECT_MIN_BAR { 0 } else { bar };
+    
+    let left = trim(median(&|rect| rect.x), source.width);
+    let right = trim(median(&|rect| source.width.saturating_sub(rect.x + rect.width)), source.width);
+    let top = trim(median(&|rect| rect.y), source.height);
+    let bottom = trim(median(&|rect| source.height.saturating_sub(rect.y + rect.height)), source.height);
+    if left + right + top + bottom == 0 {
+        return None;
+    }
+    
+    let width = source.width.saturating_sub(left + right);
+    let height = source.height.saturating_sub(top + bottom);
+    let rect = CropRect { width: width - width % 2, height: height - height % 2, x: left, y: top };
+    (rect.width >= 2 && rect.height >= 2).then_some(rect)
+}
+
+/// Фильтр обрезки черных полос в начале цепочки анализа; пусто, если обрезки нет
+fn crop_prefix(crop: Option<CropRect>) -> String {
+    crop.map_or(String::new(), |crop| format!("crop={},", crop))
+}
+
+/// Находит преобладающий цвет клипа по кадру-пробе из его середины
+///
+/// Черные полосы (`crop`) срезаются до подсчета, иначе черный всегда побеждает.
+/// `None`, если кадр не удалось получить (например, видео короче точки пробы).
+pub async fn detect_dominant_color(
+    input: &Path,
+    segment: Option<TimeRange>,
+    crop: Option<CropRect>,
+    media_duration: f64,
+) -> FfmpegResult<Option<u32>> {
+    let at = match segment {
+        Some(segment) => segment.start + segment.duration() / 2.0,
+        None => media_duration / 2.0,
+    };
+    let args = build_dominant_color_args(input, at, crop);
+    let pixels = run_capture_pass("dominant color", &args, 0.0).await?;
+    
+    Ok(dominant_color(&pixels))
+}
+
+/// Строит аргументы пробы: один кадр, уменьшенный до `DOMINANT_COLOR_SAMPLE`, в виде RGB24 в stdout
+fn build_dominant_color_args(input: &Path, at: f64, crop: Option<CropRect>) -> Vec<String> {
+    vec![
+        "-hide_banner".to_string(),
+        "-nostats".to_string(),
+        "-ss".to_string(),
+        format_seconds(at),
+        "-i".to_string(),
+        input.to_string_lossy().to_string(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-an".to_string(),
+        "-sn".to_string(),
+        "-vf".to_string(),
+        format!(
+            "{}scale={}:{}:flags=area,format=rgb24",
+            crop_prefix(crop),
+            DOMINANT_COLOR_SAMPLE.width,
+            DOMINANT_COLOR_SAMPLE.height
+        ),
+        "-f".to_string(),
+        "rawvideo".to_string(),
+        "-".to_string(),
+    ]
+}
+
+/// Находит преобладающий цвет в пикселях RGB24
+///
+/// Цвета огрубляются до 3 бит на канал, выбирается самая частая группа
+/// (при равенстве - первая), а результат - средний цвет пикселей этой группы,
+/// поэтому оттенок не искажается огрублением.
+pub fn dominant_color(pixels: &[u8]) -> Option<u32> {
+    // Группа: (порядок первого пикселя, число пикселей, суммы каналов)
+    let mut groups: HashMap<usize, (usize, u64, [u64; 3])> = HashMap::new();
+    for (order, pixel) in pixels.chunks_exact(3).enumerate() {
+        let key = pixel.iter().fold(0, |key, channel| (key << 3) | (*channel as usize >> 5));
+        let group = groups.entry(key).or_insert((order, 0, [0; 3]));
+        group.1 += 1;
+        for (sum, channel) in group.2.iter_mut().zip(pixel) {
+            *sum += *channel as u64;
+        }
+    }
+    
+    let (_, count, sums) = groups
+        .into_values()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))?;
+    let channel = |sum: u64| (sum as f64 / count as f64).round() as u32;
+    
+    Some((channel(sums[0]) << 16) | (channel(sums[1]) << 8) | channel(sums[2]))
+}
+
+/// Траектория центра действия по времени клипа: точки (секунда, доля ширины кадра)
+///
+/// Между точками центр движется линейно, до первой и после последней - стоит на месте.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReframeTrack {
+    pub points: Vec<(f64, f64)>,
+}
+
+/// Отслеживает движение в клипе для `--reframe`
+///
+/// Кадры клипа в оттенках серого, уменьшенные до `REFRAME_SAMPLE_WIDTH` по ширине,
+/// читаются из stdout FFmpeg с частотой `REFRAME_SAMPLE_FPS`. Центр движения
+/// считается для каждого окна `REFRAME_WINDOW`, затем траектория сглаживается
+/// и упрощается. `source` - кадр после обрезки черных полос `crop`, траектория
+/// задается в его долях. `None`, если не получено ни одного кадра.
+pub async fn track_motion(
+    input: &Path,
+    segment: Option<TimeRange>,
+    source: Resolution,
+    crop: Option<CropRect>,
+    media_duration: f64,
+) -> FfmpegResult<Option<ReframeTrack>> {
+    let size = motion_sample_size(source);
+    let args = build_motion_sample_args(input, segment, size, crop);
+    let clip_duration = segment.map_or(media_duration, |segment| segment.duration());
+    
+    // Кадры разбираются по мере поступления: целиком поток длинного исходника занял бы сотни мегабайт
+    let frames_per_window = (REFRAME_SAMPLE_FPS * REFRAME_WINDOW).round().max(1.0) as usize;
+    let mut windows = MotionWindows::new(size, frames_per_window);
+    let frame_size = size.width as usize * size.height as usize;
+    run_frame_pass("motion tracking", &args, clip_duration, frame_size, |frame| windows.push(frame)).await?;
+    
+    let centers = windows.finish();
+    if centers.is_empty() {
+        return Ok(None);
+    }
+    
+    let track = smooth_track(&centers, REFRAME_WINDOW, REFRAME_SMOOTHING);
+    Ok(Some(ReframeTrack { points: simplify_track(&track, REFRAME_TOLERANCE) }))
+}
+
+/// Размер кадров анализа: ширина `REFRAME_SAMPLE_WIDTH`, высота по пропорциям исходника
+fn motion_sample_size(source: Resolution) -> Resolution {
+    let height = REFRAME_SAMPLE_WIDTH as f64 * source.height as f64 / source.width.max(1) as f64;
+    Resolution::new(REFRAME_SAMPLE_WIDTH, ((height / 2.0).round() as u32 * 2).max(2))
+}
+
+/// Строит аргументы выборки кадров фрагмента: серые кадры размера `size` подряд в stdout
+fn build_motion_sample_args(input: &Path, segment: Option<TimeRange>, size: Resolution, crop: Option<CropRect>) -> Vec<String> {
+    let mut args = vec!["-hide_banner".to_string(), "-nostats".to_string()];
+    
+    if let Some(segment) = segment {
+        args.extend([
+            "-ss".to_string(),
+            format_seconds(segment.start),
+            "-t".to_string(),
+            format_seconds(segment.duration()),
+        ]);
+    }
+    
+    args.extend([
+        "-i".to_string(),
+        input.to_string_lossy().to_string(),
+        "-an".to_string(),
+        "-sn".to_string(),
+        "-vf".to_string(),
+        format!(
+            "{}fps={},scale={}:{}:flags=area,format=gray",
+            crop_prefix(crop),
+            REFRAME_SAMPLE_FPS,
+            size.width,
+            size.height
+        ),
+        "-f".to_string(),
+        "rawvideo".to_string(),
+        "-".to_string(),
+    ]);
+    args
+}
+
+/// Центры движения по окнам из `frames_per_window` серых кадров, считаемые по мере чтения кадров
+///
+/// Движущийся пиксель - тот, чья яркость изменилась с предыдущего кадра больше
+/// `REFRAME_MOTION_THRESHOLD`; центр - средняя координата таких пикселей в долях
+/// ширины. `None` - в окне почти ничего не двигалось (меньше `REFRAME_MIN_MOTION`).
+/// В памяти хранятся только предыдущий кадр и суммы текущего окна.
+pub struct MotionWindows {
+    width: usize,
+    frames_per_window: usize,
+    min_pixels: f64,
+    previous: Option<Vec<u8>>,
+    /// Сколько кадров уже попало в текущее окно
+    window_frames: usize,
+    /// Число движущихся пикселей окна и сумма их координат по горизонтали
+    count: u64,
+    sum: u64,
+    centers: Vec<Option<f64>>,
+}
+
+impl MotionWindows {
+    pub fn new(size: Resolution, frames_per_window: usize) -> Self {
+        let frames_per_window = frames_per_window.max(1);
+        let frame_size = size.width as usize * size.height as usize;
+        Self {
+            width: (size.width as usize).max(1),
+            frames_per_window,
+            min_pixels: ((frame_size * frames_per_window) as f64 * REFRAME_MIN_MOTION).max(1.0),
+            previous: None,
+            window_frames: 0,
+            count: 0,
+            sum: 0,
+            centers: Vec::new(),
+        }
+    }
+    
+    /// Добавляет очередной кадр; первый кадр окна сравнивается с последним кадром предыдущего
+    pub fn push(&mut self, frame: &[u8]) {
+        if self.window_frames == self.frames_per_window {
+            self.close_window();
+        }
+        
+        match &mut self.previous {
+            Some(previous) => {
+                for (offset, (before, after)) in previous.iter_mut().zip(frame).enumerate() {
+                    if before.abs_diff(*after) > REFRAME_MOTION_THRESHOLD {
+                        self.count += 1;
+                        self.sum += (offset % self.width) as u64;
+                    }
+                    *before = *after;
+                }
+            }
+            None => self.previous = Some(frame.to_vec()),
+        }
+        self.window_frames += 1;
+    }
+    
+    /// Закрывает последнее окно и возвращает центры всех окон
+    pub fn finish(mut self) -> Vec<Option<f64>> {
+        if self.window_frames > 0 {
+            self.close_window();
+        }
+        self.centers
+    }
+    
+    fn close_window(&mut self) {
+        let center = (self.count as f64 >= self.min_pixels).then(|| (self.sum as f64 / self.count as f64 + 0.5) / self.width as f64);
+        self.centers.push(center);
+        self.count = 0;
+        self.sum = 0;
+        self.window_frames = 0;
+    }
+}
+
+/// Превращает центры окон в плавную траекторию: точки в серединах окон длиной `window`
+///
+/// Окно без движения сохраняет центр предыдущего (начальные - первого найденного,
+/// без движения вовсе - центр кадра), затем центры усредняются по `radius` окон
+/// с каждой стороны, чтобы кадр не дергался.
+pub fn smooth_track(centers: &[Option<f64>], window: f64, radius: usize) -> Vec<(f64, f64)> {
+    let mut last = centers.iter().flatten().next().copied().unwrap_or(0.5);
+    let filled: Vec<f64> = centers
+        .iter()
+        .map(|center| {
+            if let Some(center) = center {
+                last = *center;
+            }
+            last
+        })
+        .collect();
+    
+    (0..filled.len())
+        .map(|index| {
+            let around = &filled[index.saturating_sub(radius)..(index + radius + 1).min(filled.len())];
+            let average = around.iter().sum::<f64>() / around.len() as f64;
+            ((index as f64 + 0.5) * window, average)
+        })
+        .collect()
+}
+
+/// Упрощает траекторию (Рамер - Дуглас - Пекер): убирает точки, без которых
+/// линейная интерполяция отклоняется от них не больше чем на `tolerance`
+pub fn simplify_track(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    if points.len() <= 2 {
+        return points.to_vec();
+    }
+    
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut pending = vec![(0, points.len() - 1)];
+    
+    while let Some((first, last)) = pending.pop() {
+        let ((t0, x0), (t1, x1)) = (points[first], points[last]);
+        let farthest = (first + 1..last)
+            .map(|index| {
+                let (t, x) = points[index];
+                (index, (x - (x0 + (x1 - x0) * (t - t0) / (t1 - t0))).abs())
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        
+        if let Some((index, _)) = farthest.filter(|(_, error)| *error > tolerance) {
+            keep[index] = true;
+            pending.push((first, index));
+            pending.push((index, last));
+        }
+    }
+    
+    points.iter().zip(keep).filter(|(_, keep)| *keep).map(|(point, _)| *point).collect()
+}
+
+/// Первый проход `loudnorm`: измеряет громкость клипа
+///
+/// `None`, если звук в клипе - сплошная тишина и нормализовать нечего.
+pub async fn measure_loudnorm(
+    input: &Path,
+    segment: Option<TimeRange>,
+    audio_track: usize,
+    target: &LoudnessTarget,
+    media_duration: f64,
+) -> FfmpegResult<Option<LoudnormStats>> {
+    let args = build_loudnorm_measure_args(input, segment, audio_track, target);
+    let stderr = run_analysis_pass("loudnorm measurement", &args, media_duration).await?;
+    
+    Ok(parse_loudnorm_stats(&stderr))
+}
+
+/// Строит аргументы прохода измерения (только звук, тот же фрагмент и та же дорожка, что и рендер)
+fn build_loudnorm_measure_args(
+    input: &Path,
+    segment: Option<TimeRange>,
+    audio_track: usize,
+    target: &LoudnessTarget,
+) -> Vec<String> {
+    let mut args = vec!["-hide_banner".to_string(), "-nostats".to_string()];
+    
+    if let Some(segment) = segment {
+        args.extend([
+            "-ss".to_string(),
+            format_seconds(segment.start),
+            "-t".to_string(),
+            format_seconds(segment.duration()),
+        ]);
+    }
+    
+    args.extend([
+        "-i".to_string(),
+        input.to_string_lossy().to_string(),
+        "-map".to_string(),
+        format!("0:a:{}", audio_track),
+        "-af".to_string(),
+        format!(
+            "loudnorm=I={}:TP={}:LRA={}:print_format=json",
+            target.integrated, target.true_peak, target.range
+        ),
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ]);
+    args
+}
+
+/// Извлекает JSON-статистику, которую `loudnorm` печатает в stderr после своей строки лога
+///
+/// Возвращает `None`, если статистики нет или громкость не измерена (`-inf` в тишине).
+pub fn parse_loudnorm_stats(stderr: &str) -> Option<LoudnormStats> {
+    let after_filter = &stderr[stderr.rfind("Parsed_loudnorm")?..];
+    let start = after_filter.find('{')?;
+    let end = start + after_filter[start..].find('}')?;
+    
+    let raw: HashMap<String, String> = serde_json::from_str(&after_filter[start..=end]).ok()?;
+    let value = |key: &str| raw.get(key)?.trim().parse::<f64>().ok().filter(|v| v.is_finite());
+    
+    Some(LoudnormStats {
+        input_i: value("input_i")?,
+        input_tp: value("input_tp")?,
+        input_lra: value("input_lra")?,
+        input_thresh: value("input_thresh")?,
+        output_i: value("output_i")?,
+        output_tp: value("output_tp")?,
+        target_offset: value("target_offset")?,
+    })
+}
+
+/// Возвращает число, идущее в строке лога сразу после `key`
+fn value_after(line: &str, key: &str) -> Option<f64> {
+    let value = line.split(key).nth(1)?;
+    value.split_whitespace().next()?.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    
+    #[test]
+    fn test_parse_showinfo_timestamps() {
+        let stderr = "\
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'talk.mp4':
+[Parsed_showinfo_1 @ 0x55d5c8a1c2c0] config in time_base: 1/15360, frame_rate: 30/1
+[Parsed_showinfo_1 @ 0x55d5c8a1c2c0] n:   0 pts: 193024 pts_time:12.5667 duration:    512 fmt:yuv420p
+[Parsed_showinfo_1 @ 0x55d5c8a1c2c0] n:   1 pts: 658944 pts_time:42.9 duration:    512 fmt:yuv420p
+[Parsed_showinfo_1 @ 0x55d5c8a1c2c0] n:   2 pts: 193024 pts_time:12.5667 duration:    512 fmt:yuv420p
+[Parsed_showinfo_1 @ 0x55d5c8a1c2c0] n:   3 pts: 1000 pts_time:NOPE
+[out#0/null @ 0x55d5c8a1d000] video:0KiB audio:0KiB pts_time:99
+";
+
+        assert_eq!(parse_showinfo_timestamps(stderr), vec![12.5667, 42.9]);
+        assert!(parse_showinfo_timestamps("").is_empty());
+    }
+    
+    #[test]
+    fn test_scene_detection_args() {
+        let args = build_scene_detection_args(&PathBuf::from("talk.mp4"), 0.3);
+        
+        assert!(args.contains(&"select='gt(scene,0.3)',showinfo".to_string()));
+        assert_eq!(&args[args.len() - 3..], &["-f", "null", "-"]);
+        assert!(args.contains(&"-an".to_string()));
+    }
+    
+    #[test]
+    fn test_parse_silencedetect() {
+        let stderr = "\
+[silencedetect @ 0x5581cd4b9f40] silence_start: -0.0213
+[silencedetect @ 0x5581cd4b9f40] silence_end: 2.502 | silence_duration: 2.523
+size=N/A time=00:00:30.00 bitrate=N/A speed= 512x
+[silencedetect @ 0x5581cd4b9f40] silence_start: 14.25
+[silencedetect @ 0x5581cd4b9f40] silence_end: 18.75 | silence_duration: 4.5
+[silencedetect @ 0x5581cd4b9f40] silence_start: 57.1
+";
+        
+        assert_eq!(
+            parse_silencedetect(stderr, 60.0),
+            vec![TimeRange::new(0.0, 2.502), TimeRange::new(14.25, 18.75), TimeRange::new(57.1, 60.0)]
+        );
+        assert!(parse_silencedetect("", 60.0).is_empty());
+    }
+    
+    #[test]
+    fn test_parse_ebur128_timeline() {
+        let stderr = "\
+[Parsed_ebur128_0 @ 0x5624a9c5a480] Summary:
+[Parsed_ebur128_0 @ 0x5624a9c5a480] t: 0.1        TARGET:-23 LUFS    M:-120.7 S:-120.7     I: -70.0 LUFS       LRA:   0.0 LU
+[Parsed_ebur128_0 @ 0x5624a9c5a480] t: 0.2        TARGET:-23 LUFS    M: -21.4 S:-120.7     I: -21.4 LUFS       LRA:   0.0 LU
+[Parsed_ebur128_0 @ 0x5624a9c5a480] t: 0.3        TARGET:-23 LUFS    M: -inf S:-120.7     I: -21.4 LUFS       LRA:   0.0 LU
+  Integrated loudness:
+    I:         -21.4 LUFS
+";
+        
+        assert_eq!(
+            parse_ebur128_timeline(stderr),
+            vec![
+                LoudnessSample { time: 0.1, momentary: LOUDNESS_FLOOR_LUFS },
+                LoudnessSample { time: 0.2, momentary: -21.4 },
+                LoudnessSample { time: 0.3, momentary: LOUDNESS_FLOOR_LUFS },
+            ]
+        );
+        assert!(build_loudness_args(&PathBuf::from("talk.mp4")).contains(&"ebur128=framelog=info".to_string()));
+    }
+    
+    #[test]
+    fn test_parse_loudnorm_stats() {
+        let stderr = r#"
+[Parsed_loudnorm_0 @ 0x55f1c2a3b7c0] 
+{
+	"input_i" : "-27.61",
+	"input_tp" : "-4.47",
+	"input_lra" : "18.06",
+	"input_thresh" : "-39.20",
+	"output_i" : "-14.03",
+	"output_tp" : "-1.00",
+	"output_lra" : "7.10",
+	"output_thresh" : "-24.46",
+	"normalization_type" : "dynamic",
+	"target_offset" : "0.03"
+}
+[out#0/null @ 0x55f1c2a3c000] video:0KiB audio:2250KiB
+"#;
+        
+        let stats = parse_loudnorm_stats(stderr).unwrap();
+        assert_eq!(stats.input_i, -27.61);
+        assert_eq!(stats.input_thresh, -39.2);
+        assert_eq!(stats.output_tp, -1.0);
+        assert_eq!(stats.target_offset, 0.03);
+        
+        // Тишина: громкость не измерена
+        let silent = stderr.replace("\"-27.61\"", "\"-inf\"");
+        assert_eq!(parse_loudnorm_stats(&silent), None);
+        assert_eq!(parse_loudnorm_stats("no stats here"), None);
+        
+        let rendered = LoudnormStats { output_i: -14.1, output_tp: -1.2, ..stats };
+        let result = LoudnessResult::from_passes(&stats, Some(&rendered));
+        assert_eq!((result.measured_i, result.final_i, result.final_tp), (-27.61, -14.1, -1.2));
+        assert_eq!(LoudnessResult::from_passes(&stats, None).final_i, -14.03);
+    }
+    
+    #[test]
+    fn test_loudnorm_measure_args() {
+        let target = LoudnessTarget::new(-14.0, -1.0).unwrap();
+        let args =
+            build_loudnorm_measure_args(&PathBuf::from("talk.mp4"), Some(TimeRange::new(30.0, 75.0)), 1, &target);
+        
+        assert_eq!(&args[2..7], &["-ss", "30.000", "-t", "45.000", "-i"]);
+        assert_eq!(&args[8..10], &["-map", "0:a:1"]);
+        assert!(args.contains(&"loudnorm=I=-14:TP=-1:LRA=11:print_format=json".to_string()));
+        assert_eq!(&args[args.len() - 3..], &["-f", "null", "-"]);
+    }
+    
+    #[test]
+    fn test_silence_detection_args() {
+        let args = build_silence_detection_args(&PathBuf::from("talk.mp4"), -30.0, 1.5);
+        
+        assert!(args.contains(&"silencedetect=noise=-30dB:d=1.5".to_string()));
+        assert!(args.contains(&"-vn".to_string()));
+        assert_eq!(&args[args.len() - 3..], &["-f", "null", "-"]);
+    }
+    
+    #[test]
+    fn test_crop_detection() {
+        let line = |crop: &str| format!("[Parsed_cropdetect_0 @ 0x55d5c0] x1:0 x2:1919 y1:140 y2:939 w:1920 h:800 x:0 y:140 pts:0 t:0.000000 limit:0.094118 crop={}\n", crop);
+        let stderr = [
+            line("1920:800:0:140"),
+            line("-1904:-1072:1912:1080"),
+            line("1920:800:0:140"),
+            line("1280:560:320:260"),
+            line("1920:800:0:140"),
+            "frame=  120 fps=0.0 q=-0.0 Lsize=N/A time=00:00:59.96\n".to_string(),
+        ]
+        .concat();
+        
+        // Черный кадр пропускается, темная сцена не перевешивает полосы
+        let samples = parse_cropdetect(&stderr);
+        assert_eq!(samples.len(), 4);
+        let source = Resolution::new(1920, 1080);
+        assert_eq!(aggregate_crop(&samples, source), Some(CropRect { width: 1920, height: 800, x: 0, y: 140 }));
+        
+        // Рамка в пару пикселей - не полосы
+        assert_eq!(aggregate_crop(&[CropRect { width: 1916, height: 1076, x: 2, y: 2 }], source), None);
+        assert_eq!(aggregate_crop(&[], source), None);
+        assert_eq!(
+            aggregate_crop(&[CropRect { width: 1080, height: 1080, x: 180, y: 0 }], Resolution::new(1440, 1080)),
+            Some(CropRect { width: 1080, height: 1080, x: 180, y: 0 })
+        );
+        
+        let args = build_crop_detection_args(&PathBuf::from("talk.mp4"), Some(TimeRange::new(10.0, 40.0)));
+        assert_eq!(&args[2..10], &["-skip_frame", "nokey", "-ss", "10.000", "-t", "30.000", "-i", "talk.mp4"]);
+        assert!(args.contains(&CROP_DETECT_FILTER.to_string()));
+        assert_eq!(&args[args.len() - 3..], &["-f", "null", "-"]);
+    }
+    
+    #[test]
+    fn test_dominant_color() {
+        // Два оттенка синего в одной группе перевешивают три разных цвета
+        let pixels = [
+            10, 20, 200, 12, 22, 210, 14, 18, 205,
+            250, 250, 250, 0, 0, 0, 200, 30, 30,
+        ];
+        assert_eq!(dominant_color(&pixels), Some(0x0C14CD));
+        
+        // При равенстве побеждает цвет, встреченный первым; неполный пиксель не учитывается
+        assert_eq!(dominant_color(&[0, 0, 0, 255, 255, 255, 9]), Some(0x000000));
+        assert_eq!(dominant_color(&[]), None);
+        
+        let args = build_dominant_color_args(&PathBuf::from("talk.mp4"), 75.5, None);
+        assert_eq!(&args[2..6], &["-ss", "75.500", "-i", "talk.mp4"]);
+        assert!(args.contains(&"scale=64:36:flags=area,format=rgb24".to_string()));
+        assert_eq!(&args[args.len() - 3..], &["-f", "rawvideo", "-"]);
+        
+        // Черные полосы срезаются до подсчета
+        let crop = CropRect { width: 1920, height: 800, x: 0, y: 140 };
+        let args = build_dominant_color_args(&PathBuf::from("talk.mp4"), 75.5, Some(crop));
+        assert!(args.contains(&"crop=1920:800:0:140,scale=64:36:flags=area,format=rgb24".to_string()));
+    }
+    
+    #[test]
+    fn test_motion_tracking() {
+        // Кадры 8x2: в первом окне загорается столбец 6, во втором ничего не меняется,
+        // в третьем столбец 6 гаснет, а столбец 1 загорается
+        let size = Resolution::new(8, 2);
+        let frame = |lit: &[usize]| -> Vec<u8> { (0..16).map(|i| if lit.contains(&(i % 8)) { 255 } else { 0 }).collect() };
+        let mut windows = MotionWindows::new(size, 2);
+        for lit in [&[][..], &[6], &[6], &[6], &[1], &[1]] {
+            windows.push(&frame(lit));
+        }
+        assert_eq!(windows.finish(), vec![Some(0.8125), None, Some(0.5)]);
+        assert!(MotionWindows::new(size, 2).finish().is_empty());
+        
+        // Окна без движения держат прежний центр, начальные - первый найденный
+        let track = smooth_track(&[None, Some(0.2), None, Some(0.8)], 1.0, 1);
+        let expected = [(0.5, 0.2), (1.5, 0.2), (2.5, 0.4), (3.5, 0.5)];
+        for ((time, center), (expected_time, expected_center)) in track.iter().zip(expected) {
+            assert_eq!(*time, expected_time);
+            assert!((center - expected_center).abs() < 1e-9);
+        }
+        assert_eq!(smooth_track(&[None, None], 1.0, 1), vec![(0.5, 0.5), (1.5, 0.5)]);
+        
+        // Точки, без которых отклонение не больше допуска, выбрасываются
+        let points = [(0.0, 0.0), (1.0, 0.005), (2.0, 0.0), (3.0, 0.5), (4.0, 1.0), (5.0, 1.0)];
+        assert_eq!(simplify_track(&points, 0.01), vec![(0.0, 0.0), (2.0, 0.0), (4.0, 1.0), (5.0, 1.0)]);
+        
+        let args = build_motion_sample_args(
+            &PathBuf::from("talk.mp4"),
+            Some(TimeRange::new(10.0, 40.0)),
+            motion_sample_size(Resolution::new(1920, 1080)),
+            None,
+        );
+        assert_eq!(&args[2..8], &["-ss", "10.000", "-t", "30.000", "-i", "talk.mp4"]);
+        assert!(args.contains(&"fps=4,scale=160:90:flags=area,format=gray".to_string()));
+        assert_eq!(&args[args.len() - 3..], &["-f", "rawvideo", "-"]);
+    }
+}
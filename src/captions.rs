@@ -0,0 +1,267 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::{KaraokePreset, OutputVariant, Resolution, SUBTITLE_REFERENCE_HEIGHT};
+use crate::error::{TranscribeError, TranscribeResult};
+use crate::transcribe::{parse_word_timings, WordTiming};
+use crate::utils::{FileTask, TimeRange};
+
+/// Переводит цвет 0xRRGGBB в формат ASS `&HAABBGGRR` (непрозрачный)
+pub fn ass_color(color: u32) -> String {
+    format!("&H00{:02X}{:02X}{:02X}", color & 0xFF, (color >> 8) & 0xFF, (color >> 16) & 0xFF)
+}
+
+/// Цвет для тега `\c` внутри текста: `&HBBGGRR&`
+fn ass_tag_color(color: u32) -> String {
+    format!("&H{:02X}{:02X}{:02X}&", color & 0xFF, (color >> 8) & 0xFF, (color >> 16) & 0xFF)
+}
+
+/// Переносит слова из времени исходника во время клипа
+///
+/// Слова вне фрагмента отбрасываются, попавшие на границу - обрезаются по ней.
+pub fn clip_words(words: &[WordTiming], segment: Option<TimeRange>) -> Vec<WordTiming> {
+    let segment = match segment {
+        Some(segment) => segment,
+        None => return words.to_vec(),
+    };
+    
+    words
+        .iter()
+        .filter(|word| word.end > segment.start && word.start < segment.end)
+        .map(|word| WordTiming {
+            word: word.word.clone(),
+            start: (word.start - segment.start).max(0.0),
+            end: (word.end - segment.start).min(segment.duration()),
+        })
+        .collect()
+}
+
+/// Строит ASS-субтитры с подсветкой произносимого слова для кадра заданного размера
+///
+/// Слова идут группами по `max_words_per_line`; каждое слово группы - отдельное
+/// событие, в котором вся группа видна, а текущее слово выделено цветом.
+/// Группа не гаснет в паузах между своими словами. Размеры пресета
+/// масштабируются к высоте кадра, текст стоит над нижней зоной `safe_zone`.
+pub fn build_karaoke_ass(words: &[WordTiming], preset: &KaraokePreset, frame: Resolution, safe_zone: f64) -> String {
+    let scale = frame.height as f64 / SUBTITLE_REFERENCE_HEIGHT;
+    let round = |value: f64| (value * 100.0).round() / 100.0;
+    
+    let mut ass = format!(
+        "[Script Info]\n\
+         ScriptType: v4.00+\n\
+         PlayResX: {width}\n\
+         PlayResY: {height}\n\
+         WrapStyle: 0\n\
+         ScaledBorderAndShadow: yes\n\
+         \n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, \
+         Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, \
+         MarginR, MarginV, Encoding\n\
+         Style: Karaoke,{font},{size},{color},{color},{outline_color},&H00000000,{bold},0,0,0,100,100,0,0,1,{outline},0,2,\
+         {margin_h},{margin_h},{margin_v},1\n\
+         \n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        width = frame.width,
+        height = frame.height,
+        font = preset.font,
+        size = round(preset.size * scale),
+        color = ass_color(preset.color),
+        outline_color = ass_color(preset.outline_color),
+        bold = if preset.bold { -1 } else { 0 },
+        outline = round(preset.outline * scale),
+        margin_h = (frame.width as f64 * 0.06).round() as u32,
+        margin_v = (frame.height as f64 * safe_zone).round() as u32,
+    );
+    
+    let highlight = ass_tag_color(preset.highlight_color);
+    for line in words.chunks(preset.max_words_per_line.max(1)) {
+        for (index, word) in line.iter().enumerate() {
+            let end = line.get(index + 1).map_or(word.end, |next| next.start.max(word.start));
+            let text: Vec<String> = line
+                .iter()
+                .enumerate()
+                .map(|(other, w)| {
+                    let text = escape_ass_text(&w.word);
+                    if other == index { format!("{{\\c{}}}{}{{\\r}}", highlight, text) } else { text }
+                })
+                .collect();
+            
+            ass.push_str(&format!(
+                "Dialogue: 0,{},{},Karaoke,,0,0,0,,{}\n",
+                format_ass_time(word.start),
+                format_ass_time(end),
+                text.join(" ")
+            ));
+        }
+    }
+    
+    ass
+}
+
+/// Форматирует время для ASS: `H:MM:SS.cc`
+fn format_ass_time(seconds: f64) -> String {
+    let centis = (seconds.max(0.0) * 100.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        centis / 360_000,
+        centis / 6000 % 60,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+/// Убирает из слова символы, которые ASS считает разметкой (`{...}` - теги, `\` - команды)
+fn escape_ass_text(word: &str) -> String {
+    word.replace('\\', "/").replace('{', "(").replace('}', ")")
+}
+
+/// Загружает тайминги слов клипа (по времени клипа)
+///
+/// Источник - файл `clip.words.json` рядом с исходником (по времени исходника),
+/// иначе тайминги слов, сохраненные распознаванием речи рядом с выходом.
+pub fn load_clip_words(task: &FileTask) -> TranscribeResult<Option<Vec<WordTiming>>> {
+    let transcript_words = task
+        .transcript
+        .as_ref()
+        .map(|transcript| transcript.with_extension("words.json"))
+        .filter(|path| path.is_file());
+    
+    let (path, segment) = match (&task.word_timings, transcript_words) {
+        (Some(path), _) => (path.clone(), task.segment),
+        (None, Some(path)) => (path, None),
+        (None, None) => return Ok(None),
+    };
+    
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| TranscribeError::invalid_output(path.clone(), e.to_string()))?;
+    let words = parse_word_timings(&content).map_err(|reason| TranscribeError::invalid_output(path, reason))?;
+    
+    Ok(Some(clip_words(&words, segment)))
+}
+
+/// Пишет ASS-файлы пословных субтитров для всех выходов задачи: `<выход>.ass`
+///
+/// Возвращает пустой список, если у клипа нет таймингов слов.
+/// Файлы нужны только на время рендера и удаляются `remove_karaoke_files`.
+pub fn write_karaoke_files(
+    task: &FileTask,
+    preset: &KaraokePreset,
+    variants: &[OutputVariant],
+    safe_zone: f64,
+) -> TranscribeResult<Vec<PathBuf>> {
+    let words = match load_clip_words(task)? {
+        Some(words) => words,
+        None => return Ok(Vec::new()),
+    };
+    
+    task.outputs
+        .iter()
+        .zip(variants)
+        .map(|(output, variant)| {
+            let path = karaoke_path(output);
+            let ass = build_karaoke_ass(&words, preset, variant.resolution, safe_zone);
+            std::fs::write(&path, ass).map_err(|e| TranscribeError::cannot_store(path.clone(), e.to_string()))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Удаляет ASS-файлы пословных субтитров после рендера
+pub fn remove_karaoke_files(files: &[PathBuf]) {
+    for path in files {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Путь ASS-файла выхода: `<выход>.ass`
+fn karaoke_path(output: &Path) -> PathBuf {
+    output.with_extension("ass")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KARAOKE_PRESETS;
+    use tempfile::TempDir;
+    
+    fn word(text: &str, start: f64, end: f64) -> WordTiming {
+        WordTiming { word: text.to_string(), start, end }
+    }
+    
+    #[test]
+    fn test_build_karaoke_ass() {
+        let preset = KaraokePreset { max_words_per_line: 2, ..KARAOKE_PRESETS[0] };
+        let words = vec![word("Hello", 0.0, 0.4), word("{big}", 0.5, 0.9), word("world", 1.2, 1.6)];
+        let ass = build_karaoke_ass(&words, &preset, Resolution::new(720, 1280), 0.2);
+        
+        // Размеры пресета заданы для кадра 1920 - для 1280 уменьшаются на треть
+        assert!(ass.contains("PlayResX: 720\nPlayResY: 1280\n"));
+        assert!(ass.contains(
+            "Style: Karaoke,Arial,53.33,&H00FFFFFF,&H00FFFFFF,&H00000000,&H00000000,-1,0,0,0,100,100,0,0,1,3.33,0,2,43,43,256,1\n"
+        ));
+        
+        // Каждое слово подсвечено в своем событии, группа видна до следующего слова
+        let events: Vec<&str> = ass.lines().filter(|line| line.starts_with("Dialogue:")).collect();
+        assert_eq!(events, vec![
+            "Dialogue: 0,0:00:00.00,0:00:00.50,Karaoke,,0,0,0,,{\\c&H00D4FF&}Hello{\\r} (big)",
+            "Dialogue: 0,0:00:00.50,0:00:00.90,Karaoke,,0,0,0,,Hello {\\c&H00D4FF&}(big){\\r}",
+            "Dialogue: 0,0:00:01.20,0:00:01.60,Karaoke,,0,0,0,,{\\c&H00D4FF&}world{\\r}",
+        ]);
+        
+        assert_eq!(format_ass_time(3725.456), "1:02:05.46");
+    }
+    
+    #[test]
+    fn test_clip_words_shift_to_segment() {
+        let words = vec![word("before", 8.0, 9.0), word("edge", 9.8, 10.3), word("inside", 12.0, 12.5), word("after", 20.5, 21.0)];
+        
+        let clipped = clip_words(&words, Some(TimeRange::new(10.0, 20.0)));
+        assert_eq!(clipped.len(), 2);
+        assert_eq!(clipped[0].start, 0.0);
+        assert!((clipped[0].end - 0.3).abs() < 1e-9);
+        assert_eq!(clipped[1].word, "inside");
+        assert!((clipped[1].start - 2.0).abs() < 1e-9);
+        
+        assert_eq!(clip_words(&words, None), words);
+    }
+    
+    #[test]
+    fn test_write_karaoke_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let words_path = temp_dir.path().join("talk.words.json");
+        std::fs::write(&words_path, r#"[{"word": "Hi", "start": 61.0, "end": 61.5}]"#).unwrap();
+        
+        let variants = vec![
+            OutputVariant { label: "9x16".to_string(), resolution: Resolution::new(1080, 1920) },
+            OutputVariant { label: "1x1".to_string(), resolution: Resolution::new(1080, 1080) },
+        ];
+        let outputs = vec![temp_dir.path().join("talk-short-9x16.mp4"), temp_dir.path().join("talk-short-1x1.mp4")];
+        let mut task = FileTask::new(PathBuf::from("talk.mp4"), outputs);
+        let preset = KaraokePreset::find("classic").unwrap();
+        
+        // Без таймингов слов ASS не создается
+        assert!(write_karaoke_files(&task, &preset, &variants, 0.2).unwrap().is_empty());
+        
+        // Слова из файла рядом с исходником сдвигаются к началу фрагмента
+        task.word_timings = Some(words_path);
+        task.segment = Some(TimeRange::new(60.0, 90.0));
+        let files = write_karaoke_files(&task, &preset, &variants, 0.2).unwrap();
+        assert_eq!(files, vec![temp_dir.path().join("talk-short-9x16.ass"), temp_dir.path().join("talk-short-1x1.ass")]);
+        let square = std::fs::read_to_string(&files[1]).unwrap();
+        assert!(square.contains("PlayResY: 1080"));
+        assert!(square.contains("Dialogue: 0,0:00:01.00,0:00:01.50,"));
+        remove_karaoke_files(&files);
+        assert!(files.iter().all(|path| !path.exists()));
+        
+        // Тайминги из распознавания уже по времени клипа
+        task.word_timings = None;
+        task.transcript = Some(temp_dir.path().join("talk-short-9x16.srt"));
+        std::fs::write(temp_dir.path().join("talk-short-9x16.words.json"), r#"[{"word": "Yo", "start": 2.0, "end": 2.5}]"#).unwrap();
+        let words = load_clip_words(&task).unwrap().unwrap();
+        assert_eq!((words[0].start, words[0].end), (2.0, 2.5));
+        
+        assert!(KaraokePreset::find("PUNCHY").is_ok());
+        assert!(KaraokePreset::find("neon").is_err());
+    }
+}
@@ -1,10 +1,24 @@
 use clap::Parser;
-use std::path::PathBuf;
-use crate::config::AppConfig;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::config::{
+    AppConfig, AspectRatio, AudioSelection, BackgroundMode, KaraokePreset, LoudnessTarget, MissingAudio, MusicSettings, OutputVariant, Resolution,
+    SplitAudio, SplitSettings, SubtitleStyle, TitleSettings, WatermarkPosition, WatermarkSettings, DEFAULT_HIGHLIGHT_LENGTH, DEFAULT_SPLIT_RATIO,
+    DEFAULT_TITLE_SIZE,
+    DEFAULT_WATERMARK_MARGIN, DEFAULT_WATERMARK_OPACITY, DEFAULT_WATERMARK_SCALE,
+    DEFAULT_RESOLUTION, DEFAULT_SCENE_MAX_LENGTH, DEFAULT_SCENE_MIN_LENGTH, DEFAULT_SCENE_THRESHOLD,
+    DEFAULT_SILENCE_DURATION, DEFAULT_SILENCE_NOISE_DB, DEFAULT_TARGET_LUFS, DEFAULT_TRUE_PEAK, MAX_HIGHLIGHTS,
+    DEFAULT_MUSIC_VOLUME, MAX_OUTPUT_VARIANTS, MIN_SEGMENT_LENGTH, MUSIC_EXTENSIONS, MUSIC_FADE_DURATION,
+};
 use crate::error::{ConfigError, ConfigResult};
+use crate::ffmpeg::RenderSettings;
+use crate::manifest::{CutList, PairList};
+use crate::planner::{CutStrategy, HighlightSettings, SceneCutSettings, SilenceCutSettings, SilenceMode};
+use crate::transcribe::{CommandTranscriber, Transcriber};
+use crate::utils::{find_music_files, parse_time_spec};
 
 /// CLI tool for batch video processing using FFmpeg
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone, Default)]
 #[command(
     name = "shorts-cutter",
     version = env!("CARGO_PKG_VERSION"),
@@ -28,6 +42,379 @@ pub struct CliArgs {
         help = "Number of parallel threads (default: CPU cores)"
     )]
     pub threads: Option<usize>,
+    
+    /// Output resolution, e.g. 1080x1920
+    #[arg(
+        long,
+        value_name = "WxH",
+        conflicts_with = "aspect",
+        help = "Output resolution, e.g. 1080x1920 (default: 720x1280)"
+    )]
+    pub resolution: Option<String>,
+    
+    /// Output aspect ratio, e.g. 9:16, 4:5 or 1:1
+    #[arg(
+        long,
+        value_name = "W:H",
+        help = "Output aspect ratio, e.g. 9:16, 4:5 or 1:1 (short side stays 720px)"
+    )]
+    pub aspect: Option<String>,
+    
+    /// Several output formats rendered from a single decode
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_delimiter = ',',
+        help = "Render several formats at once, e.g. 9:16,1:1,4:5 or 1080x1920,1080x1080"
+    )]
+    pub formats: Vec<String>,
+    
+    /// Split each input into parts of the given length
+    #[arg(
+        long,
+        value_name = "DURATION",
+        help = "Split each video into parts of this length, e.g. 60s, 1m30s or 00:01:00"
+    )]
+    pub segment_length: Option<String>,
+    
+    /// Split each input at scene changes
+    #[arg(
+        long,
+        conflicts_with = "segment_length",
+        help = "Split each video at scene changes (see --scene-threshold, --min-segment, --max-segment)"
+    )]
+    pub scene_cuts: bool,
+    
+    /// Scene change threshold for scene-based cutting
+    #[arg(
+        long,
+        value_name = "0..1",
+        requires = "scene_cuts",
+        help = "Scene change sensitivity, lower finds more cuts (default: 0.3)"
+    )]
+    pub scene_threshold: Option<f64>,
+    
+    /// Minimum part length for scene-based cutting
+    #[arg(
+        long,
+        value_name = "DURATION",
+        requires = "scene_cuts",
+        help = "Minimum length of a scene-based part (default: 15s)"
+    )]
+    pub min_segment: Option<String>,
+    
+    /// Maximum part length for scene-based cutting
+    #[arg(
+        long,
+        value_name = "DURATION",
+        requires = "scene_cuts",
+        help = "Maximum length of a scene-based part (default: 60s)"
+    )]
+    pub max_segment: Option<String>,
+    
+    /// Cut list with time ranges per input file
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["segment_length", "scene_cuts"],
+        help = "CSV (file,start,end[,title]) or JSON cut list; only listed files are processed"
+    )]
+    pub cuts: Option<PathBuf>,
+    
+    /// Trim leading/trailing silence or split at long pauses
+    #[arg(
+        long,
+        value_name = "MODE",
+        conflicts_with_all = ["segment_length", "scene_cuts", "cuts"],
+        help = "Silence handling: 'trim' removes leading/trailing silence, 'split' cuts at pauses"
+    )]
+    pub silence: Option<String>,
+    
+    /// Noise level treated as silence
+    #[arg(
+        long,
+        value_name = "DB",
+        requires = "silence",
+        allow_hyphen_values = true,
+        help = "Noise level treated as silence, e.g. -35dB (default: -30dB)"
+    )]
+    pub silence_noise: Option<String>,
+    
+    /// Minimum pause length that counts as silence
+    #[arg(
+        long,
+        value_name = "DURATION",
+        requires = "silence",
+        help = "Minimum pause length to trim or split at, e.g. 2s (default: 1s)"
+    )]
+    pub silence_duration: Option<String>,
+    
+    /// Pick the K loudest moments of each input
+    #[arg(
+        long,
+        value_name = "K",
+        conflicts_with_all = ["segment_length", "scene_cuts", "cuts", "silence"],
+        help = "Cut the K loudest non-overlapping moments of each video (see --highlight-length)"
+    )]
+    pub highlights: Option<usize>,
+    
+    /// Length of each highlight
+    #[arg(
+        long,
+        value_name = "DURATION",
+        requires = "highlights",
+        help = "Length of each highlight, e.g. 45s (default: 30s)"
+    )]
+    pub highlight_length: Option<String>,
+    
+    /// What to do with inputs that have no audio stream
+    #[arg(
+        long,
+        value_name = "MODE",
+        help = "Inputs without audio: 'silence' adds a silent track, 'omit' renders without audio, 'error' fails (default: silence)"
+    )]
+    pub missing_audio: Option<String>,
+    
+    /// Audio track to use, by number or language tag
+    #[arg(
+        long,
+        value_name = "TRACK",
+        help = "Audio track to use: number among audio tracks (0 = first) or language tag, e.g. eng"
+    )]
+    pub audio_track: Option<String>,
+    
+    /// One output per audio language
+    #[arg(
+        long,
+        conflicts_with = "audio_track",
+        help = "Render one output per audio language, with the language tag in the file name"
+    )]
+    pub per_language: bool,
+    
+    /// Background music file or directory of tracks
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Mix a music bed under each clip, ducked under speech; a directory is used round-robin"
+    )]
+    pub music: Option<PathBuf>,
+    
+    /// Music volume before ducking
+    #[arg(
+        long,
+        value_name = "0..1",
+        requires = "music",
+        help = "Music volume before ducking (default: 0.25)"
+    )]
+    pub music_volume: Option<f64>,
+    
+    /// Normalize loudness with a two-pass EBU R128 loudnorm
+    #[arg(
+        long,
+        help = "Normalize loudness (EBU R128, two passes) to --target-lufs / --true-peak; with --music only the voice is normalized"
+    )]
+    pub loudnorm: bool,
+    
+    /// Target integrated loudness for --loudnorm
+    #[arg(
+        long,
+        value_name = "LUFS",
+        requires = "loudnorm",
+        allow_hyphen_values = true,
+        help = "Target integrated loudness in LUFS (default: -14)"
+    )]
+    pub target_lufs: Option<f64>,
+    
+    /// Maximum true peak for --loudnorm
+    #[arg(
+        long,
+        value_name = "DBTP",
+        requires = "loudnorm",
+        allow_hyphen_values = true,
+        help = "Maximum true peak in dBTP (default: -1)"
+    )]
+    pub true_peak: Option<f64>,
+    
+    /// Subtitle font name
+    #[arg(long, value_name = "NAME", help = "Font for burned-in subtitles (default: Arial)")]
+    pub subtitle_font: Option<String>,
+    
+    /// Subtitle font size
+    #[arg(
+        long,
+        value_name = "PX",
+        help = "Subtitle font size in pixels of a 1920-high frame, scaled for other formats (default: 64)"
+    )]
+    pub subtitle_size: Option<f64>,
+    
+    /// Subtitle text color
+    #[arg(long, value_name = "COLOR", help = "Subtitle text color: #RRGGBB or a name, e.g. yellow (default: white)")]
+    pub subtitle_color: Option<String>,
+    
+    /// Subtitle outline color
+    #[arg(long, value_name = "COLOR", help = "Subtitle outline color: #RRGGBB or a name (default: black)")]
+    pub subtitle_outline_color: Option<String>,
+    
+    /// Subtitle outline width
+    #[arg(
+        long,
+        value_name = "PX",
+        help = "Subtitle outline width in pixels of a 1920-high frame, 0 disables it (default: 4)"
+    )]
+    pub subtitle_outline: Option<f64>,
+    
+    /// Bottom safe zone for subtitles
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Keep subtitles above the bottom PERCENT of the frame covered by platform UI (default: 20)"
+    )]
+    pub subtitle_margin: Option<f64>,
+    
+    /// Local speech-to-text command for clips without subtitle files
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        help = "Transcribe clips without subtitle files with a local command; {audio} is a 16 kHz WAV, \
+                the command must write {output}.srt or {output}.json, e.g. \
+                \"whisper-cli -m ggml-base.bin -f {audio} -osrt -of {output}\""
+    )]
+    pub transcribe: Option<String>,
+    
+    /// Word-by-word highlighted captions
+    #[arg(
+        long,
+        value_name = "PRESET",
+        num_args = 0..=1,
+        default_missing_value = "classic",
+        help = "Word-by-word highlighted captions from word timings (clip.words.json or --transcribe JSON); \
+                presets: classic, punchy, minimal (default: classic)"
+    )]
+    pub karaoke: Option<String>,
+    
+    /// Headline text at the top of each short
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        help = "Headline at the top of each short; placeholders: {stem} (file name), {title} (cut list title, \
+                falls back to the file name), {part}"
+    )]
+    pub title: Option<String>,
+    
+    /// Font file for the headline
+    #[arg(long, value_name = "FILE", requires = "title", help = "Font file (.ttf/.otf) for the headline")]
+    pub title_font: Option<PathBuf>,
+    
+    /// Headline font size
+    #[arg(
+        long,
+        value_name = "PX",
+        requires = "title",
+        help = "Headline font size in pixels of a 1920-high frame, scaled for other formats (default: 72)"
+    )]
+    pub title_size: Option<f64>,
+    
+    /// Logo image overlaid on every output
+    #[arg(long, value_name = "IMAGE", help = "Logo image (PNG, JPEG, GIF or WebP) overlaid on every output")]
+    pub watermark: Option<PathBuf>,
+    
+    /// Watermark corner
+    #[arg(
+        long,
+        value_name = "CORNER",
+        requires = "watermark",
+        help = "Watermark corner: top-left, top-right, bottom-left or bottom-right (default: top-right)"
+    )]
+    pub watermark_position: Option<String>,
+    
+    /// Watermark margin
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        requires = "watermark",
+        help = "Distance from the frame edges in percent of the output width (default: 4)"
+    )]
+    pub watermark_margin: Option<f64>,
+    
+    /// Watermark width
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        requires = "watermark",
+        help = "Watermark width in percent of the output width (default: 15)"
+    )]
+    pub watermark_scale: Option<f64>,
+    
+    /// Watermark opacity
+    #[arg(long, value_name = "0-1", requires = "watermark", help = "Watermark opacity from 0 to 1 (default: 0.8)")]
+    pub watermark_opacity: Option<f64>,
+    
+    /// Background around horizontal video
+    #[arg(
+        long,
+        value_name = "MODE",
+        help = "Background around horizontal video: blur, mirror, dominant, color:COLOR, \
+                gradient[:TOP:BOTTOM], image:FILE or video:FILE (default: blur)"
+    )]
+    pub background: Option<String>,
+    
+    /// Crop that follows the action
+    #[arg(
+        long,
+        conflicts_with_all = ["background", "split_screen"],
+        help = "Follow the action in horizontal video with a moving crop instead of a background"
+    )]
+    pub reframe: bool,
+    
+    /// Keep baked-in black bars
+    #[arg(long, help = "Keep black bars: skip the cropdetect pre-pass (crops given in --cuts still apply)")]
+    pub no_autocrop: bool,
+    
+    /// Clip played before every short
+    #[arg(long, value_name = "FILE", help = "Intro clip joined before every short, fitted to each output format")]
+    pub intro: Option<PathBuf>,
+    
+    /// Clip played after every short
+    #[arg(long, value_name = "FILE", help = "Outro clip (end card) joined after every short")]
+    pub outro: Option<PathBuf>,
+    
+    /// Two inputs stacked in one frame
+    #[arg(
+        long,
+        help = "Stack two inputs in one frame: NAME.top.mp4 above NAME.bottom.mp4, or the pairs from --split-pairs"
+    )]
+    pub split_screen: bool,
+    
+    /// Input pairs for --split-screen
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "split_screen",
+        help = "CSV (top,bottom) or JSON list of input pairs for --split-screen; only listed files are processed"
+    )]
+    pub split_pairs: Option<PathBuf>,
+    
+    /// Height of the top input
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        requires = "split_screen",
+        help = "Share of the frame height taken by the top input, 10-90 (default: 50)"
+    )]
+    pub split_ratio: Option<f64>,
+    
+    /// Audio of the split screen
+    #[arg(
+        long,
+        value_name = "SOURCE",
+        requires = "split_screen",
+        help = "Split-screen audio: top, bottom or mix of both (default: top)"
+    )]
+    pub split_audio: Option<String>,
+    
+    /// Only print the planned clips without rendering
+    #[arg(long, help = "Analyze inputs and list the planned clips without rendering anything")]
+    pub dry_run: bool,
 }
 
 impl CliArgs {
@@ -88,14 +475,576 @@ impl CliArgs {
             None => AppConfig::default_thread_count(),
         };
         
+        // Валидируем целевое разрешение
+        let resolution = match (&self.resolution, &self.aspect) {
+            (Some(_), Some(_)) => {
+                return Err(ConfigError::invalid_arg(
+                    "--resolution and --aspect cannot be used together"
+                ));
+            }
+            (Some(resolution), None) => Resolution::parse(resolution)?,
+            (None, Some(aspect)) => AspectRatio::parse(aspect)?.to_resolution(DEFAULT_RESOLUTION)?,
+            (None, None) => DEFAULT_RESOLUTION,
+        };
+        
+        // Валидируем список выходных форматов
+        let variants = if self.formats.is_empty() {
+            vec![OutputVariant {
+                label: resolution.to_string(),
+                resolution,
+            }]
+        } else {
+            if self.formats.len() > MAX_OUTPUT_VARIANTS {
+                return Err(ConfigError::invalid_arg(format!(
+                    "Too many output formats: {} (max {})",
+                    self.formats.len(),
+                    MAX_OUTPUT_VARIANTS
+                )));
+            }
+            
+            let mut variants: Vec<OutputVariant> = Vec::new();
+            for spec in &self.formats {
+                let variant = OutputVariant::parse(spec, resolution)?;
+                if variants.iter().any(|v| v.label == variant.label || v.resolution == variant.resolution) {
+                    return Err(ConfigError::invalid_arg(format!("Duplicate output format: {}", spec)));
+                }
+                variants.push(variant);
+            }
+            variants
+        };
+        
+        let missing_audio = match &self.missing_audio {
+            Some(value) => MissingAudio::parse(value)?,
+            None => MissingAudio::default(),
+        };
+        
+        // Валидируем выбор звуковой дорожки
+        let audio = match (&self.audio_track, self.per_language) {
+            (Some(_), true) => {
+                return Err(ConfigError::invalid_arg("--audio-track and --per-language cannot be used together"));
+            }
+            (Some(track), false) => AudioSelection::parse(track)?,
+            (None, true) => AudioSelection::PerLanguage,
+            (None, false) => AudioSelection::First,
+        };
+        
+        // Валидируем музыкальную подложку
+        if self.music_volume.is_some() && self.music.is_none() {
+            return Err(ConfigError::invalid_arg("--music-volume requires --music"));
+        }
+        let (music, music_tracks) = match &self.music {
+            Some(path) => music_settings(path, self.music_volume)?,
+            None => (None, Vec::new()),
+        };
+        
+        // Валидируем оформление субтитров
+        let subtitle_style = subtitle_style(
+            self.subtitle_font.as_deref(),
+            self.subtitle_size,
+            self.subtitle_color.as_deref(),
+            self.subtitle_outline_color.as_deref(),
+            self.subtitle_outline,
+            self.subtitle_margin,
+        )?;
+        
+        let transcriber = match &self.transcribe {
+            Some(template) => Some(CommandTranscriber::parse(template)?),
+            None => None,
+        };
+        
+        let karaoke = match &self.karaoke {
+            Some(name) => Some(KaraokePreset::find(name)?),
+            None => None,
+        };
+        
+        // Валидируем заголовок
+        if (self.title_font.is_some() || self.title_size.is_some()) && self.title.is_none() {
+            return Err(ConfigError::invalid_arg("--title-font and --title-size require --title"));
+        }
+        let title = match &self.title {
+            Some(template) => Some(title_settings(template, self.title_font.clone(), self.title_size)?),
+            None => None,
+        };
+        
+        // Валидируем водяной знак
+        let has_watermark_options = self.watermark_position.is_some()
+            || self.watermark_margin.is_some()
+            || self.watermark_scale.is_some()
+            || self.watermark_opacity.is_some();
+        if has_watermark_options && self.watermark.is_none() {
+            return Err(ConfigError::invalid_arg(
+                "--watermark-position, --watermark-margin, --watermark-scale and --watermark-opacity require --watermark"
+            ));
+        }
+        let watermark = match &self.watermark {
+            Some(path) => Some(watermark_settings(
+                path,
+                self.watermark_position.as_deref(),
+                self.watermark_margin,
+                self.watermark_scale,
+                self.watermark_opacity,
+            )?),
+            None => None,
+        };
+        
+        // Валидируем фон
+        let background = match &self.background {
+            Some(value) => background_mode(value)?,
+            None => BackgroundMode::default(),
+        };
+        
+        // Валидируем разделенный экран
+        if (self.split_pairs.is_some() || self.split_ratio.is_some() || self.split_audio.is_some()) && !self.split_screen {
+            return Err(ConfigError::invalid_arg(
+                "--split-pairs, --split-ratio and --split-audio require --split-screen"
+            ));
+        }
+        if self.split_screen && self.background.is_some() {
+            return Err(ConfigError::invalid_arg(
+                "--background cannot be used with --split-screen: the inputs fill the whole frame"
+            ));
+        }
+        if self.reframe && (self.background.is_some() || self.split_screen) {
+            return Err(ConfigError::invalid_arg(
+                "--reframe cannot be used with --background or --split-screen: the crop fills the whole frame"
+            ));
+        }
+        let split = if self.split_screen {
+            Some(split_settings(self.split_ratio, self.split_audio.as_deref())?)
+        } else {
+            None
+        };
+        // Дорожка выбирается только у верхнего исходника: нижний всегда звучит первой дорожкой
+        if split.as_ref().is_some_and(|split| split.audio != SplitAudio::Top) && !matches!(audio, AudioSelection::First) {
+            return Err(ConfigError::invalid_arg(
+                "--audio-track and --per-language apply to the top input only and cannot be used with --split-audio bottom or mix"
+            ));
+        }
+        let split_pairs = match &self.split_pairs {
+            Some(path) if !path.is_file() => {
+                return Err(ConfigError::invalid_arg(format!("Split pair list not found: {}", path.display())));
+            }
+            Some(path) => Some(PairList::load(path)?),
+            None => None,
+        };
+        
+        // Валидируем заставку и финальную карточку; их анализ - после проверки FFmpeg
+        for (option, path) in [("--intro", &self.intro), ("--outro", &self.outro)] {
+            if let Some(path) = path {
+                if !path.is_file() {
+                    return Err(ConfigError::invalid_arg(format!("{} clip not found: {}", option, path.display())));
+                }
+            }
+        }
+        
+        // Валидируем нормализацию громкости
+        if (self.target_lufs.is_some() || self.true_peak.is_some()) && !self.loudnorm {
+            return Err(ConfigError::invalid_arg("--target-lufs and --true-peak require --loudnorm"));
+        }
+        let loudnorm = if self.loudnorm {
+            Some(LoudnessTarget::new(
+                self.target_lufs.unwrap_or(DEFAULT_TARGET_LUFS),
+                self.true_peak.unwrap_or(DEFAULT_TRUE_PEAK),
+            )?)
+        } else {
+            None
+        };
+        
+        // Валидируем стратегию нарезки
+        let has_scene_options =
+            self.scene_threshold.is_some() || self.min_segment.is_some() || self.max_segment.is_some();
+        if has_scene_options && !self.scene_cuts {
+            return Err(ConfigError::invalid_arg(
+                "--scene-threshold, --min-segment and --max-segment require --scene-cuts"
+            ));
+        }
+        
+        if (self.silence_noise.is_some() || self.silence_duration.is_some()) && self.silence.is_none() {
+            return Err(ConfigError::invalid_arg(
+                "--silence-noise and --silence-duration require --silence"
+            ));
+        }
+        
+        if self.highlight_length.is_some() && self.highlights.is_none() {
+            return Err(ConfigError::invalid_arg("--highlight-length requires --highlights"));
+        }
+        
+        let strategies = [
+            self.segment_length.is_some(),
+            self.scene_cuts,
+            self.cuts.is_some(),
+            self.silence.is_some(),
+            self.highlights.is_some(),
+        ];
+        if strategies.iter().filter(|enabled| **enabled).count() > 1 {
+            return Err(ConfigError::invalid_arg(
+                "--segment-length, --scene-cuts, --cuts, --silence and --highlights cannot be used together"
+            ));
+        }
+        
+        let cut_strategy = if let Some(value) = &self.segment_length {
+            CutStrategy::FixedLength {
+                seconds: parse_segment_length(value)?,
+            }
+        } else if self.scene_cuts {
+            CutStrategy::Scenes(scene_cut_settings(
+                self.scene_threshold,
+                self.min_segment.as_deref(),
+                self.max_segment.as_deref(),
+            )?)
+        } else if let Some(path) = &self.cuts {
+            if !path.is_file() {
+                return Err(ConfigError::invalid_arg(
+                    format!("Cut list file not found: {}", path.display())
+                ));
+            }
+            CutStrategy::CutList(Arc::new(CutList::load(path)?))
+        } else if let Some(mode) = &self.silence {
+            CutStrategy::Silence(silence_cut_settings(
+                mode,
+                self.silence_noise.as_deref(),
+                self.silence_duration.as_deref(),
+            )?)
+        } else if let Some(count) = self.highlights {
+            if count == 0 || count > MAX_HIGHLIGHTS {
+                return Err(ConfigError::invalid_arg(format!(
+                    "Highlight count must be between 1 and {}: {}",
+                    MAX_HIGHLIGHTS, count
+                )));
+            }
+            let length = match &self.highlight_length {
+                Some(value) => parse_segment_length(value)?,
+                None => DEFAULT_HIGHLIGHT_LENGTH,
+            };
+            CutStrategy::Highlights(HighlightSettings { count, length })
+        } else {
+            CutStrategy::Whole
+        };
+        
         Ok(ValidatedArgs {
             input,
             output,
             threads,
+            resolution,
+            variants,
+            cut_strategy,
+            missing_audio,
+            audio,
+            loudnorm,
+            music,
+            music_tracks,
+            subtitle_style,
+            transcriber,
+            karaoke,
+            title,
+            watermark,
+            background,
+            reframe: self.reframe,
+            autocrop: !self.no_autocrop,
+            intro: self.intro,
+            outro: self.outro,
+            split,
+            split_pairs,
+            dry_run: self.dry_run,
         })
     }
 }
 
+/// Валидирует параметры нарезки по сценам
+fn scene_cut_settings(
+    threshold: Option<f64>,
+    min_segment: Option<&str>,
+    max_segment: Option<&str>,
+) -> ConfigResult<SceneCutSettings> {
+    let threshold = threshold.unwrap_or(DEFAULT_SCENE_THRESHOLD);
+    if !(threshold > 0.0 && threshold < 1.0) {
+        return Err(ConfigError::invalid_arg(format!(
+            "Scene threshold must be between 0 and 1 (exclusive): {}",
+            threshold
+        )));
+    }
+    
+    let min_length = match min_segment {
+        Some(value) => parse_segment_length(value)?,
+        None => DEFAULT_SCENE_MIN_LENGTH,
+    };
+    let max_length = match max_segment {
+        Some(value) => parse_segment_length(value)?,
+        None => DEFAULT_SCENE_MAX_LENGTH,
+    };
+    
+    if max_length < min_length {
+        return Err(ConfigError::invalid_duration(
+            max_segment.map_or_else(|| format!("{}s", max_length), String::from),
+            format!("maximum part length is shorter than the minimum ({}s)", min_length),
+        ));
+    }
+    
+    Ok(SceneCutSettings { threshold, min_length, max_length })
+}
+
+/// Валидирует параметры обработки тишины
+fn silence_cut_settings(
+    mode: &str,
+    noise: Option<&str>,
+    duration: Option<&str>,
+) -> ConfigResult<SilenceCutSettings> {
+    let mode = SilenceMode::parse(mode).ok_or_else(|| {
+        ConfigError::invalid_arg(format!("Unknown silence mode '{}': expected 'trim' or 'split'", mode))
+    })?;
+    
+    let noise_db = match noise {
+        Some(value) => {
+            let number = value.trim().trim_end_matches("dB").trim_end_matches("db");
+            match number.parse::<f64>() {
+                Ok(db) if (-90.0..0.0).contains(&db) => db,
+                _ => {
+                    return Err(ConfigError::invalid_arg(format!(
+                        "Invalid silence noise level '{}': expected a negative dB value, e.g. -30dB",
+                        value
+                    )));
+                }
+            }
+        }
+        None => DEFAULT_SILENCE_NOISE_DB,
+    };
+    
+    let min_silence = match duration {
+        Some(value) => {
+            let seconds = parse_time_spec(value)
+                .ok_or_else(|| ConfigError::invalid_duration(value, "expected e.g. 1s, 2.5 or 00:00:02"))?;
+            if seconds <= 0.0 {
+                return Err(ConfigError::invalid_duration(value, "pause length must be positive"));
+            }
+            seconds
+        }
+        None => DEFAULT_SILENCE_DURATION,
+    };
+    
+    Ok(SilenceCutSettings { mode, noise_db, min_silence })
+}
+
+/// Собирает музыкальные треки и проверяет громкость подложки
+fn music_settings(path: &Path, volume: Option<f64>) -> ConfigResult<(Option<MusicSettings>, Vec<PathBuf>)> {
+    if !path.exists() {
+        return Err(ConfigError::invalid_arg(format!("Music path not found: {}", path.display())));
+    }
+    
+    let tracks = find_music_files(path)
+        .map_err(|e| ConfigError::invalid_arg(format!("Cannot read music directory: {}", e)))?;
+    if tracks.is_empty() {
+        return Err(ConfigError::invalid_arg(format!(
+            "No music tracks ({}) found in {}",
+            MUSIC_EXTENSIONS.join(", "),
+            path.display()
+        )));
+    }
+    
+    let volume = volume.unwrap_or(DEFAULT_MUSIC_VOLUME);
+    if !(volume > 0.0 && volume <= 1.0) {
+        return Err(ConfigError::invalid_arg(format!("Music volume must be in (0, 1]: {}", volume)));
+    }
+    
+    Ok((Some(MusicSettings { volume, fade: MUSIC_FADE_DURATION }), tracks))
+}
+
+/// Собирает оформление субтитров; незаданные параметры берутся по умолчанию
+fn subtitle_style(
+    font: Option<&str>,
+    size: Option<f64>,
+    color: Option<&str>,
+    outline_color: Option<&str>,
+    outline: Option<f64>,
+    margin: Option<f64>,
+) -> ConfigResult<SubtitleStyle> {
+    let mut style = SubtitleStyle::default();
+    
+    if let Some(font) = font {
+        let font = font.trim();
+        // Запятая разделяет поля стиля ASS
+        if font.is_empty() || font.contains(',') {
+            return Err(ConfigError::invalid_arg(format!("Invalid subtitle font name: '{}'", font)));
+        }
+        style.font = font.to_string();
+    }
+    if let Some(size) = size {
+        if !(size > 0.0 && size <= 400.0) {
+            return Err(ConfigError::invalid_arg(format!("Subtitle size must be in (0, 400] pixels: {}", size)));
+        }
+        style.size = size;
+    }
+    if let Some(outline) = outline {
+        if !(0.0..=50.0).contains(&outline) {
+            return Err(ConfigError::invalid_arg(format!("Subtitle outline must be in [0, 50] pixels: {}", outline)));
+        }
+        style.outline = outline;
+    }
+    if let Some(margin) = margin {
+        if !(0.0..50.0).contains(&margin) {
+            return Err(ConfigError::invalid_arg(format!("Subtitle margin must be in [0, 50) percent: {}", margin)));
+        }
+        style.safe_zone = margin / 100.0;
+    }
+    if let Some(color) = color {
+        style.color = SubtitleStyle::parse_color(color)?;
+    }
+    if let Some(color) = outline_color {
+        style.outline_color = SubtitleStyle::parse_color(color)?;
+    }
+    
+    Ok(style)
+}
+
+/// Валидирует шаблон, файл шрифта и размер заголовка
+fn title_settings(template: &str, font_file: Option<PathBuf>, size: Option<f64>) -> ConfigResult<TitleSettings> {
+    if template.trim().is_empty() {
+        return Err(ConfigError::invalid_arg("Title template is empty"));
+    }
+    
+    let size = size.unwrap_or(DEFAULT_TITLE_SIZE);
+    if !(size > 0.0 && size <= 400.0) {
+        return Err(ConfigError::invalid_arg(format!("Title size must be in (0, 400] pixels: {}", size)));
+    }
+    
+    if let Some(path) = &font_file {
+        if !path.is_file() {
+            return Err(ConfigError::invalid_arg(format!("Title font file not found: {}", path.display())));
+        }
+    }
+    
+    TitleSettings::new(template, font_file, size)
+}
+
+/// Разбирает режим фона и проверяет, что файл картинки или видео фона существует
+///
+/// Картинку целиком декодирует FFmpeg при запуске (`check_image_decodes`),
+/// поэтому битый файл отклоняется до рендера, а не в каждой задаче.
+fn background_mode(value: &str) -> ConfigResult<BackgroundMode> {
+    let mode = BackgroundMode::parse(value)?;
+    
+    match &mode {
+        BackgroundMode::Image(path) if !path.is_file() => Err(ConfigError::invalid_arg(format!(
+            "Background image not found: {}",
+            path.display()
+        ))),
+        BackgroundMode::Video(path) if !path.is_file() => Err(ConfigError::invalid_arg(format!(
+            "Background video not found: {}",
+            path.display()
+        ))),
+        _ => Ok(mode),
+    }
+}
+
+/// Проверяет, что FFmpeg декодирует изображение: первый кадр целиком, а не только заголовок
+///
+/// На многих ошибках декодирования (обрезанный PNG или JPEG с целым заголовком)
+/// FFmpeg завершается успешно, поэтому `-xerror` останавливает его на первой
+/// ошибке, а любое сообщение в stderr тоже считается отказом.
+async fn check_image_decodes(path: &Path, what: &str) -> ConfigResult<()> {
+    let output = tokio::process::Command::new(crate::config::FFMPEG_EXECUTABLE)
+        .args(["-v", "error", "-xerror", "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-f", "null", "-"])
+        .output()
+        .await
+        .map_err(|_| ConfigError::FfmpegNotFound)?;
+    
+    match decode_failure(output.status.success(), &output.stderr) {
+        Some(reason) => Err(ConfigError::invalid_arg(format!(
+            "{} is not a decodable image: {} ({})",
+            what,
+            path.display(),
+            reason
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Причина отказа проверки декодирования: ненулевой код выхода или любое сообщение FFmpeg
+fn decode_failure(success: bool, stderr: &[u8]) -> Option<String> {
+    let stderr = String::from_utf8_lossy(stderr);
+    match stderr.lines().map(str::trim).rfind(|line| !line.is_empty()) {
+        Some(line) => Some(line.to_string()),
+        None if success => None,
+        None => Some("unknown error".to_string()),
+    }
+}
+
+/// Валидирует долю верхнего исходника и источник звука разделенного экрана
+fn split_settings(ratio: Option<f64>, audio: Option<&str>) -> ConfigResult<SplitSettings> {
+    let ratio = ratio.map_or(DEFAULT_SPLIT_RATIO, |percent| percent / 100.0);
+    if !(0.1..=0.9).contains(&ratio) {
+        return Err(ConfigError::invalid_arg(format!(
+            "Split ratio must be in [10, 90] percent: {}",
+            ratio * 100.0
+        )));
+    }
+    
+    let audio = match audio {
+        Some(value) => SplitAudio::parse(value)?,
+        None => SplitAudio::default(),
+    };
+    
+    Ok(SplitSettings { ratio, audio })
+}
+
+/// Валидирует водяной знак: файл должен существовать, параметры - попадать в допустимые диапазоны
+///
+/// Декодируемость изображения проверяется вместе с FFmpeg в `check_ffmpeg_availability`.
+fn watermark_settings(
+    path: &Path,
+    position: Option<&str>,
+    margin: Option<f64>,
+    scale: Option<f64>,
+    opacity: Option<f64>,
+) -> ConfigResult<WatermarkSettings> {
+    if !path.is_file() {
+        return Err(ConfigError::invalid_arg(format!("Watermark image not found: {}", path.display())));
+    }
+    let position = match position {
+        Some(value) => WatermarkPosition::parse(value)?,
+        None => WatermarkPosition::default(),
+    };
+    
+    let margin = margin.map_or(DEFAULT_WATERMARK_MARGIN, |percent| percent / 100.0);
+    if !(0.0..0.25).contains(&margin) {
+        return Err(ConfigError::invalid_arg(format!(
+            "Watermark margin must be in [0, 25) percent: {}",
+            margin * 100.0
+        )));
+    }
+    
+    let scale = scale.map_or(DEFAULT_WATERMARK_SCALE, |percent| percent / 100.0);
+    if !(scale > 0.0 && scale <= 1.0) {
+        return Err(ConfigError::invalid_arg(format!(
+            "Watermark scale must be in (0, 100] percent: {}",
+            scale * 100.0
+        )));
+    }
+    
+    let opacity = opacity.unwrap_or(DEFAULT_WATERMARK_OPACITY);
+    if !(opacity > 0.0 && opacity <= 1.0) {
+        return Err(ConfigError::invalid_arg(format!("Watermark opacity must be in (0, 1]: {}", opacity)));
+    }
+    
+    Ok(WatermarkSettings { path: path.to_path_buf(), position, margin, scale, opacity })
+}
+
+/// Разбирает длину фрагмента и проверяет нижнюю границу
+fn parse_segment_length(value: &str) -> ConfigResult<f64> {
+    let seconds = parse_time_spec(value)
+        .ok_or_else(|| ConfigError::invalid_duration(value, "expected e.g. 60s, 1m30s or 00:01:00"))?;
+    
+    if seconds < MIN_SEGMENT_LENGTH {
+        return Err(ConfigError::invalid_duration(
+            value,
+            format!("segment length must be at least {}s", MIN_SEGMENT_LENGTH),
+        ));
+    }
+    
+    Ok(seconds)
+}
+
 /// Валидированные и нормализованные аргументы CLI
 #[derive(Debug, Clone)]
 pub struct ValidatedArgs {
@@ -107,10 +1056,73 @@ pub struct ValidatedArgs {
     
     /// Количество потоков для обработки
     pub threads: usize,
+    
+    /// Разрешение выходного видео
+    pub resolution: Resolution,
+    
+    /// Выходные форматы, которые рендерятся за один проход
+    pub variants: Vec<OutputVariant>,
+    
+    /// Стратегия нарезки входных файлов на шортсы
+    pub cut_strategy: CutStrategy,
+    
+    /// Что делать с исходниками без звуковой дорожки
+    pub missing_audio: MissingAudio,
+    
+    /// Выбор звуковой дорожки
+    pub audio: AudioSelection,
+    
+    /// Цель нормализации громкости (`--loudnorm`)
+    pub loudnorm: Option<LoudnessTarget>,
+    
+    /// Параметры музыкальной подложки (`--music`)
+    pub music: Option<MusicSettings>,
+    
+    /// Музыкальные треки, которые раздаются клипам по кругу
+    pub music_tracks: Vec<PathBuf>,
+    
+    /// Оформление вшитых субтитров
+    pub subtitle_style: SubtitleStyle,
+    
+    /// Команда распознавания речи (`--transcribe`)
+    pub transcriber: Option<CommandTranscriber>,
+    
+    /// Пресет пословных субтитров (`--karaoke`)
+    pub karaoke: Option<KaraokePreset>,
+    
+    /// Заголовок поверх кадра (`--title`)
+    pub title: Option<TitleSettings>,
+    
+    /// Водяной знак поверх кадра (`--watermark`)
+    pub watermark: Option<WatermarkSettings>,
+    
+    /// Фон вокруг горизонтального видео (`--background`)
+    pub background: BackgroundMode,
+    
+    /// Окно кадра следует за движением (`--reframe`)
+    pub reframe: bool,
+    
+    /// Поиск и обрезка черных полос (отключается `--no-autocrop`)
+    pub autocrop: bool,
+    
+    /// Заставка перед каждым клипом (`--intro`)
+    pub intro: Option<PathBuf>,
+    
+    /// Финальная карточка после каждого клипа (`--outro`)
+    pub outro: Option<PathBuf>,
+    
+    /// Разделенный экран (`--split-screen`)
+    pub split: Option<SplitSettings>,
+    
+    /// Список пар разделенного экрана (`--split-pairs`); без него пары собираются по именам файлов
+    pub split_pairs: Option<PairList>,
+    
+    /// Только показать план нарезки, ничего не рендеря
+    pub dry_run: bool,
 }
 
 impl ValidatedArgs {
-    /// Проверяет доступность FFmpeg в системе
+    /// Проверяет доступность FFmpeg и FFprobe в системе
     pub async fn check_ffmpeg_availability(&self) -> ConfigResult<()> {
         use tokio::process::Command;
         
@@ -124,9 +1136,53 @@ impl ValidatedArgs {
             return Err(ConfigError::FfmpegNotFound);
         }
         
+        // ffprobe нужен для анализа входных файлов перед обработкой
+        let output = Command::new(crate::config::FFPROBE_EXECUTABLE)
+            .args(crate::config::FFMPEG_VERSION_ARGS)
+            .output()
+            .await
+            .map_err(|_| ConfigError::FfprobeNotFound)?;
+        
+        if !output.status.success() {
+            return Err(ConfigError::FfprobeNotFound);
+        }
+        
+        // Битое изображение иначе обнаружится только посреди рендера каждой задачи
+        if let Some(watermark) = &self.watermark {
+            check_image_decodes(&watermark.path, "Watermark").await?;
+        }
+        if let BackgroundMode::Image(path) = &self.background {
+            check_image_decodes(path, "Background image").await?;
+        }
+        
         Ok(())
     }
     
+    /// Собирает параметры рендера для построения FFmpeg команд
+    pub fn render_settings(&self) -> RenderSettings {
+        RenderSettings {
+            variants: self.variants.clone(),
+            missing_audio: self.missing_audio,
+            loudnorm: self.loudnorm,
+            music: self.music,
+            subtitles: self.subtitle_style.clone(),
+            transcriber: self
+                .transcriber
+                .clone()
+                .map(|transcriber| Arc::new(transcriber) as Arc<dyn Transcriber>),
+            karaoke: self.karaoke,
+            title: self.title.clone(),
+            watermark: self.watermark.clone(),
+            // Заставки анализируются через ffprobe в `BrandClip::load`
+            intro: None,
+            outro: None,
+            background: self.background.clone(),
+            split: self.split,
+            reframe: self.reframe,
+            autocrop: self.autocrop,
+        }
+    }
+    
     /// Генерирует полный путь к лог-файлу
     pub fn log_file_path(&self) -> PathBuf {
         let log_filename = AppConfig::generate_log_filename();
@@ -139,6 +1195,107 @@ impl ValidatedArgs {
         println!("  Input directory:  {}", self.input.display());
         println!("  Output directory: {}", self.output.display());
         println!("  Threads:          {}", self.threads);
+        if self.variants.len() > 1 {
+            let formats: Vec<String> = self.variants
+                .iter()
+                .map(|v| format!("{} ({})", v.label, v.resolution))
+                .collect();
+            println!("  Formats:          {}", formats.join(", "));
+        } else {
+            println!("  Resolution:       {}", self.resolution);
+        }
+        match &self.cut_strategy {
+            CutStrategy::Whole => {}
+            CutStrategy::FixedLength { seconds } => println!("  Segment length:   {}s", seconds),
+            CutStrategy::Scenes(scene) => println!(
+                "  Scene cuts:       threshold {}, {}s-{}s per part",
+                scene.threshold, scene.min_length, scene.max_length
+            ),
+            CutStrategy::Silence(silence) => println!(
+                "  Silence:          {:?} pauses over {}s below {}dB",
+                silence.mode, silence.min_silence, silence.noise_db
+            ),
+            CutStrategy::Highlights(highlights) => println!(
+                "  Highlights:       {} loudest moments of {}s",
+                highlights.count, highlights.length
+            ),
+            CutStrategy::CutList(list) => println!(
+                "  Cut list:         {} ({} ranges)",
+                list.path.display(),
+                list.rows.len()
+            ),
+        }
+        if self.missing_audio != MissingAudio::default() {
+            println!("  Missing audio:    {:?}", self.missing_audio);
+        }
+        match &self.audio {
+            AudioSelection::First => {}
+            AudioSelection::Index(index) => println!("  Audio track:      #{}", index),
+            AudioSelection::Language(tag) => println!("  Audio track:      {}", tag),
+            AudioSelection::PerLanguage => println!("  Audio track:      one output per language"),
+        }
+        if let Some(music) = &self.music {
+            println!("  Music:            {} track(s), volume {}", self.music_tracks.len(), music.volume);
+        }
+        if self.subtitle_style != SubtitleStyle::default() {
+            let style = &self.subtitle_style;
+            println!(
+                "  Subtitles:        {} {}px, #{:06X} on #{:06X}, above bottom {}%",
+                style.font, style.size, style.color, style.outline_color, style.safe_zone * 100.0
+            );
+        }
+        if let Some(transcriber) = &self.transcriber {
+            println!("  Transcription:    {} (cached next to outputs)", transcriber.args[0]);
+        }
+        if let Some(preset) = &self.karaoke {
+            println!("  Karaoke captions: {} ({} words per line)", preset.name, preset.max_words_per_line);
+        }
+        if let Some(title) = &self.title {
+            let font = title.font_file.as_ref().map_or("default font".to_string(), |path| path.display().to_string());
+            println!("  Title:            \"{}\" {}px, {}", title.template, title.size, font);
+        }
+        if let Some(watermark) = &self.watermark {
+            println!(
+                "  Watermark:        {} ({:?}, {}% wide, opacity {})",
+                watermark.path.display(),
+                watermark.position,
+                watermark.scale * 100.0,
+                watermark.opacity
+            );
+        }
+        if self.background != BackgroundMode::default() {
+            println!("  Background:       {}", self.background);
+        }
+        if self.reframe {
+            println!("  Reframe:          crop follows the motion");
+        }
+        if !self.autocrop {
+            println!("  Black bars:       kept");
+        }
+        if let Some(path) = &self.intro {
+            println!("  Intro:            {}", path.display());
+        }
+        if let Some(path) = &self.outro {
+            println!("  Outro:            {}", path.display());
+        }
+        if let Some(split) = &self.split {
+            let pairs = match &self.split_pairs {
+                Some(list) => format!("{} ({} pairs)", list.path.display(), list.rows.len()),
+                None => "NAME.top / NAME.bottom files".to_string(),
+            };
+            println!(
+                "  Split screen:     top {}%, {:?} audio, pairs from {}",
+                split.ratio * 100.0,
+                split.audio,
+                pairs
+            );
+        }
+        if let Some(target) = &self.loudnorm {
+            println!("  Loudness:         {} LUFS, true peak {} dBTP", target.integrated, target.true_peak);
+        }
+        if self.dry_run {
+            println!("  Dry run:          clips are listed, not rendered");
+        }
         println!("  Log file:         {}", self.log_file_path().display());
         println!();
     }
@@ -160,6 +1317,7 @@ mod tests {
             input: temp_input.path().to_path_buf(),
             output: temp_output.path().to_path_buf(),
             threads: Some(0),
+            ..Default::default()
         };
         
         assert!(args.validate_and_normalize().is_err());
@@ -169,6 +1327,7 @@ mod tests {
             input: temp_input.path().to_path_buf(),
             output: temp_output.path().to_path_buf(),
             threads: Some(crate::config::MAX_THREADS + 1),
+            ..Default::default()
         };
         
         assert!(args.validate_and_normalize().is_err());
@@ -183,6 +1342,7 @@ mod tests {
             input: nonexistent_input,
             output: temp_output.path().to_path_buf(),
             threads: Some(1),
+            ..Default::default()
         };
         
         assert!(args.validate_and_normalize().is_err());
@@ -197,6 +1357,7 @@ mod tests {
             input: temp_input.path().to_path_buf(),
             output: temp_output.path().to_path_buf(),
             threads: Some(2),
+            ..Default::default()
         };
         
         let result = args.validate_and_normalize();
@@ -204,7 +1365,499 @@ mod tests {
         
         let validated = result.unwrap();
         assert_eq!(validated.threads, 2);
+        assert_eq!(validated.resolution, DEFAULT_RESOLUTION);
         assert!(validated.input.is_absolute());
         assert!(validated.output.is_absolute());
     }
+    
+    /// Валидирует аргументы с временными входной и выходной директориями и одним потоком
+    fn validate_with(args: CliArgs) -> ConfigResult<ValidatedArgs> {
+        let temp_input = TempDir::new().unwrap();
+        let temp_output = TempDir::new().unwrap();
+        
+        CliArgs {
+            input: temp_input.path().to_path_buf(),
+            output: temp_output.path().to_path_buf(),
+            threads: Some(1),
+            ..args
+        }
+        .validate_and_normalize()
+    }
+    
+    #[test]
+    fn test_resolution_and_aspect_options() {
+        let resolution = |value: &str| CliArgs { resolution: Some(value.to_string()), ..Default::default() };
+        let aspect = |value: &str| CliArgs { aspect: Some(value.to_string()), ..Default::default() };
+        
+        assert_eq!(validate_with(resolution("1080x1920")).unwrap().resolution, Resolution::new(1080, 1920));
+        assert_eq!(validate_with(aspect("9:16")).unwrap().resolution, Resolution::new(720, 1280));
+        assert_eq!(validate_with(aspect("4:5")).unwrap().resolution, Resolution::new(720, 900));
+        assert_eq!(validate_with(aspect("1:1")).unwrap().resolution, Resolution::new(720, 720));
+        
+        // Нечетные размеры и выход за пределы
+        assert!(matches!(validate_with(resolution("1081x1920")), Err(ConfigError::InvalidResolution { .. })));
+        assert!(matches!(validate_with(resolution("64x64")), Err(ConfigError::InvalidResolution { .. })));
+        assert!(matches!(validate_with(resolution("8192x8192")), Err(ConfigError::InvalidResolution { .. })));
+        assert!(matches!(validate_with(resolution("1080by1920")), Err(ConfigError::InvalidResolution { .. })));
+        
+        // Некорректные соотношения сторон
+        assert!(matches!(validate_with(aspect("0:16")), Err(ConfigError::InvalidAspectRatio { .. })));
+        assert!(matches!(validate_with(aspect("1:10")), Err(ConfigError::InvalidAspectRatio { .. })));
+        assert!(matches!(validate_with(aspect("wide")), Err(ConfigError::InvalidAspectRatio { .. })));
+        
+        assert!(validate_with(CliArgs { aspect: Some("9:16".to_string()), ..resolution("1080x1920") }).is_err());
+    }
+    
+    #[test]
+    fn test_multiple_output_formats() {
+        let formats = |values: &[&str]| CliArgs { formats: values.iter().map(|f| f.to_string()).collect(), ..Default::default() };
+        
+        let single = validate_with(CliArgs::default()).unwrap().variants;
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].resolution, DEFAULT_RESOLUTION);
+        
+        let variants = validate_with(CliArgs {
+            resolution: Some("1080x1920".to_string()),
+            ..formats(&["9:16", "1:1", "4:5"])
+        })
+        .unwrap()
+        .variants;
+        let labels: Vec<&str> = variants.iter().map(|v| v.label.as_str()).collect();
+        assert_eq!(labels, vec!["9x16", "1x1", "4x5"]);
+        assert_eq!(variants[0].resolution, Resolution::new(1080, 1920));
+        assert_eq!(variants[1].resolution, Resolution::new(1080, 1080));
+        assert_eq!(variants[2].resolution, Resolution::new(1080, 1350));
+        
+        let explicit = validate_with(formats(&["1080x1920", "720x720"])).unwrap().variants;
+        assert_eq!(explicit[1].label, "720x720");
+        
+        assert!(validate_with(formats(&["9:16", "9:16"])).is_err());
+        assert!(validate_with(formats(&["9:16", "720x1280"])).is_err());
+        assert!(validate_with(formats(&["9:16", "3x3"])).is_err());
+    }
+    
+    #[test]
+    fn test_segment_length_option() {
+        let segment = |value: &str| CliArgs { segment_length: Some(value.to_string()), ..Default::default() };
+        
+        assert_eq!(validate_with(CliArgs::default()).unwrap().cut_strategy, CutStrategy::Whole);
+        assert_eq!(validate_with(segment("60s")).unwrap().cut_strategy, CutStrategy::FixedLength { seconds: 60.0 });
+        assert_eq!(validate_with(segment("1m30s")).unwrap().cut_strategy, CutStrategy::FixedLength { seconds: 90.0 });
+        assert_eq!(validate_with(segment("00:02:00")).unwrap().cut_strategy, CutStrategy::FixedLength { seconds: 120.0 });
+        
+        assert!(matches!(validate_with(segment("1s")), Err(ConfigError::InvalidDuration { .. })));
+        assert!(matches!(validate_with(segment("soon")), Err(ConfigError::InvalidDuration { .. })));
+    }
+    
+    #[test]
+    fn test_scene_cut_options() {
+        let scenes = CliArgs { scene_cuts: true, ..Default::default() };
+        
+        assert_eq!(
+            validate_with(scenes.clone()).unwrap().cut_strategy,
+            CutStrategy::Scenes(SceneCutSettings {
+                threshold: DEFAULT_SCENE_THRESHOLD,
+                min_length: DEFAULT_SCENE_MIN_LENGTH,
+                max_length: DEFAULT_SCENE_MAX_LENGTH,
+            })
+        );
+        let tuned = CliArgs {
+            scene_threshold: Some(0.4),
+            min_segment: Some("20s".to_string()),
+            max_segment: Some("1m30s".to_string()),
+            ..scenes.clone()
+        };
+        assert_eq!(
+            validate_with(tuned).unwrap().cut_strategy,
+            CutStrategy::Scenes(SceneCutSettings { threshold: 0.4, min_length: 20.0, max_length: 90.0 })
+        );
+        
+        assert!(validate_with(CliArgs { scene_threshold: Some(1.5), ..scenes.clone() }).is_err());
+        assert!(validate_with(CliArgs { scene_threshold: Some(0.0), ..scenes.clone() }).is_err());
+        assert!(matches!(
+            validate_with(CliArgs {
+                min_segment: Some("90s".to_string()),
+                max_segment: Some("60s".to_string()),
+                ..scenes.clone()
+            }),
+            Err(ConfigError::InvalidDuration { .. })
+        ));
+        assert!(validate_with(CliArgs { scene_threshold: Some(0.3), ..Default::default() }).is_err());
+        assert!(validate_with(CliArgs { segment_length: Some("60s".to_string()), ..scenes }).is_err());
+    }
+    
+    #[test]
+    fn test_cut_list_option() {
+        let temp_dir = TempDir::new().unwrap();
+        let cuts_path = temp_dir.path().join("cuts.csv");
+        fs::write(&cuts_path, "file,start,end,title\ntalk.mp4,0,30,Intro\ntalk.mp4,x,40\n").unwrap();
+        
+        let cuts = |path: PathBuf| CliArgs { cuts: Some(path), ..Default::default() };
+        
+        match validate_with(cuts(cuts_path.clone())).unwrap().cut_strategy {
+            CutStrategy::CutList(list) => {
+                assert_eq!(list.rows.len(), 1);
+                assert_eq!(list.errors.len(), 1);
+            }
+            other => panic!("unexpected strategy: {:?}", other),
+        }
+        
+        assert!(validate_with(cuts(temp_dir.path().join("missing.csv"))).is_err());
+        
+        let json_path = temp_dir.path().join("cuts.json");
+        fs::write(&json_path, "not json").unwrap();
+        assert!(matches!(
+            validate_with(cuts(json_path)),
+            Err(ConfigError::CutList(crate::error::ManifestError::Malformed { .. }))
+        ));
+        
+        assert!(validate_with(CliArgs { segment_length: Some("60s".to_string()), ..cuts(cuts_path) }).is_err());
+    }
+    
+    #[test]
+    fn test_silence_options() {
+        let silence = |mode: &str| CliArgs { silence: Some(mode.to_string()), ..Default::default() };
+        
+        assert_eq!(
+            validate_with(silence("trim")).unwrap().cut_strategy,
+            CutStrategy::Silence(SilenceCutSettings {
+                mode: SilenceMode::Trim,
+                noise_db: DEFAULT_SILENCE_NOISE_DB,
+                min_silence: DEFAULT_SILENCE_DURATION,
+            })
+        );
+        let tuned = CliArgs {
+            silence_noise: Some("-35dB".to_string()),
+            silence_duration: Some("2.5s".to_string()),
+            ..silence("Split")
+        };
+        assert_eq!(
+            validate_with(tuned).unwrap().cut_strategy,
+            CutStrategy::Silence(SilenceCutSettings { mode: SilenceMode::Split, noise_db: -35.0, min_silence: 2.5 })
+        );
+        
+        assert!(validate_with(silence("mute")).is_err());
+        assert!(validate_with(CliArgs { silence_noise: Some("10dB".to_string()), ..silence("trim") }).is_err());
+        assert!(validate_with(CliArgs { silence_noise: Some("loud".to_string()), ..silence("trim") }).is_err());
+        assert!(matches!(
+            validate_with(CliArgs { silence_duration: Some("0".to_string()), ..silence("split") }),
+            Err(ConfigError::InvalidDuration { .. })
+        ));
+        assert!(validate_with(CliArgs { silence_noise: Some("-30dB".to_string()), ..Default::default() }).is_err());
+    }
+    
+    #[test]
+    fn test_highlight_options() {
+        let highlights = |count: usize| CliArgs { highlights: Some(count), ..Default::default() };
+        
+        assert_eq!(
+            validate_with(highlights(5)).unwrap().cut_strategy,
+            CutStrategy::Highlights(HighlightSettings { count: 5, length: DEFAULT_HIGHLIGHT_LENGTH })
+        );
+        assert_eq!(
+            validate_with(CliArgs { highlight_length: Some("45s".to_string()), ..highlights(3) }).unwrap().cut_strategy,
+            CutStrategy::Highlights(HighlightSettings { count: 3, length: 45.0 })
+        );
+        
+        assert!(validate_with(highlights(0)).is_err());
+        assert!(validate_with(highlights(MAX_HIGHLIGHTS + 1)).is_err());
+        assert!(matches!(
+            validate_with(CliArgs { highlight_length: Some("2s".to_string()), ..highlights(3) }),
+            Err(ConfigError::InvalidDuration { .. })
+        ));
+        assert!(validate_with(CliArgs { highlight_length: Some("30s".to_string()), ..Default::default() }).is_err());
+    }
+    
+    #[test]
+    fn test_missing_audio_option() {
+        let missing_audio = |mode: &str| CliArgs { missing_audio: Some(mode.to_string()), ..Default::default() };
+        
+        assert_eq!(validate_with(CliArgs::default()).unwrap().render_settings().missing_audio, MissingAudio::Silence);
+        assert_eq!(validate_with(missing_audio("omit")).unwrap().render_settings().missing_audio, MissingAudio::Omit);
+        assert_eq!(validate_with(missing_audio("ERROR")).unwrap().render_settings().missing_audio, MissingAudio::Error);
+        assert!(validate_with(missing_audio("mute")).is_err());
+    }
+    
+    #[test]
+    fn test_loudnorm_options() {
+        let loudnorm = CliArgs { loudnorm: true, ..Default::default() };
+        
+        assert_eq!(validate_with(CliArgs::default()).unwrap().render_settings().loudnorm, None);
+        
+        let target = validate_with(loudnorm.clone()).unwrap().render_settings().loudnorm.unwrap();
+        assert_eq!((target.integrated, target.true_peak), (DEFAULT_TARGET_LUFS, DEFAULT_TRUE_PEAK));
+        
+        let tuned = CliArgs { target_lufs: Some(-16.0), true_peak: Some(-1.5), ..loudnorm.clone() };
+        let target = validate_with(tuned).unwrap().render_settings().loudnorm.unwrap();
+        assert_eq!((target.integrated, target.true_peak), (-16.0, -1.5));
+        
+        assert!(validate_with(CliArgs { target_lufs: Some(-2.0), ..loudnorm.clone() }).is_err());
+        assert!(validate_with(CliArgs { true_peak: Some(1.0), ..loudnorm }).is_err());
+        assert!(validate_with(CliArgs { target_lufs: Some(-14.0), ..Default::default() }).is_err());
+    }
+    
+    #[test]
+    fn test_music_option() {
+        let music_dir = TempDir::new().unwrap();
+        fs::write(music_dir.path().join("b.mp3"), b"").unwrap();
+        fs::write(music_dir.path().join("a.wav"), b"").unwrap();
+        let empty_dir = TempDir::new().unwrap();
+        
+        let music = |path: &Path| CliArgs { music: Some(path.to_path_buf()), ..Default::default() };
+        
+        let validated = validate_with(music(music_dir.path())).unwrap();
+        assert_eq!(validated.music_tracks.len(), 2);
+        assert!(validated.music_tracks[0].ends_with("a.wav"));
+        assert_eq!(validated.render_settings().music.unwrap().volume, DEFAULT_MUSIC_VOLUME);
+        
+        let track = music_dir.path().join("b.mp3");
+        let validated = validate_with(CliArgs { music_volume: Some(0.5), ..music(&track) }).unwrap();
+        assert_eq!(validated.music_tracks, vec![track.clone()]);
+        assert_eq!(validated.music.unwrap().volume, 0.5);
+        
+        assert!(validate_with(music(empty_dir.path())).is_err());
+        assert!(validate_with(music(Path::new("/nonexistent/music.mp3"))).is_err());
+        assert!(validate_with(CliArgs { music_volume: Some(1.5), ..music(&track) }).is_err());
+        assert!(validate_with(CliArgs { music_volume: Some(0.5), ..Default::default() }).is_err());
+    }
+    
+    #[test]
+    fn test_audio_track_options() {
+        let track = |value: &str| CliArgs { audio_track: Some(value.to_string()), ..Default::default() };
+        let per_language = CliArgs { per_language: true, ..Default::default() };
+        
+        assert_eq!(validate_with(CliArgs::default()).unwrap().audio, AudioSelection::First);
+        assert_eq!(validate_with(track("1")).unwrap().audio, AudioSelection::Index(1));
+        assert_eq!(validate_with(track("ENG")).unwrap().audio, AudioSelection::Language("eng".to_string()));
+        assert_eq!(validate_with(per_language.clone()).unwrap().audio, AudioSelection::PerLanguage);
+        
+        assert!(validate_with(track("en gb")).is_err());
+        assert!(validate_with(CliArgs { audio_track: Some("eng".to_string()), ..per_language }).is_err());
+    }
+    
+    #[test]
+    fn test_subtitle_style_options() {
+        assert_eq!(validate_with(CliArgs::default()).unwrap().render_settings().subtitles, SubtitleStyle::default());
+        
+        let style = validate_with(CliArgs {
+            subtitle_font: Some("Montserrat Black".to_string()),
+            subtitle_size: Some(80.0),
+            subtitle_color: Some("#ffcc00".to_string()),
+            subtitle_outline_color: Some("102030".to_string()),
+            subtitle_outline: Some(0.0),
+            subtitle_margin: Some(25.0),
+            ..Default::default()
+        })
+        .unwrap()
+        .render_settings()
+        .subtitles;
+        assert_eq!(style.font, "Montserrat Black");
+        assert_eq!(style.size, 80.0);
+        assert_eq!(style.color, 0xFFCC00);
+        assert_eq!(style.outline_color, 0x102030);
+        assert_eq!(style.outline, 0.0);
+        assert_eq!(style.safe_zone, 0.25);
+        
+        let yellow = validate_with(CliArgs { subtitle_color: Some("Yellow".to_string()), ..Default::default() });
+        assert_eq!(yellow.unwrap().render_settings().subtitles.color, 0xFFFF00);
+        
+        assert!(validate_with(CliArgs { subtitle_color: Some("#fffff".to_string()), ..Default::default() }).is_err());
+        assert!(validate_with(CliArgs { subtitle_outline_color: Some("navy".to_string()), ..Default::default() }).is_err());
+        assert!(validate_with(CliArgs { subtitle_size: Some(0.0), ..Default::default() }).is_err());
+        assert!(validate_with(CliArgs { subtitle_margin: Some(60.0), ..Default::default() }).is_err());
+        assert!(validate_with(CliArgs { subtitle_font: Some("A,B".to_string()), ..Default::default() }).is_err());
+    }
+    
+    #[test]
+    fn test_title_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let font = temp_dir.path().join("Bold.ttf");
+        fs::write(&font, b"font").unwrap();
+        
+        assert_eq!(validate_with(CliArgs::default()).unwrap().render_settings().title, None);
+        
+        let title = validate_with(CliArgs {
+            title: Some("{title} | {part}".to_string()),
+            title_font: Some(font.clone()),
+            title_size: Some(90.0),
+            ..Default::default()
+        })
+        .unwrap()
+        .render_settings()
+        .title
+        .unwrap();
+        assert_eq!(title.template, "{title} | {part}");
+        assert_eq!(title.font_file, Some(font));
+        assert_eq!(title.size, 90.0);
+        
+        let default_size = validate_with(CliArgs { title: Some("{stem}".to_string()), ..Default::default() });
+        assert_eq!(default_size.unwrap().render_settings().title.unwrap().size, DEFAULT_TITLE_SIZE);
+        
+        assert!(validate_with(CliArgs { title: Some("{name}".to_string()), ..Default::default() }).is_err());
+        assert!(validate_with(CliArgs { title: Some(" ".to_string()), ..Default::default() }).is_err());
+        assert!(validate_with(CliArgs {
+            title: Some("{stem}".to_string()),
+            title_font: Some(temp_dir.path().join("missing.ttf")),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(validate_with(CliArgs { title: Some("{stem}".to_string()), title_size: Some(0.0), ..Default::default() }).is_err());
+        assert!(validate_with(CliArgs { title_size: Some(60.0), ..Default::default() }).is_err());
+    }
+    
+    #[test]
+    fn test_background_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let image = temp_dir.path().join("bg.gif");
+        fs::write(&image, "image").unwrap();
+        let video = temp_dir.path().join("loop.mp4");
+        fs::write(&video, "video").unwrap();
+        
+        let background = |value: &str| CliArgs { background: Some(value.to_string()), ..Default::default() };
+        let mode = |value: &str| validate_with(background(value)).unwrap().render_settings().background;
+        
+        assert_eq!(mode("Mirror"), BackgroundMode::Mirror);
+        assert_eq!(mode("dominant"), BackgroundMode::Dominant);
+        assert_eq!(mode("color:#102030"), BackgroundMode::Color(0x102030));
+        assert_eq!(mode("gradient"), BackgroundMode::Gradient(0x303048, 0x101018));
+        assert_eq!(mode("gradient:red:black"), BackgroundMode::Gradient(0xFF0000, 0x000000));
+        assert_eq!(mode(&format!("image:{}", image.display())), BackgroundMode::Image(image.clone()));
+        assert_eq!(mode(&format!("video:{}", video.display())), BackgroundMode::Video(video.clone()));
+        
+        assert!(validate_with(background("stripes")).is_err());
+        assert!(validate_with(background("color")).is_err());
+        assert!(validate_with(background("gradient:red")).is_err());
+        assert!(validate_with(background("mirror:yes")).is_err());
+        // Файлы фона должны существовать; картинку декодирует FFmpeg при запуске
+        assert!(validate_with(background(&format!("image:{}", temp_dir.path().join("missing.png").display()))).is_err());
+        assert!(validate_with(background(&format!("video:{}", temp_dir.path().join("missing.mp4").display()))).is_err());
+    }
+    
+    #[test]
+    fn test_reframe_and_autocrop_options() {
+        let reframe = CliArgs { reframe: true, ..Default::default() };
+        
+        let settings = validate_with(reframe.clone()).unwrap().render_settings();
+        assert!(settings.reframe);
+        assert!(settings.autocrop);
+        assert!(!validate_with(CliArgs { no_autocrop: true, ..reframe.clone() }).unwrap().render_settings().autocrop);
+        assert!(validate_with(CliArgs { background: Some("mirror".to_string()), ..reframe.clone() }).is_err());
+        assert!(validate_with(CliArgs { split_screen: true, ..reframe }).is_err());
+    }
+    
+    #[test]
+    fn test_split_screen_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let pairs = temp_dir.path().join("pairs.csv");
+        fs::write(&pairs, "top,bottom\ncam.mp4,game.mp4\n").unwrap();
+        
+        let split = CliArgs { split_screen: true, ..Default::default() };
+        
+        let validated = validate_with(split.clone()).unwrap();
+        assert_eq!(validated.render_settings().split, Some(SplitSettings { ratio: 0.5, audio: SplitAudio::Top }));
+        assert!(validated.split_pairs.is_none());
+        
+        let validated = validate_with(CliArgs {
+            split_pairs: Some(pairs.clone()),
+            split_ratio: Some(40.0),
+            split_audio: Some("Mix".to_string()),
+            ..split.clone()
+        })
+        .unwrap();
+        assert_eq!(validated.split, Some(SplitSettings { ratio: 0.4, audio: SplitAudio::Mix }));
+        assert_eq!(validated.split_pairs.unwrap().rows.len(), 1);
+        
+        assert!(validate_with(CliArgs { split_ratio: Some(40.0), ..Default::default() }).is_err());
+        assert!(validate_with(CliArgs { split_ratio: Some(95.0), ..split.clone() }).is_err());
+        assert!(validate_with(CliArgs { split_audio: Some("left".to_string()), ..split.clone() }).is_err());
+        assert!(validate_with(CliArgs { audio_track: Some("1".to_string()), ..split.clone() }).is_ok());
+        for split_audio in ["bottom", "mix"] {
+            assert!(validate_with(CliArgs {
+                split_audio: Some(split_audio.to_string()),
+                audio_track: Some("eng".to_string()),
+                ..split.clone()
+            })
+            .is_err());
+        }
+        assert!(validate_with(CliArgs { background: Some("mirror".to_string()), ..split.clone() }).is_err());
+        assert!(validate_with(CliArgs { split_pairs: Some(temp_dir.path().join("missing.csv")), ..split }).is_err());
+    }
+    
+    #[test]
+    fn test_intro_outro_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let intro = temp_dir.path().join("sting.mp4");
+        fs::write(&intro, "video").unwrap();
+        
+        let validated = validate_with(CliArgs { intro: Some(intro.clone()), ..Default::default() }).unwrap();
+        assert_eq!(validated.intro, Some(intro));
+        assert_eq!(validated.outro, None);
+        // Клипы анализируются ffprobe уже после валидации
+        assert!(validated.render_settings().intro.is_none());
+        
+        assert!(validate_with(CliArgs { outro: Some(temp_dir.path().join("missing.mp4")), ..Default::default() }).is_err());
+        assert!(validate_with(CliArgs { intro: Some(temp_dir.path().to_path_buf()), ..Default::default() }).is_err());
+    }
+    
+    #[test]
+    fn test_watermark_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let logo = temp_dir.path().join("logo.png");
+        fs::write(&logo, "image").unwrap();
+        
+        let with_logo = CliArgs { watermark: Some(logo.clone()), ..Default::default() };
+        
+        let watermark = validate_with(with_logo.clone()).unwrap().render_settings().watermark.unwrap();
+        assert_eq!(watermark.position, WatermarkPosition::TopRight);
+        assert_eq!(
+            (watermark.margin, watermark.scale, watermark.opacity),
+            (DEFAULT_WATERMARK_MARGIN, DEFAULT_WATERMARK_SCALE, DEFAULT_WATERMARK_OPACITY)
+        );
+        
+        let watermark = validate_with(CliArgs {
+            watermark_position: Some("Bottom-Left".to_string()),
+            watermark_margin: Some(2.0),
+            watermark_scale: Some(25.0),
+            watermark_opacity: Some(1.0),
+            ..with_logo.clone()
+        })
+        .unwrap()
+        .render_settings()
+        .watermark
+        .unwrap();
+        assert_eq!(watermark.position, WatermarkPosition::BottomLeft);
+        assert_eq!((watermark.margin, watermark.scale, watermark.opacity), (0.02, 0.25, 1.0));
+        
+        // Файл должен существовать; декодируемость проверяет FFmpeg при запуске
+        assert!(validate_with(CliArgs { watermark: Some(temp_dir.path().join("missing.png")), ..Default::default() }).is_err());
+        
+        assert!(validate_with(CliArgs { watermark_position: Some("center".to_string()), ..with_logo.clone() }).is_err());
+        assert!(validate_with(CliArgs { watermark_margin: Some(30.0), ..with_logo.clone() }).is_err());
+        assert!(validate_with(CliArgs { watermark_scale: Some(0.0), ..with_logo.clone() }).is_err());
+        assert!(validate_with(CliArgs { watermark_opacity: Some(1.5), ..with_logo }).is_err());
+        assert!(validate_with(CliArgs { watermark_opacity: Some(0.5), ..Default::default() }).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_image_decode_check() {
+        let temp_dir = TempDir::new().unwrap();
+        let fake = temp_dir.path().join("fake.png");
+        fs::write(&fake, "not an image").unwrap();
+        
+        // Без FFmpeg проверка тоже не проходит: файл не считается изображением без декодирования
+        assert!(check_image_decodes(&fake, "Watermark").await.is_err());
+        
+        // Фон-картинка проходит разбор по существованию файла и отклоняется той же проверкой
+        let background = background_mode(&format!("image:{}", fake.display())).unwrap();
+        assert_eq!(background, BackgroundMode::Image(fake.clone()));
+        assert!(check_image_decodes(&fake, "Background image").await.is_err());
+        
+        // Предупреждение о битых данных при нулевом коде выхода - тоже отказ
+        assert_eq!(decode_failure(true, b""), None);
+        assert_eq!(
+            decode_failure(true, b"[png @ 0x1] inflate returned error -5\n\n").as_deref(),
+            Some("[png @ 0x1] inflate returned error -5")
+        );
+        assert_eq!(decode_failure(false, b"").as_deref(), Some("unknown error"));
+    }
 }
@@ -1,10 +1,12 @@
+use std::path::PathBuf;
 use std::time::Duration;
+use crate::error::{ConfigError, ConfigResult};
 
 /// Центральная конфигурация приложения
 #[derive(Debug, Clone)]
 pub struct AppConfig {
-    /// Фильтр для FFmpeg (захардкожен)
-    pub ffmpeg_filter_complex: String,
+    /// Разрешение выходного видео
+    pub output_resolution: Resolution,
     
     /// Поддерживаемые расширения входных файлов
     pub supported_extensions: Vec<String>,
@@ -31,7 +33,7 @@ pub struct AppConfig {
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
-            ffmpeg_filter_complex: FFMPEG_FILTER_COMPLEX.to_string(),
+            output_resolution: DEFAULT_RESOLUTION,
             supported_extensions: vec!["mp4".to_string()],
             output_suffix: OUTPUT_SUFFIX.to_string(),
             ffmpeg_timeout: FFMPEG_TIMEOUT,
@@ -63,11 +65,9 @@ impl AppConfig {
             ));
         }
         
-        if self.ffmpeg_filter_complex.is_empty() {
-            return Err(crate::error::ConfigError::invalid_arg(
-                "FFmpeg filter complex cannot be empty"
-            ));
-        }
+        self.output_resolution.validate().map_err(|reason| {
+            crate::error::ConfigError::invalid_resolution(self.output_resolution.to_string(), reason)
+        })?;
         
         Ok(())
     }
@@ -86,11 +86,892 @@ impl AppConfig {
     }
 }
 
+/// Размер кадра в пикселях
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Resolution {
+    /// Создает новое разрешение
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+    
+    /// Соотношение сторон (ширина / высота)
+    pub fn aspect_ratio(&self) -> f64 {
+        self.width as f64 / self.height as f64
+    }
+    
+    /// Проверяет, является ли кадр вертикальным
+    pub fn is_portrait(&self) -> bool {
+        self.height > self.width
+    }
+    
+    /// Разбирает разрешение вида `1080x1920` и проверяет его пригодность для вывода
+    pub fn parse(value: &str) -> ConfigResult<Self> {
+        let (width, height) = value
+            .trim()
+            .split_once(['x', 'X'])
+            .ok_or_else(|| ConfigError::invalid_resolution(value, "expected WIDTHxHEIGHT, e.g. 1080x1920"))?;
+        
+        let width: u32 = width.trim().parse()
+            .map_err(|_| ConfigError::invalid_resolution(value, "width is not a number"))?;
+        let height: u32 = height.trim().parse()
+            .map_err(|_| ConfigError::invalid_resolution(value, "height is not a number"))?;
+        
+        let resolution = Self::new(width, height);
+        resolution.validate().map_err(|reason| ConfigError::invalid_resolution(value, reason))?;
+        Ok(resolution)
+    }
+    
+    /// Проверяет четность и допустимые пределы размеров кадра
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, size) in [("width", self.width), ("height", self.height)] {
+            if !(MIN_OUTPUT_DIMENSION..=MAX_OUTPUT_DIMENSION).contains(&size) {
+                return Err(format!(
+                    "{} must be between {} and {}",
+                    name, MIN_OUTPUT_DIMENSION, MAX_OUTPUT_DIMENSION
+                ));
+            }
+            if size % 2 != 0 {
+                return Err(format!("{} must be even", name));
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Прямоугольник кадра исходника, который остается после обрезки черных полос
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CropRect {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl CropRect {
+    /// Размер кадра после обрезки
+    pub fn resolution(&self) -> Resolution {
+        Resolution::new(self.width, self.height)
+    }
+    
+    /// Проверяет, что прямоугольник целиком лежит в кадре исходника
+    pub fn fits(&self, source: Resolution) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self.x.checked_add(self.width).is_some_and(|right| right <= source.width)
+            && self.y.checked_add(self.height).is_some_and(|bottom| bottom <= source.height)
+    }
+}
+
+/// Обрезка черных полос исходника: найденная `cropdetect` или заданная в списке нарезки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceCrop {
+    /// Кадр остается целиком
+    Full,
+    Rect(CropRect),
+}
+
+impl SourceCrop {
+    /// Разбирает обрезку из списка нарезки: `W:H:X:Y` или `none` (полосы не срезаются)
+    ///
+    /// Все значения четные, как у `cropdetect` с `round=2`: иначе сдвигается цветность yuv420p.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("none") {
+            return Ok(Self::Full);
+        }
+        
+        let expected = || format!("expected W:H:X:Y or none, got '{}'", value);
+        let numbers: Vec<u32> = value
+            .split(':')
+            .map(|part| part.trim().parse().map_err(|_| expected()))
+            .collect::<Result<_, _>>()?;
+        match numbers[..] {
+            [width, height, ..] if width == 0 || height == 0 => Err(format!("empty crop '{}'", value)),
+            [width, height, x, y] if [width, height, x, y].iter().all(|number| number % 2 == 0) => {
+                Ok(Self::Rect(CropRect { width, height, x, y }))
+            }
+            [_, _, _, _] => Err(format!("crop values must be even, got '{}'", value)),
+            _ => Err(expected()),
+        }
+    }
+    
+    /// Прямоугольник обрезки; `None` - кадр целиком
+    pub fn rect(&self) -> Option<CropRect> {
+        match self {
+            Self::Full => None,
+            Self::Rect(rect) => Some(*rect),
+        }
+    }
+}
+
+/// Соотношение сторон вида `9:16`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AspectRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AspectRatio {
+    /// Разбирает соотношение сторон вида `9:16`
+    pub fn parse(value: &str) -> ConfigResult<Self> {
+        let (width, height) = value
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| ConfigError::invalid_aspect(value, "expected W:H, e.g. 9:16"))?;
+        
+        let width: u32 = width.trim().parse()
+            .map_err(|_| ConfigError::invalid_aspect(value, "width is not a number"))?;
+        let height: u32 = height.trim().parse()
+            .map_err(|_| ConfigError::invalid_aspect(value, "height is not a number"))?;
+        
+        if width == 0 || height == 0 {
+            return Err(ConfigError::invalid_aspect(value, "both parts must be greater than zero"));
+        }
+        
+        let ratio = width as f64 / height as f64;
+        if !(1.0 / MAX_ASPECT_RATIO..=MAX_ASPECT_RATIO).contains(&ratio) {
+            return Err(ConfigError::invalid_aspect(
+                value,
+                format!("ratio must be between 1:{} and {}:1", MAX_ASPECT_RATIO, MAX_ASPECT_RATIO),
+            ));
+        }
+        
+        Ok(Self { width, height })
+    }
+    
+    /// Строит разрешение с этим соотношением сторон
+    ///
+    /// Короткая сторона берется из `base`, длинная вычисляется и округляется до четного.
+    pub fn to_resolution(self, base: Resolution) -> ConfigResult<Resolution> {
+        let short_side = base.width.min(base.height) as f64;
+        let ratio = self.width as f64 / self.height as f64;
+        
+        let (width, height) = if ratio <= 1.0 {
+            (short_side, short_side / ratio)
+        } else {
+            (short_side * ratio, short_side)
+        };
+        
+        let resolution = Resolution::new(
+            ((width / 2.0).round() as u32) * 2,
+            ((height / 2.0).round() as u32) * 2,
+        );
+        resolution.validate().map_err(|reason| ConfigError::invalid_aspect(self.to_string(), reason))?;
+        Ok(resolution)
+    }
+}
+
+/// Выходной формат: одно из нескольких представлений одного и того же клипа
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputVariant {
+    /// Метка формата для суффикса имени файла, например `9x16`
+    pub label: String,
+    pub resolution: Resolution,
+}
+
+impl OutputVariant {
+    /// Разбирает формат вида `9:16` (соотношение сторон) или `1080x1920` (разрешение)
+    ///
+    /// Соотношения сторон переводятся в разрешение с короткой стороной из `base`.
+    pub fn parse(spec: &str, base: Resolution) -> ConfigResult<Self> {
+        let spec = spec.trim();
+        
+        if spec.contains(':') {
+            let aspect = AspectRatio::parse(spec)?;
+            Ok(Self {
+                label: format!("{}x{}", aspect.width, aspect.height),
+                resolution: aspect.to_resolution(base)?,
+            })
+        } else {
+            let resolution = Resolution::parse(spec)?;
+            Ok(Self {
+                label: resolution.to_string(),
+                resolution,
+            })
+        }
+    }
+}
+
+/// Что делать с исходником без звуковой дорожки
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingAudio {
+    /// Добавить тихую дорожку из `anullsrc` - платформы, требующие звук, примут файл
+    #[default]
+    Silence,
+    /// Собрать выходной файл без звука
+    Omit,
+    /// Считать такой исходник ошибкой
+    Error,
+}
+
+impl MissingAudio {
+    /// Разбирает значение `--missing-audio` (`silence`, `omit` или `error`)
+    pub fn parse(value: &str) -> ConfigResult<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "silence" => Ok(Self::Silence),
+            "omit" => Ok(Self::Omit),
+            "error" => Ok(Self::Error),
+            _ => Err(ConfigError::invalid_arg(format!(
+                "Unknown missing audio mode '{}': expected 'silence', 'omit' or 'error'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Фон вокруг горизонтального видео в вертикальном кадре (`--background`)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum BackgroundMode {
+    /// Размытое и увеличенное видео
+    #[default]
+    Blur,
+    /// Заливка цветом 0xRRGGBB
+    Color(u32),
+    /// Заливка преобладающим цветом кадра-пробы клипа
+    Dominant,
+    /// Зеркальные отражения краев видео
+    Mirror,
+    /// Вертикальный градиент: цвет сверху, цвет снизу
+    Gradient(u32, u32),
+    /// Картинка на весь кадр
+    Image(PathBuf),
+    /// Зацикленное видео на весь кадр (без звука)
+    Video(PathBuf),
+}
+
+impl BackgroundMode {
+    /// Разбирает значение `--background`: `blur`, `mirror`, `dominant`, `color:COLOR`,
+    /// `gradient[:TOP:BOTTOM]`, `image:FILE` или `video:FILE`
+    pub fn parse(value: &str) -> ConfigResult<Self> {
+        let value = value.trim();
+        let (mode, argument) = match value.split_once(':') {
+            Some((mode, argument)) => (mode, Some(argument)),
+            None => (value, None),
+        };
+        
+        let mode = match (mode.to_ascii_lowercase().as_str(), argument) {
+            ("blur", None) => Self::Blur,
+            ("mirror", None) => Self::Mirror,
+            ("dominant", None) => Self::Dominant,
+            ("color", Some(color)) => Self::Color(SubtitleStyle::parse_color(color)?),
+            ("gradient", None) => Self::Gradient(DEFAULT_BACKGROUND_GRADIENT.0, DEFAULT_BACKGROUND_GRADIENT.1),
+            ("gradient", Some(colors)) => match colors.split_once(':') {
+                Some((top, bottom)) => {
+                    Self::Gradient(SubtitleStyle::parse_color(top)?, SubtitleStyle::parse_color(bottom)?)
+                }
+                None => {
+                    return Err(ConfigError::invalid_arg(format!(
+                        "Invalid gradient '{}': expected gradient:TOP:BOTTOM, e.g. gradient:#203040:black",
+                        value
+                    )));
+                }
+            },
+            ("image", Some(path)) if !path.is_empty() => Self::Image(PathBuf::from(path)),
+            ("video", Some(path)) if !path.is_empty() => Self::Video(PathBuf::from(path)),
+            _ => {
+                return Err(ConfigError::invalid_arg(format!(
+                    "Unknown background '{}': expected blur, mirror, dominant, color:COLOR, \
+                     gradient[:TOP:BOTTOM], image:FILE or video:FILE",
+                    value
+                )));
+            }
+        };
+        
+        Ok(mode)
+    }
+}
+
+/// Угол кадра для водяного знака (`--watermark-position`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WatermarkPosition {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl WatermarkPosition {
+    /// Разбирает значение `--watermark-position` (`top-left`, `top-right`, `bottom-left`, `bottom-right`)
+    pub fn parse(value: &str) -> ConfigResult<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "top-left" => Ok(Self::TopLeft),
+            "top-right" => Ok(Self::TopRight),
+            "bottom-left" => Ok(Self::BottomLeft),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => Err(ConfigError::invalid_arg(format!(
+                "Unknown watermark position '{}': expected 'top-left', 'top-right', 'bottom-left' or 'bottom-right'",
+                value
+            ))),
+        }
+    }
+    
+    /// Координаты `overlay` для отступа `margin` пикселей от краев кадра
+    pub fn overlay_position(&self, margin: u32) -> (String, String) {
+        let left = margin.to_string();
+        let right = format!("W-w-{}", margin);
+        let top = margin.to_string();
+        let bottom = format!("H-h-{}", margin);
+        
+        match self {
+            Self::TopLeft => (left, top),
+            Self::TopRight => (right, top),
+            Self::BottomLeft => (left, bottom),
+            Self::BottomRight => (right, bottom),
+        }
+    }
+}
+
+/// Водяной знак поверх кадра (`--watermark`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatermarkSettings {
+    /// Изображение логотипа
+    pub path: PathBuf,
+    pub position: WatermarkPosition,
+    /// Отступ от краев - доля ширины выходного кадра
+    pub margin: f64,
+    /// Ширина логотипа - доля ширины выходного кадра
+    pub scale: f64,
+    /// Непрозрачность от 0 (не виден) до 1
+    pub opacity: f64,
+}
+
+/// Чей звук идет в клип разделенного экрана (`--split-audio`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitAudio {
+    /// Звук верхнего исходника
+    #[default]
+    Top,
+    /// Звук нижнего исходника
+    Bottom,
+    /// Смесь обоих исходников
+    Mix,
+}
+
+impl SplitAudio {
+    /// Разбирает значение `--split-audio` (`top`, `bottom` или `mix`)
+    pub fn parse(value: &str) -> ConfigResult<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "top" => Ok(Self::Top),
+            "bottom" => Ok(Self::Bottom),
+            "mix" => Ok(Self::Mix),
+            _ => Err(ConfigError::invalid_arg(format!(
+                "Unknown split-screen audio '{}': expected 'top', 'bottom' or 'mix'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Разделенный экран: два исходника один над другим (`--split-screen`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplitSettings {
+    /// Доля высоты кадра, которую занимает верхний исходник
+    pub ratio: f64,
+    pub audio: SplitAudio,
+}
+
+/// Цель нормализации громкости по EBU R128 (фильтр `loudnorm`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessTarget {
+    /// Интегральная громкость в LUFS
+    pub integrated: f64,
+    /// Максимальный истинный пик в dBTP
+    pub true_peak: f64,
+    /// Допустимый диапазон громкости (LRA) в LU
+    pub range: f64,
+}
+
+impl LoudnessTarget {
+    /// Проверяет цель на допустимые для `loudnorm` диапазоны
+    pub fn new(integrated: f64, true_peak: f64) -> ConfigResult<Self> {
+        if !(-70.0..=-5.0).contains(&integrated) {
+            return Err(ConfigError::invalid_arg(format!(
+                "Target loudness must be between -70 and -5 LUFS: {}",
+                integrated
+            )));
+        }
+        if !(-9.0..=0.0).contains(&true_peak) {
+            return Err(ConfigError::invalid_arg(format!(
+                "True peak must be between -9 and 0 dBTP: {}",
+                true_peak
+            )));
+        }
+        
+        Ok(Self { integrated, true_peak, range: LOUDNORM_RANGE })
+    }
+}
+
+/// Какую звуковую дорожку исходника брать в шортс
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum AudioSelection {
+    /// Первая звуковая дорожка
+    #[default]
+    First,
+    /// Дорожка по номеру среди звуковых (с нуля)
+    Index(usize),
+    /// Первая дорожка с указанным тегом языка (`eng`, `rus`, ...)
+    Language(String),
+    /// Отдельный выходной файл для каждого языка
+    PerLanguage,
+}
+
+impl AudioSelection {
+    /// Разбирает значение `--audio-track`: число - номер дорожки, иначе - тег языка
+    pub fn parse(value: &str) -> ConfigResult<Self> {
+        let value = value.trim();
+        if let Ok(index) = value.parse::<usize>() {
+            return Ok(Self::Index(index));
+        }
+        
+        if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphabetic() || c == '-') {
+            return Err(ConfigError::invalid_arg(format!(
+                "Invalid audio track '{}': expected a track number or a language tag, e.g. 1 or eng",
+                value
+            )));
+        }
+        
+        Ok(Self::Language(value.to_ascii_lowercase()))
+    }
+}
+
+/// Параметры музыкальной подложки (`--music`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MusicSettings {
+    /// Множитель громкости музыки до приглушения
+    pub volume: f64,
+    /// Длительность появления и затухания (секунды)
+    pub fade: f64,
+}
+
+/// Оформление вшитых субтитров
+///
+/// Размеры заданы в пикселях кадра высотой `SUBTITLE_REFERENCE_HEIGHT`
+/// и масштабируются вместе с кадром, поэтому одинаково выглядят в любом формате.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleStyle {
+    /// Имя шрифта (ищется через fontconfig)
+    pub font: String,
+    /// Размер шрифта
+    pub size: f64,
+    /// Цвет текста, 0xRRGGBB
+    pub color: u32,
+    /// Цвет обводки, 0xRRGGBB
+    pub outline_color: u32,
+    /// Толщина обводки
+    pub outline: f64,
+    /// Отступ снизу - доля высоты кадра, закрытая интерфейсом платформы
+    pub safe_zone: f64,
+}
+
+impl Default for SubtitleStyle {
+    fn default() -> Self {
+        Self {
+            font: DEFAULT_SUBTITLE_FONT.to_string(),
+            size: DEFAULT_SUBTITLE_SIZE,
+            color: 0xFFFFFF,
+            outline_color: 0x000000,
+            outline: DEFAULT_SUBTITLE_OUTLINE,
+            safe_zone: DEFAULT_SUBTITLE_SAFE_ZONE,
+        }
+    }
+}
+
+impl SubtitleStyle {
+    /// Разбирает цвет: `#RRGGBB`, `RRGGBB` или имя (`white`, `black`, `yellow`, ...)
+    pub fn parse_color(value: &str) -> ConfigResult<u32> {
+        let value = value.trim();
+        let named = match value.to_ascii_lowercase().as_str() {
+            "white" => Some(0xFFFFFF),
+            "black" => Some(0x000000),
+            "yellow" => Some(0xFFFF00),
+            "red" => Some(0xFF0000),
+            "green" => Some(0x00FF00),
+            "blue" => Some(0x0000FF),
+            _ => None,
+        };
+        if let Some(color) = named {
+            return Ok(color);
+        }
+        
+        let hex = value.strip_prefix('#').unwrap_or(value);
+        if hex.len() != 6 {
+            return Err(ConfigError::invalid_arg(format!(
+                "Invalid color '{}': expected #RRGGBB or a color name, e.g. white",
+                value
+            )));
+        }
+        u32::from_str_radix(hex, 16).map_err(|_| {
+            ConfigError::invalid_arg(format!("Invalid color '{}': expected #RRGGBB or a color name, e.g. white", value))
+        })
+    }
+}
+
+/// Пресет оформления пословных ("караоке") субтитров
+///
+/// Размеры заданы в пикселях кадра высотой `SUBTITLE_REFERENCE_HEIGHT`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KaraokePreset {
+    pub name: &'static str,
+    pub font: &'static str,
+    pub size: f64,
+    pub bold: bool,
+    /// Цвет текста, 0xRRGGBB
+    pub color: u32,
+    /// Цвет произносимого слова, 0xRRGGBB
+    pub highlight_color: u32,
+    /// Цвет обводки, 0xRRGGBB
+    pub outline_color: u32,
+    pub outline: f64,
+    /// Сколько слов одновременно на экране
+    pub max_words_per_line: usize,
+}
+
+impl KaraokePreset {
+    /// Ищет пресет по имени (`--karaoke`)
+    pub fn find(name: &str) -> ConfigResult<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        KARAOKE_PRESETS
+            .iter()
+            .find(|preset| preset.name == name)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = KARAOKE_PRESETS.iter().map(|preset| preset.name).collect();
+                ConfigError::invalid_arg(format!(
+                    "Unknown karaoke preset '{}': expected one of {}",
+                    name,
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+/// Заголовок поверх кадра (`--title`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TitleSettings {
+    /// Шаблон текста с подстановками `{stem}`, `{title}`, `{part}`
+    pub template: String,
+    /// Файл шрифта; `None` - шрифт FFmpeg по умолчанию
+    pub font_file: Option<PathBuf>,
+    /// Размер шрифта в пикселях кадра высотой `SUBTITLE_REFERENCE_HEIGHT`
+    pub size: f64,
+}
+
+impl TitleSettings {
+    /// Проверяет шаблон: допускаются только известные подстановки
+    pub fn new(template: &str, font_file: Option<PathBuf>, size: f64) -> ConfigResult<Self> {
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            let close = match rest[open..].find('}') {
+                Some(close) => open + close,
+                None => break,
+            };
+            let name = &rest[open + 1..close];
+            if !TITLE_PLACEHOLDERS.contains(&name) {
+                return Err(ConfigError::invalid_arg(format!(
+                    "Unknown title placeholder '{{{}}}': expected {{stem}}, {{title}} or {{part}}",
+                    name
+                )));
+            }
+            rest = &rest[close + 1..];
+        }
+        
+        Ok(Self { template: template.to_string(), font_file, size })
+    }
+    
+    /// Подставляет значения в шаблон; `{title}` без заголовка из списка нарезки - имя файла
+    ///
+    /// Шаблон разбирается за один проход: фигурные скобки в подставленных значениях
+    /// (например, `{part}` в имени файла) остаются как есть.
+    pub fn render(&self, stem: &str, title: Option<&str>, part: Option<&str>) -> String {
+        let mut rendered = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+        while let Some(open) = rest.find('{') {
+            let close = match rest[open..].find('}') {
+                Some(close) => open + close,
+                None => break,
+            };
+            let value = match &rest[open + 1..close] {
+                "stem" => stem,
+                "title" => title.unwrap_or(stem),
+                "part" => part.unwrap_or(""),
+                _ => &rest[open..=close],
+            };
+            rendered.push_str(&rest[..open]);
+            rendered.push_str(value);
+            rest = &rest[close + 1..];
+        }
+        rendered.push_str(rest);
+        
+        rendered.trim().to_string()
+    }
+}
+
+impl std::fmt::Display for BackgroundMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Blur => write!(f, "blur"),
+            Self::Color(color) => write!(f, "color #{:06X}", color),
+            Self::Dominant => write!(f, "dominant color"),
+            Self::Mirror => write!(f, "mirror"),
+            Self::Gradient(top, bottom) => write!(f, "gradient #{:06X} -> #{:06X}", top, bottom),
+            Self::Image(path) => write!(f, "image {}", path.display()),
+            Self::Video(path) => write!(f, "looping video {}", path.display()),
+        }
+    }
+}
+
+impl std::fmt::Display for AspectRatio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.width, self.height)
+    }
+}
+
+impl std::fmt::Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl std::fmt::Display for CropRect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+}
+
 // Константы приложения
 
-/// Жестко заданная FFmpeg команда для создания вертикальных шортсов
-pub const FFMPEG_FILTER_COMPLEX: &str = 
-    "[0:v]scale=2276:1280,boxblur=4[bg];[1:v]scale=720:-1[fg];[bg][fg]overlay=(W-w)/2:(H-h)/2[tmp];[tmp]crop=720:1280:(2276-720)/2:0[out]";
+/// Разрешение вертикального шортса по умолчанию
+pub const DEFAULT_RESOLUTION: Resolution = Resolution::new(720, 1280);
+
+/// Минимально допустимый размер стороны выходного кадра
+pub const MIN_OUTPUT_DIMENSION: u32 = 128;
+
+/// Максимально допустимый размер стороны выходного кадра
+pub const MAX_OUTPUT_DIMENSION: u32 = 4096;
+
+/// Максимальное отношение длинной стороны к короткой для `--aspect`
+pub const MAX_ASPECT_RATIO: f64 = 4.0;
+
+/// Максимальное количество выходных форматов за один проход
+pub const MAX_OUTPUT_VARIANTS: usize = 8;
+
+/// Минимальная длина фрагмента для `--segment-length` (секунды)
+pub const MIN_SEGMENT_LENGTH: f64 = 5.0;
+
+/// Хвост короче этого значения (секунды) присоединяется к предыдущей части
+pub const MIN_TAIL_SEGMENT: f64 = 1.0;
+
+/// Уровень шума (dB), ниже которого звук считается тишиной
+pub const DEFAULT_SILENCE_NOISE_DB: f64 = -30.0;
+
+/// Минимальная длительность тишины (секунды), которую находит `silencedetect`
+pub const DEFAULT_SILENCE_DURATION: f64 = 1.0;
+
+/// Фрагменты речи короче этого значения (секунды) при нарезке по тишине отбрасываются
+pub const MIN_SPEECH_SEGMENT: f64 = 2.0;
+
+/// Допуск (секунды), в пределах которого тишина считается примыкающей к краю файла
+pub const SILENCE_EDGE_TOLERANCE: f64 = 0.05;
+
+/// Длина фрагмента по умолчанию для `--highlights` (секунды)
+pub const DEFAULT_HIGHLIGHT_LENGTH: f64 = 30.0;
+
+/// Максимальное количество фрагментов, которое можно запросить через `--highlights`
+pub const MAX_HIGHLIGHTS: usize = 100;
+
+/// Кратковременная громкость (LUFS) ниже этого уровня считается тишиной (порог EBU R128)
+pub const LOUDNESS_FLOOR_LUFS: f64 = -70.0;
+
+/// Допуск (секунды), на который фрагмент из `--cuts` может выходить за длительность файла
+pub const CUT_RANGE_TOLERANCE: f64 = 0.1;
+
+/// Порог смены сцены для фильтра `select='gt(scene,X)'` (0..1)
+pub const DEFAULT_SCENE_THRESHOLD: f64 = 0.3;
+
+/// Минимальная длина шортса при нарезке по сценам (секунды)
+pub const DEFAULT_SCENE_MIN_LENGTH: f64 = 15.0;
+
+/// Максимальная длина шортса при нарезке по сценам (секунды)
+pub const DEFAULT_SCENE_MAX_LENGTH: f64 = 60.0;
+
+/// Целевая громкость по умолчанию для `--loudnorm` (LUFS, рекомендация YouTube)
+pub const DEFAULT_TARGET_LUFS: f64 = -14.0;
+
+/// Максимальный истинный пик по умолчанию для `--loudnorm` (dBTP)
+pub const DEFAULT_TRUE_PEAK: f64 = -1.0;
+
+/// Допустимый диапазон громкости (LRA) для `loudnorm`
+pub const LOUDNORM_RANGE: f64 = 11.0;
+
+/// Частота дискретизации после `loudnorm` (фильтр внутри работает на 192 кГц)
+pub const LOUDNORM_SAMPLE_RATE: u32 = 48000;
+
+/// Громкость музыкальной подложки по умолчанию (множитель `volume`)
+pub const DEFAULT_MUSIC_VOLUME: f64 = 0.25;
+
+/// Длительность появления и затухания музыки (секунды)
+pub const MUSIC_FADE_DURATION: f64 = 1.5;
+
+/// Приглушение музыки под речью: музыка сжимается, когда громкость голоса выше порога
+pub const MUSIC_DUCKING_FILTER: &str = "sidechaincompress=threshold=0.05:ratio=8:attack=20:release=400";
+
+/// Расширения файлов субтитров, которые ищутся рядом с исходником (в порядке приоритета)
+pub const SUBTITLE_EXTENSIONS: &[&str] = &["srt", "vtt"];
+
+/// Шрифт субтитров по умолчанию
+pub const DEFAULT_SUBTITLE_FONT: &str = "Arial";
+
+/// Размер шрифта субтитров по умолчанию (пиксели кадра высотой 1920)
+pub const DEFAULT_SUBTITLE_SIZE: f64 = 64.0;
+
+/// Толщина обводки субтитров по умолчанию (пиксели кадра высотой 1920)
+pub const DEFAULT_SUBTITLE_OUTLINE: f64 = 4.0;
+
+/// Доля высоты кадра снизу, закрытая интерфейсом платформ (подпись, кнопки)
+pub const DEFAULT_SUBTITLE_SAFE_ZONE: f64 = 0.2;
+
+/// Высота кадра, в пикселях которого задаются размеры субтитров
+pub const SUBTITLE_REFERENCE_HEIGHT: f64 = 1920.0;
+
+/// Высота сетки, в которой libass рендерит SRT/VTT (PlayResY заголовка FFmpeg)
+pub const SUBTITLE_PLAY_RES_Y: f64 = 288.0;
+
+/// Пресеты пословных субтитров; первый используется по умолчанию
+pub const KARAOKE_PRESETS: &[KaraokePreset] = &[
+    KaraokePreset {
+        name: "classic",
+        font: "Arial",
+        size: 80.0,
+        bold: true,
+        color: 0xFFFFFF,
+        highlight_color: 0xFFD400,
+        outline_color: 0x000000,
+        outline: 5.0,
+        max_words_per_line: 4,
+    },
+    KaraokePreset {
+        name: "punchy",
+        font: "Arial Black",
+        size: 96.0,
+        bold: true,
+        color: 0xFFFFFF,
+        highlight_color: 0x00E676,
+        outline_color: 0x000000,
+        outline: 6.0,
+        max_words_per_line: 3,
+    },
+    KaraokePreset {
+        name: "minimal",
+        font: "Helvetica",
+        size: 64.0,
+        bold: false,
+        color: 0xE0E0E0,
+        highlight_color: 0x4FC3F7,
+        outline_color: 0x202020,
+        outline: 2.0,
+        max_words_per_line: 6,
+    },
+];
+
+/// Подстановки шаблона заголовка: имя исходника, заголовок из списка нарезки, метка части
+pub const TITLE_PLACEHOLDERS: &[&str] = &["stem", "title", "part"];
+
+/// Размер шрифта заголовка по умолчанию (пиксели кадра высотой 1920)
+pub const DEFAULT_TITLE_SIZE: f64 = 72.0;
+
+/// Толщина обводки заголовка (пиксели кадра высотой 1920)
+pub const TITLE_BORDER: f64 = 4.0;
+
+/// Отступ заголовка сверху - доля высоты кадра (ниже верхнего интерфейса платформ)
+pub const TITLE_TOP_MARGIN: f64 = 0.08;
+
+/// Поля заголовка слева и справа - доля ширины кадра
+pub const TITLE_SIDE_MARGIN: f64 = 0.06;
+
+/// Средняя ширина символа относительно размера шрифта (для переноса строк)
+pub const TITLE_CHAR_WIDTH: f64 = 0.55;
+
+/// Высота строки заголовка относительно размера шрифта
+pub const TITLE_LINE_HEIGHT: f64 = 1.25;
+
+/// Максимум строк заголовка; остаток обрезается многоточием
+pub const TITLE_MAX_LINES: usize = 3;
+
+/// Ширина водяного знака по умолчанию - доля ширины кадра
+pub const DEFAULT_WATERMARK_SCALE: f64 = 0.15;
+
+/// Отступ водяного знака от краев по умолчанию - доля ширины кадра
+pub const DEFAULT_WATERMARK_MARGIN: f64 = 0.04;
+
+/// Непрозрачность водяного знака по умолчанию
+pub const DEFAULT_WATERMARK_OPACITY: f64 = 0.8;
+
+/// Частота дискретизации звука, который передается распознаванию речи (требование whisper.cpp)
+pub const TRANSCRIBE_SAMPLE_RATE: u32 = 16000;
+
+/// Максимум слов в одном субтитре, собранном из таймингов слов
+pub const CAPTION_MAX_WORDS: usize = 7;
+
+/// Максимальная длительность одного субтитра из таймингов слов (секунды)
+pub const CAPTION_MAX_DURATION: f64 = 4.0;
+
+/// Пауза между словами, после которой начинается новый субтитр (секунды)
+pub const CAPTION_MAX_GAP: f64 = 0.8;
+
+/// Параметры тихой дорожки для исходников без звука
+pub const SILENT_AUDIO_SOURCE: &str = "anullsrc=channel_layout=stereo:sample_rate=48000";
+
+/// Сила размытия фона (параметр boxblur)
+pub const BACKGROUND_BLUR_RADIUS: u32 = 4;
+
+/// Цвета градиентного фона по умолчанию: сверху и снизу
+pub const DEFAULT_BACKGROUND_GRADIENT: (u32, u32) = (0x303048, 0x101018);
+
+/// Размер кадра-пробы для поиска преобладающего цвета
+pub const DOMINANT_COLOR_SAMPLE: Resolution = Resolution::new(64, 36);
+
+/// Фильтр поиска черных полос; `reset=1` - каждый кадр оценивается отдельно
+pub const CROP_DETECT_FILTER: &str = "cropdetect=limit=24:round=2:reset=1";
+
+/// Полосы уже этой доли стороны кадра не срезаются (шум по краям, рамка кодека)
+pub const CROP_DETECT_MIN_BAR: f64 = 0.02;
+
+/// Ширина кадров, на которых отслеживается движение для `--reframe` (высота - по пропорциям исходника)
+pub const REFRAME_SAMPLE_WIDTH: u32 = 160;
+
+/// Сколько кадров в секунду анализируется для `--reframe`
+pub const REFRAME_SAMPLE_FPS: f64 = 4.0;
+
+/// Длина окна, для которого считается один центр движения (секунды)
+pub const REFRAME_WINDOW: f64 = 1.0;
+
+/// Разница яркости пикселя между соседними кадрами, которая считается движением
+pub const REFRAME_MOTION_THRESHOLD: u8 = 24;
+
+/// Минимальная доля движущихся пикселей окна; при меньшей окно сохраняет прежний центр
+pub const REFRAME_MIN_MOTION: f64 = 0.002;
+
+/// Сколько окон с каждой стороны усредняется при сглаживании траектории
+pub const REFRAME_SMOOTHING: usize = 2;
+
+/// Допуск упрощения траектории - доля ширины кадра, на которую окно может отклониться
+pub const REFRAME_TOLERANCE: f64 = 0.01;
+
+/// Доля высоты кадра для верхнего исходника разделенного экрана по умолчанию
+pub const DEFAULT_SPLIT_RATIO: f64 = 0.5;
+
+/// Метки в именах исходников, которые собираются в разделенный экран: `clip.top.mp4` и `clip.bottom.mp4`
+pub const SPLIT_TAGS: (&str, &str) = ("top", "bottom");
+
+/// Относительный допуск, в пределах которого соотношения сторон считаются равными
+pub const ASPECT_RATIO_TOLERANCE: f64 = 0.01;
 
 /// Суффикс для выходных файлов
 pub const OUTPUT_SUFFIX: &str = "-short";
@@ -98,6 +979,15 @@ pub const OUTPUT_SUFFIX: &str = "-short";
 /// Максимальное время выполнения FFmpeg для одного файла (10 минут)
 pub const FFMPEG_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
+/// Во сколько раз таймаут FFmpeg может превышать длительность исходника
+pub const FFMPEG_TIMEOUT_PER_MEDIA_SECOND: u32 = 4;
+
+/// Битрейт для оценки размера, если ffprobe его не сообщил (бит/с)
+pub const FALLBACK_BIT_RATE: u64 = 4_000_000;
+
+/// Максимальное время выполнения ffprobe для одного файла
+pub const FFPROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Максимально допустимое количество потоков
 pub const MAX_THREADS: usize = 32;
 
@@ -107,6 +997,9 @@ pub const LOG_FILENAME_PATTERN: &str = "shorts-cutter-%Y%m%d-%H%M%S.log";
 /// Имя исполняемого файла FFmpeg
 pub const FFMPEG_EXECUTABLE: &str = "ffmpeg";
 
+/// Имя исполняемого файла FFprobe
+pub const FFPROBE_EXECUTABLE: &str = "ffprobe";
+
 /// Аргументы FFmpeg для проверки версии
 pub const FFMPEG_VERSION_ARGS: &[&str] = &["-version"];
 
@@ -116,6 +1009,9 @@ pub const FFMPEG_BUFFER_SIZE: usize = 8192;
 /// Расширения файлов для поиска (в нижнем регистре)
 pub const DEFAULT_INPUT_EXTENSIONS: &[&str] = &["mp4"];
 
+/// Расширения музыкальных файлов для `--music` (в нижнем регистре)
+pub const MUSIC_EXTENSIONS: &[&str] = &["mp3", "m4a", "aac", "wav", "flac", "ogg", "opus"];
+
 /// Коды возврата приложения
 pub mod exit_codes {
     /// Успешное завершение
@@ -35,8 +35,23 @@ pub enum ConfigError {
     #[error("FFmpeg not found in PATH")]
     FfmpegNotFound,
     
+    #[error("FFprobe not found in PATH")]
+    FfprobeNotFound,
+    
     #[error("Invalid argument: {message}")]
     InvalidArgument { message: String },
+    
+    #[error("Invalid resolution '{value}': {reason}")]
+    InvalidResolution { value: String, reason: String },
+    
+    #[error("Invalid aspect ratio '{value}': {reason}")]
+    InvalidAspectRatio { value: String, reason: String },
+    
+    #[error("Invalid duration '{value}': {reason}")]
+    InvalidDuration { value: String, reason: String },
+    
+    #[error("Manifest error: {0}")]
+    CutList(#[from] ManifestError),
 }
 
 /// Ошибки работы с файловой системой
@@ -79,6 +94,76 @@ pub enum FfmpegError {
     
     #[error("FFmpeg stderr parsing failed")]
     StderrParsingFailed,
+    
+    #[error("Cannot probe media file {path}: {reason}")]
+    ProbeFailed { path: PathBuf, reason: String },
+    
+    #[error("No video stream found in: {path}")]
+    NoVideoStream { path: PathBuf },
+    
+    #[error("No audio stream found in: {path}")]
+    NoAudioStream { path: PathBuf },
+    
+    #[error("Audio track {selector} not found in {path} (available: {available})")]
+    AudioTrackNotFound { path: PathBuf, selector: String, available: String },
+    
+    #[error("FFmpeg {pass} pass failed: {reason}")]
+    AnalysisFailed { pass: String, reason: String },
+}
+
+/// Ошибки списка нарезки (`--cuts`) и списка пар разделенного экрана (`--split-pairs`)
+///
+/// Ошибки чтения файла прерывают запуск, ошибки отдельных строк попадают в финальный отчет.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ManifestError {
+    #[error("Cannot read list {path}: {reason}")]
+    Unreadable { path: PathBuf, reason: String },
+    
+    #[error("Malformed list {path}: {reason}")]
+    Malformed { path: PathBuf, reason: String },
+    
+    #[error("Cut list row {row}: file '{file}' not found in the input directory")]
+    UnknownFile { file: String, row: usize },
+    
+    #[error("Cut list row {row}: '{file}' matches {count} files in the input directory; use a path relative to it")]
+    AmbiguousFile { file: String, row: usize, count: usize },
+    
+    #[error("Cut list row {row}: invalid range for '{file}': {reason}")]
+    InvalidRange { file: String, row: usize, reason: String },
+    
+    #[error("Cut list row {row}: invalid crop for '{file}': {reason}")]
+    InvalidCrop { file: String, row: usize, reason: String },
+    
+    #[error("Cut list row {row}: range ends at {end:.3}s but '{file}' is only {duration:.3}s long")]
+    RangeBeyondDuration { file: String, row: usize, end: f64, duration: f64 },
+    
+    #[error("Split pair list row {row}: {reason}")]
+    InvalidPair { row: usize, reason: String },
+}
+
+/// Ошибки распознавания речи (`--transcribe`)
+#[derive(Error, Debug)]
+pub enum TranscribeError {
+    #[error("Cannot start transcription command '{program}'")]
+    CannotSpawn { program: String },
+    
+    #[error("Transcription command failed with exit code {code}: {reason}")]
+    CommandFailed { code: i32, reason: String },
+    
+    #[error("Transcription timeout after {seconds} seconds")]
+    Timeout { seconds: u64 },
+    
+    #[error("Transcription produced neither {prefix}.srt nor {prefix}.json")]
+    NoOutput { prefix: PathBuf },
+    
+    #[error("Invalid transcript {path}: {reason}")]
+    InvalidOutput { path: PathBuf, reason: String },
+    
+    #[error("Cannot store transcript {path}: {reason}")]
+    CannotStore { path: PathBuf, reason: String },
+    
+    #[error("{0}")]
+    Ffmpeg(#[from] FfmpegError),
 }
 
 /// Ошибки системы логирования
@@ -103,6 +188,8 @@ pub type ConfigResult<T> = std::result::Result<T, ConfigError>;
 pub type FileSystemResult<T> = std::result::Result<T, FileSystemError>;
 pub type FfmpegResult<T> = std::result::Result<T, FfmpegError>;
 pub type LoggingResult<T> = std::result::Result<T, LoggingError>;
+pub type ManifestResult<T> = std::result::Result<T, ManifestError>;
+pub type TranscribeResult<T> = std::result::Result<T, TranscribeError>;
 
 /// Helper функции для создания ошибок с контекстом
 impl ConfigError {
@@ -121,6 +208,18 @@ impl ConfigError {
     pub fn invalid_arg(message: impl Into<String>) -> Self {
         Self::InvalidArgument { message: message.into() }
     }
+    
+    pub fn invalid_resolution(value: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::InvalidResolution { value: value.into(), reason: reason.into() }
+    }
+    
+    pub fn invalid_aspect(value: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::InvalidAspectRatio { value: value.into(), reason: reason.into() }
+    }
+    
+    pub fn invalid_duration(value: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::InvalidDuration { value: value.into(), reason: reason.into() }
+    }
 }
 
 impl FfmpegError {
@@ -135,6 +234,86 @@ impl FfmpegError {
     pub fn invalid_format(path: PathBuf) -> Self {
         Self::InvalidInputFormat { path }
     }
+    
+    pub fn probe_failed(path: PathBuf, reason: String) -> Self {
+        Self::ProbeFailed { path, reason }
+    }
+    
+    pub fn no_video_stream(path: PathBuf) -> Self {
+        Self::NoVideoStream { path }
+    }
+    
+    pub fn no_audio_stream(path: PathBuf) -> Self {
+        Self::NoAudioStream { path }
+    }
+    
+    pub fn audio_track_not_found(path: PathBuf, selector: impl Into<String>, available: impl Into<String>) -> Self {
+        Self::AudioTrackNotFound { path, selector: selector.into(), available: available.into() }
+    }
+    
+    pub fn analysis_failed(pass: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::AnalysisFailed { pass: pass.into(), reason: reason.into() }
+    }
+}
+
+impl ManifestError {
+    pub fn unreadable(path: PathBuf, reason: impl Into<String>) -> Self {
+        Self::Unreadable { path, reason: reason.into() }
+    }
+    
+    pub fn malformed(path: PathBuf, reason: impl Into<String>) -> Self {
+        Self::Malformed { path, reason: reason.into() }
+    }
+    
+    pub fn unknown_file(file: impl Into<String>, row: usize) -> Self {
+        Self::UnknownFile { file: file.into(), row }
+    }
+    
+    pub fn ambiguous_file(file: impl Into<String>, row: usize, count: usize) -> Self {
+        Self::AmbiguousFile { file: file.into(), row, count }
+    }
+    
+    pub fn invalid_range(file: impl Into<String>, row: usize, reason: impl Into<String>) -> Self {
+        Self::InvalidRange { file: file.into(), row, reason: reason.into() }
+    }
+    
+    pub fn invalid_crop(file: impl Into<String>, row: usize, reason: impl Into<String>) -> Self {
+        Self::InvalidCrop { file: file.into(), row, reason: reason.into() }
+    }
+    
+    pub fn beyond_duration(file: impl Into<String>, row: usize, end: f64, duration: f64) -> Self {
+        Self::RangeBeyondDuration { file: file.into(), row, end, duration }
+    }
+    
+    pub fn invalid_pair(row: usize, reason: impl Into<String>) -> Self {
+        Self::InvalidPair { row, reason: reason.into() }
+    }
+}
+
+impl TranscribeError {
+    pub fn cannot_spawn(program: impl Into<String>) -> Self {
+        Self::CannotSpawn { program: program.into() }
+    }
+    
+    pub fn command_failed(code: i32, reason: impl Into<String>) -> Self {
+        Self::CommandFailed { code, reason: reason.into() }
+    }
+    
+    pub fn timeout(seconds: u64) -> Self {
+        Self::Timeout { seconds }
+    }
+    
+    pub fn no_output(prefix: PathBuf) -> Self {
+        Self::NoOutput { prefix }
+    }
+    
+    pub fn invalid_output(path: PathBuf, reason: impl Into<String>) -> Self {
+        Self::InvalidOutput { path, reason: reason.into() }
+    }
+    
+    pub fn cannot_store(path: PathBuf, reason: impl Into<String>) -> Self {
+        Self::CannotStore { path, reason: reason.into() }
+    }
 }
 
 impl FileSystemError {
@@ -1,34 +1,158 @@
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Arc;
 use tokio::process::Command;
 use tokio::time::{timeout, Duration};
 use tracing::{debug, info, warn};
-use crate::config::{FFMPEG_EXECUTABLE, FFMPEG_FILTER_COMPLEX, FFMPEG_TIMEOUT};
+use crate::config::{
+    BackgroundMode, CropRect, KaraokePreset, LoudnessTarget, MissingAudio, MusicSettings, OutputVariant, Resolution, SplitAudio, SplitSettings,
+    SubtitleStyle, TitleSettings, WatermarkSettings, ASPECT_RATIO_TOLERANCE,
+    BACKGROUND_BLUR_RADIUS, DEFAULT_RESOLUTION, FFMPEG_EXECUTABLE, FFMPEG_TIMEOUT,
+    FFMPEG_TIMEOUT_PER_MEDIA_SECOND, LOUDNORM_SAMPLE_RATE, MUSIC_DUCKING_FILTER, SILENT_AUDIO_SOURCE,
+    SUBTITLE_PLAY_RES_Y, SUBTITLE_REFERENCE_HEIGHT, TITLE_BORDER, TITLE_CHAR_WIDTH, TITLE_LINE_HEIGHT,
+    TITLE_MAX_LINES, TITLE_SIDE_MARGIN, TITLE_TOP_MARGIN,
+};
+use crate::analysis::{LoudnormStats, ReframeTrack};
+use crate::captions::ass_color;
 use crate::error::{FfmpegError, FfmpegResult};
+use crate::probe::{probe_media, MediaInfo};
+use crate::transcribe::Transcriber;
+use crate::utils::{format_seconds, FileTask};
+
+/// Параметры рендера, общие для всех задач запуска
+#[derive(Debug, Clone)]
+pub struct RenderSettings {
+    /// Выходные форматы, которые рендерятся из одного декодирования
+    pub variants: Vec<OutputVariant>,
+    /// Что делать с исходниками без звуковой дорожки
+    pub missing_audio: MissingAudio,
+    /// Цель нормализации громкости; `None` - звук не трогаем
+    pub loudnorm: Option<LoudnessTarget>,
+    /// Параметры музыкальной подложки; треки назначаются задачам в `FileTask::music`
+    pub music: Option<MusicSettings>,
+    /// Оформление субтитров; файлы субтитров привязаны к задачам в `FileTask::subtitles`
+    pub subtitles: SubtitleStyle,
+    /// Распознавание речи для клипов без файла субтитров (`--transcribe`)
+    pub transcriber: Option<Arc<dyn Transcriber>>,
+    /// Пресет пословных субтитров (`--karaoke`); ASS-файлы готовятся в `FileTask::karaoke`
+    pub karaoke: Option<KaraokePreset>,
+    /// Заголовок поверх кадра (`--title`)
+    pub title: Option<TitleSettings>,
+    /// Водяной знак поверх кадра (`--watermark`)
+    pub watermark: Option<WatermarkSettings>,
+    /// Заставка перед каждым клипом (`--intro`)
+    pub intro: Option<BrandClip>,
+    /// Финальная карточка после каждого клипа (`--outro`)
+    pub outro: Option<BrandClip>,
+    /// Фон вокруг горизонтального видео (`--background`)
+    pub background: BackgroundMode,
+    /// Разделенный экран (`--split-screen`); второй исходник задачи - `FileTask::split_input`
+    pub split: Option<SplitSettings>,
+    /// Окно кадра следует за движением (`--reframe`); траектории готовятся в `FileTask::reframe`
+    pub reframe: bool,
+    /// Поиск черных полос перед рендером; обрезка задач хранится в `FileTask::crop`
+    pub autocrop: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            variants: vec![OutputVariant {
+                label: DEFAULT_RESOLUTION.to_string(),
+                resolution: DEFAULT_RESOLUTION,
+            }],
+            missing_audio: MissingAudio::default(),
+            loudnorm: None,
+            music: None,
+            subtitles: SubtitleStyle::default(),
+            transcriber: None,
+            karaoke: None,
+            title: None,
+            watermark: None,
+            intro: None,
+            outro: None,
+            background: BackgroundMode::default(),
+            split: None,
+            reframe: false,
+            autocrop: false,
+        }
+    }
+}
+
+/// Заставка или финальная карточка, которая приклеивается к каждому клипу
+#[derive(Debug, Clone)]
+pub struct BrandClip {
+    pub path: PathBuf,
+    /// Результат ffprobe: размер кадра, длительность и наличие звука
+    pub media: MediaInfo,
+}
+
+impl BrandClip {
+    /// Анализирует клип один раз перед обработкой
+    pub async fn load(path: &Path) -> FfmpegResult<Self> {
+        let media = probe_media(path).await?;
+        Self::from_media(path, media)
+    }
+    
+    /// Проверяет, что у клипа есть кадр и известна длительность
+    pub fn from_media(path: &Path, media: MediaInfo) -> FfmpegResult<Self> {
+        if media.width() == 0 || media.height() == 0 {
+            return Err(FfmpegError::no_video_stream(path.to_path_buf()));
+        }
+        if media.duration <= 0.0 {
+            return Err(FfmpegError::probe_failed(path.to_path_buf(), "unknown duration".to_string()));
+        }
+        
+        Ok(Self { path: path.to_path_buf(), media })
+    }
+}
 
 /// Структура для представления FFmpeg команды
 #[derive(Debug, Clone)]
 pub struct FfmpegCommand {
     pub input_path: PathBuf,
-    pub output_path: PathBuf,
+    /// Выходные файлы - по одному на каждый формат
+    pub output_paths: Vec<PathBuf>,
     pub command_string: String,
+    /// Таймаут выполнения, рассчитанный по длительности исходника
+    pub timeout: Duration,
+    args: Vec<String>,
 }
 
 impl FfmpegCommand {
     /// Создает новую FFmpeg команду для обработки видео в вертикальный shorts
-    pub fn new(input_path: PathBuf, output_path: PathBuf) -> Self {
-        let command_string = build_ffmpeg_command_string(&input_path, &output_path);
+    ///
+    /// Исходник без звука - ошибка только при `MissingAudio::Error`.
+    pub fn new(task: &FileTask, media_info: &MediaInfo, settings: &RenderSettings) -> FfmpegResult<Self> {
+        if let (Some(_), Some(path), None) = (&settings.split, &task.split_input, &task.split_media_info) {
+            return Err(FfmpegError::probe_failed(path.clone(), "split-screen input was not analyzed".to_string()));
+        }
         
-        Self {
-            input_path,
-            output_path,
-            command_string,
+        let has_audio = voice_source(task, media_info, settings).is_none_or(|(_, _, media)| media.has_audio());
+        if !has_audio && settings.missing_audio == MissingAudio::Error {
+            return Err(FfmpegError::no_audio_stream(task.input.clone()));
         }
+        
+        let args = build_ffmpeg_args(task, media_info, settings);
+        let command_string = build_ffmpeg_command_string(&args);
+        let bumpers: f64 = [&settings.intro, &settings.outro]
+            .into_iter()
+            .flatten()
+            .map(|clip| clip.media.duration)
+            .sum();
+        
+        Ok(Self {
+            input_path: task.input.clone(),
+            output_paths: task.outputs.clone(),
+            command_string,
+            timeout: timeout_for_duration(clip_duration(task, media_info) + bumpers),
+            args,
+        })
     }
     
     /// Возвращает аргументы для выполнения команды
     pub fn args(&self) -> Vec<String> {
-        build_ffmpeg_args(&self.input_path, &self.output_path)
+        self.args.clone()
     }
     
     /// Возвращает строковое представление команды для логирования
@@ -74,10 +198,12 @@ pub async fn execute_ffmpeg_command(cmd: FfmpegCommand) -> FfmpegResult<FfmpegEx
         return Err(FfmpegError::invalid_format(cmd.input_path));
     }
     
-    // Создаем директорию для выходного файла если нужно
-    if let Some(parent) = cmd.output_path.parent() {
-        if let Err(_) = tokio::fs::create_dir_all(parent).await {
-            return Err(FfmpegError::CannotSpawnProcess);
+    // Создаем директории для выходных файлов если нужно
+    for output_path in &cmd.output_paths {
+        if let Some(parent) = output_path.parent() {
+            if let Err(_) = tokio::fs::create_dir_all(parent).await {
+                return Err(FfmpegError::CannotSpawnProcess);
+            }
         }
     }
     
@@ -92,7 +218,7 @@ pub async fn execute_ffmpeg_command(cmd: FfmpegCommand) -> FfmpegResult<FfmpegEx
         .map_err(|_| FfmpegError::CannotSpawnProcess)?;
     
     // Ждем завершения с таймаутом
-    let execution_result = match timeout(FFMPEG_TIMEOUT, child.wait_with_output()).await {
+    let execution_result = match timeout(cmd.timeout, child.wait_with_output()).await {
         Ok(Ok(output)) => {
             let duration = start_time.elapsed();
             let stdout = String::from_utf8_lossy(&output.stdout).to_string();
@@ -134,7 +260,7 @@ pub async fn execute_ffmpeg_command(cmd: FfmpegCommand) -> FfmpegResult<FfmpegEx
             warn!("FFmpeg timeout for: {}", 
                  cmd.input_path.file_name().unwrap_or_default().to_string_lossy());
             
-            return Err(FfmpegError::timeout(FFMPEG_TIMEOUT.as_secs()));
+            return Err(FfmpegError::timeout(cmd.timeout.as_secs()));
         }
     };
     
@@ -172,30 +298,1062 @@ impl FfmpegExecutionResult {
     }
 }
 
+/// Способ компоновки исходного кадра в вертикальный
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameLayout {
+    /// Фон на весь кадр (по умолчанию размытый, см. `Background`) и неискаженное видео по центру
+    BlurredBackground,
+    /// Исходник уже в целевом соотношении сторон - только масштабирование
+    ScaleOnly,
+    /// Вертикальный исходник другого соотношения сторон - вписываем без размытия
+    Fit,
+}
+
+impl FrameLayout {
+    /// Выбирает компоновку по геометрии исходника и целевому разрешению
+    pub fn choose(source: Resolution, target: Resolution) -> Self {
+        let ratio = source.aspect_ratio() / target.aspect_ratio();
+        
+        if (ratio - 1.0).abs() <= ASPECT_RATIO_TOLERANCE {
+            FrameLayout::ScaleOnly
+        } else if source.is_portrait() {
+            FrameLayout::Fit
+        } else {
+            FrameLayout::BlurredBackground
+        }
+    }
+}
+
+/// Фон компоновки `FrameLayout::BlurredBackground`, готовый для графа
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Background<'a> {
+    Blur,
+    Color(u32),
+    Mirror,
+    Gradient(u32, u32),
+    /// Кадры дополнительного входа FFmpeg (картинка или видео) с указанным номером
+    Input(usize),
+    /// Без фона: кадр обрезается окном, которое следует за движением (`--reframe`)
+    Crop(&'a ReframeTrack),
+}
+
+/// Нужен ли фон хотя бы одному формату (горизонтальный исходник в кадре другой формы)
+pub fn needs_background(source: Resolution, targets: &[Resolution]) -> bool {
+    targets
+        .iter()
+        .any(|target| FrameLayout::choose(source, *target) == FrameLayout::BlurredBackground)
+}
+
+/// Строит filter_complex для конкретного исходника
+///
+/// Размеры фона и переднего плана считаются из реальной геометрии исходника,
+/// поэтому фон всегда закрывает кадр целиком, а видео не растягивается.
+/// Для нескольких форматов поток раздваивается через `split` и каждый формат
+/// получает собственную цепочку и выход `[out0]`, `[out1]`, ...
+/// Кадры исходника берутся из потока `stream`: `0:v` или его копии без черных полос.
+/// Картинка или видео фона (`Background::Input`) тоже раздаются форматам через `split`.
+/// Фоны из отдельного источника приводятся к частоте кадров исходника `fps`:
+/// `overlay` выдает кадры с частотой фона, а не видео поверх него.
+pub fn build_filter_complex(
+    source: Resolution,
+    stream: &str,
+    targets: &[Resolution],
+    background: Background,
+    fps: Option<f64>,
+) -> String {
+    let layouts: Vec<FrameLayout> = targets
+        .iter()
+        .map(|target| FrameLayout::choose(source, *target))
+        .collect();
+    
+    // Вход декодируется один раз, а копии кадров раздаются цепочкам через split
+    let suffix = |index: usize| if targets.len() == 1 { String::new() } else { index.to_string() };
+    let mut source_labels = Vec::new();
+    for (index, layout) in layouts.iter().enumerate() {
+        source_labels.push(format!("src{}", suffix(index)));
+        if *layout == FrameLayout::BlurredBackground && background == Background::Blur {
+            source_labels.push(format!("fgsrc{}", suffix(index)));
+        }
+    }
+    
+    let mut chains = Vec::new();
+    let mut sources = split_stream(stream, "split", &source_labels, &mut chains).into_iter();
+    
+    // Картинка или видео фона тоже декодируются один раз на все форматы
+    let background_labels: Vec<String> = layouts
+        .iter()
+        .enumerate()
+        .filter(|(_, layout)| **layout == FrameLayout::BlurredBackground)
+        .map(|(index, _)| format!("bgsrc{}", suffix(index)))
+        .collect();
+    let mut background_sources = match background {
+        Background::Input(input) => split_stream(&format!("{}:v", input), "split", &background_labels, &mut chains),
+        _ => Vec::new(),
+    }
+    .into_iter();
+    
+    for (index, (target, layout)) in targets.iter().zip(&layouts).enumerate() {
+        let out = format!("out{}", suffix(index));
+        let source_label = sources.next().unwrap_or_default();
+        
+        let chain = match layout {
+            FrameLayout::BlurredBackground => match background {
+                Background::Blur => {
+                    let foreground_label = sources.next().unwrap_or_default();
+                    build_blurred_chain(source, *target, &source_label, &foreground_label, &suffix(index), &out)
+                }
+                Background::Color(color) => {
+                    let fit = scale_to_fit(source, *target);
+                    format!(
+                        "[{}]scale={}:{},pad={}:{}:(ow-iw)/2:(oh-ih)/2:color=0x{:06X},setsar=1[{}]",
+                        source_label, fit.width, fit.height, target.width, target.height, color, out
+                    )
+                }
+                Background::Mirror => build_mirror_chain(source, *target, &source_label, &suffix(index), &out),
+                Background::Gradient(top, bottom) => {
+                    let gradient = build_gradient_source(*target, top, bottom, fps);
+                    build_overlay_chain(source, *target, &gradient, &source_label, &suffix(index), &out)
+                }
+                Background::Crop(track) => build_reframe_chain(source, *target, track, &source_label, &out),
+                Background::Input(_) => {
+                    let background_label = background_sources.next().unwrap_or_default();
+                    let cover = format!(
+                        "[{}]scale={w}:{h}:force_original_aspect_ratio=increase,crop={w}:{h},setsar=1{}",
+                        background_label,
+                        fps_filter(fps),
+                        w = target.width,
+                        h = target.height
+                    );
+                    build_overlay_chain(source, *target, &cover, &source_label, &suffix(index), &out)
+                }
+            },
+            FrameLayout::ScaleOnly => {
+                format!("[{}]scale={}:{},setsar=1[{}]", source_label, target.width, target.height, out)
+            }
+            FrameLayout::Fit => {
+                let fit = scale_to_fit(source, *target);
+                
+                format!(
+                    "[{}]scale={}:{},pad={}:{}:(ow-iw)/2:(oh-ih)/2,setsar=1[{}]",
+                    source_label, fit.width, fit.height, target.width, target.height, out
+                )
+            }
+        };
+        chains.push(chain);
+    }
+    
+    chains.join(";")
+}
+
+/// Цепочка "размытый фон + видео по центру" для одного формата
+fn build_blurred_chain(
+    source: Resolution,
+    target: Resolution,
+    background_label: &str,
+    foreground_label: &str,
+    suffix: &str,
+    out: &str,
+) -> String {
+    let cover = scale_to_cover(source, target);
+    let fit = scale_to_fit(source, target);
+    
+    format!(
+        "[{bg_in}]scale={}:{},crop={}:{},boxblur={}[bg{s}];[{fg_in}]scale={}:{}[fg{s}];[bg{s}][fg{s}]overlay=(W-w)/2:(H-h)/2,setsar=1[{out}]",
+        cover.width, cover.height, target.width, target.height, BACKGROUND_BLUR_RADIUS,
+        fit.width, fit.height,
+        bg_in = background_label, fg_in = foreground_label, s = suffix, out = out,
+    )
+}
+
+/// Цепочка "фон + видео по центру" для фона из отдельного источника (градиент, картинка, видео)
+///
+/// Фон бесконечен или зациклен, поэтому длительность выхода задает видео (`shortest=1`).
+fn build_overlay_chain(
+    source: Resolution,
+    target: Resolution,
+    background: &str,
+    foreground_label: &str,
+    suffix: &str,
+    out: &str,
+) -> String {
+    let fit = scale_to_fit(source, target);
+    
+    format!(
+        "{background}[bg{s}];[{fg_in}]scale={}:{}[fg{s}];[bg{s}][fg{s}]overlay=(W-w)/2:(H-h)/2:shortest=1,setsar=1[{out}]",
+        fit.width, fit.height,
+        background = background, fg_in = foreground_label, s = suffix, out = out,
+    )
+}
+
+/// Источник вертикального градиента размером с кадр
+///
+/// `geq` считает цвет строки по ее номеру в столбце шириной 1 пиксель,
+/// затем столбец растягивается на всю ширину - это дешево для любого кадра.
+/// Частота кадров источника - `fps` исходника (без нее `color` дает 25 fps).
+fn build_gradient_source(target: Resolution, top: u32, bottom: u32, fps: Option<f64>) -> String {
+    let channel = |name: &str, shift: u32| {
+        let (from, to) = ((top >> shift) & 0xFF, (bottom >> shift) & 0xFF);
+        format!("{}={}+({}-{})*Y/H", name, from, to, from)
+    };
+    
+    let rate = fps.map(|fps| format!(":r={}", format_fps(fps))).unwrap_or_default();
+    format!(
+        "color=c=black:s=1x{h}{},format=gbrp,geq={}:{}:{},scale={w}:{h},setsar=1",
+        rate,
+        channel("r", 16),
+        channel("g", 8),
+        channel("b", 0),
+        w = target.width,
+        h = target.height
+    )
+}
+
+/// Цепочка "зеркальные края + видео по центру"
+///
+/// Вписанное видео повторяется по обе стороны от себя с чередующимся
+/// отражением (`vflip` для полос сверху и снизу, `hflip` - слева и справа),
+/// пока копии не закроют поля, а лишнее обрезается по центру.
+fn build_mirror_chain(source: Resolution, target: Resolution, input_label: &str, suffix: &str, out: &str) -> String {
+    let fit = scale_to_fit(source, target);
+    let vertical = fit.height < target.height;
+    let (piece, span, flip, stack) = if vertical {
+        (fit.height, target.height, "vflip", "vstack")
+    } else {
+        (fit.width, target.width, "hflip", "hstack")
+    };
+    let side = ((span.saturating_sub(piece) as f64 / 2.0) / piece as f64).ceil().max(1.0) as usize;
+    let count = side * 2 + 1;
+    
+    let labels: Vec<String> = (0..count).map(|index| format!("mir{}_{}", suffix, index)).collect();
+    let mut chains = vec![format!(
+        "[{}]scale={}:{},setsar=1,split={}{}",
+        input_label,
+        fit.width,
+        fit.height,
+        count,
+        labels.iter().map(|label| format!("[{}]", label)).collect::<String>()
+    )];
+    
+    // Копии через одну от центра отражены, поэтому стыки совпадают
+    let mut stacked = String::new();
+    for (index, label) in labels.iter().enumerate() {
+        if index.abs_diff(side) % 2 == 1 {
+            chains.push(format!("[{}]{}[{}f]", label, flip, label));
+            stacked.push_str(&format!("[{}f]", label));
+        } else {
+            stacked.push_str(&format!("[{}]", label));
+        }
+    }
+    chains.push(format!(
+        "{}{}=inputs={},crop={}:{},setsar=1[{}]",
+        stacked, stack, count, target.width, target.height, out
+    ));
+    
+    chains.join(";")
+}
+
+/// Цепочка "окно кадра следует за действием" (`--reframe`)
+///
+/// Из исходника вырезается окно пропорций формата на всю высоту кадра, которое
+/// сдвигается по траектории. Между точками траектории окно движется линейно,
+/// поэтому `x` - сумма отрезков `clip(t-T,0,D)` с наклоном своего участка:
+/// выражение остается плоским при любом числе точек. Координаты считаются
+/// заранее и не выводят окно за край кадра.
+fn build_reframe_chain(source: Resolution, target: Resolution, track: &ReframeTrack, input_label: &str, out: &str) -> String {
+    let crop_width = even_round(source.height as f64 * target.aspect_ratio()).min(source.width);
+    let crop_height = even_round(source.width as f64 / target.aspect_ratio()).min(source.height);
+    let max_x = source.width.saturating_sub(crop_width) as f64;
+    let position = |center: f64| (center * source.width as f64 - crop_width as f64 / 2.0).round().clamp(0.0, max_x);
+    
+    let points: Vec<(f64, f64)> = track.points.iter().map(|(time, center)| (*time, position(*center))).collect();
+    let round = |value: f64| (value * 1000.0).round() / 1000.0;
+    let mut x = points.first().map_or((max_x / 2.0).round(), |(_, x)| *x).to_string();
+    for pair in points.windows(2) {
+        let ((start, from), (end, to)) = (pair[0], pair[1]);
+        if from != to && end > start {
+            x.push_str(&format!(
+                "+({})*clip(t-{},0,{})",
+                round((to - from) / (end - start)),
+                format_seconds(start),
+                format_seconds(end - start)
+            ));
+        }
+    }
+    
+    format!(
+        "[{}]crop=w={}:h={}:x='{}':y=(ih-oh)/2,scale={}:{},setsar=1[{}]",
+        input_label, crop_width, crop_height, x, target.width, target.height, out
+    )
+}
+
+/// Строит filter_complex разделенного экрана: верхний исходник над нижним
+///
+/// Каждый исходник масштабируется так, чтобы закрыть свою часть кадра
+/// (верхней достается доля `ratio` высоты), и обрезается по центру.
+/// Оба исходника приводятся к частоте кадров `fps` верхнего, иначе `vstack`
+/// склеит кадры разных моментов. Части склеиваются через `vstack`, который
+/// заканчивается вместе с более коротким исходником. Кадры исходников берутся
+/// из потоков `top_stream` и `bottom_stream` (входы или их копии без черных полос).
+/// Выходы называются так же, как в `build_filter_complex`.
+pub fn build_split_filter_complex(
+    top: Resolution,
+    top_stream: &str,
+    bottom: Resolution,
+    bottom_stream: &str,
+    targets: &[Resolution],
+    ratio: f64,
+    fps: Option<f64>,
+) -> String {
+    let suffix = |index: usize| if targets.len() == 1 { String::new() } else { index.to_string() };
+    let labels = |name: &str| -> Vec<String> { (0..targets.len()).map(|index| format!("{}src{}", name, suffix(index))).collect() };
+    
+    let fps = fps_filter(fps);
+    let mut chains = Vec::new();
+    let tops = split_stream(top_stream, "split", &labels("top"), &mut chains);
+    let bottoms = split_stream(bottom_stream, "split", &labels("bottom"), &mut chains);
+    
+    for (index, target) in targets.iter().enumerate() {
+        let s = suffix(index);
+        let top_height = even_round(target.height as f64 * ratio).min(target.height.saturating_sub(2)).max(2);
+        let parts = [
+            ("top", top, &tops[index], Resolution::new(target.width, top_height)),
+            ("bottom", bottom, &bottoms[index], Resolution::new(target.width, target.height - top_height)),
+        ];
+        
+        // Исходники могут отличаться форматом пикселей, а vstack требует одинаковый
+        for (name, source, stream, region) in parts {
+            let cover = scale_to_cover(source, region);
+            chains.push(format!(
+                "[{}]scale={}:{},crop={}:{},setsar=1,format=yuv420p{}[{}{}]",
+                stream, cover.width, cover.height, region.width, region.height, fps, name, s
+            ));
+        }
+        chains.push(format!("[top{s}][bottom{s}]vstack=inputs=2:shortest=1[out{s}]", s = s));
+    }
+    
+    chains.join(";")
+}
+
+/// Исходник, звук которого идет в клип: путь, номер дорожки среди звуковых и результат ffprobe
+///
+/// Для разделенного экрана это верхний или нижний исходник по `SplitAudio`.
+/// `None` - смешиваются оба исходника (`SplitAudio::Mix`, звук есть в обоих);
+/// если звук есть только в одном из них, берется он.
+pub fn voice_source<'a>(
+    task: &'a FileTask,
+    media_info: &'a MediaInfo,
+    settings: &RenderSettings,
+) -> Option<(&'a Path, usize, &'a MediaInfo)> {
+    let top = (task.input.as_path(), task.audio_track.unwrap_or(0), media_info);
+    let (audio, bottom) = match (&settings.split, &task.split_input, &task.split_media_info) {
+        (Some(split), Some(path), Some(media)) => (split.audio, (path.as_path(), 0, media)),
+        _ => return Some(top),
+    };
+    
+    match audio {
+        SplitAudio::Top => Some(top),
+        SplitAudio::Bottom => Some(bottom),
+        SplitAudio::Mix if media_info.has_audio() && bottom.2.has_audio() => None,
+        SplitAudio::Mix if bottom.2.has_audio() => Some(bottom),
+        SplitAudio::Mix => Some(top),
+    }
+}
+
+/// Срезает черные полосы потока до компоновки, добавляя `crop` с выходом `label` в граф
+///
+/// Без обрезки используется сам поток.
+fn crop_stream(stream: &str, crop: Option<CropRect>, label: &str, chains: &mut Vec<String>) -> String {
+    match crop {
+        Some(crop) => {
+            chains.push(format!("[{}]crop={}[{}]", stream, crop, label));
+            label.to_string()
+        }
+        None => stream.to_string(),
+    }
+}
+
+/// Раздает поток по меткам, при необходимости добавляя `split` (`asplit` для звука) в граф
+///
+/// Для единственного потребителя `split` не нужен - используется сам поток.
+fn split_stream(stream: &str, filter: &str, labels: &[String], chains: &mut Vec<String>) -> Vec<String> {
+    match labels.len() {
+        0 => Vec::new(),
+        1 => vec![stream.to_string()],
+        count => {
+            let outputs: String = labels.iter().map(|l| format!("[{}]", l)).collect();
+            chains.push(format!("[{}]{}={}{}", stream, filter, count, outputs));
+            labels.to_vec()
+        }
+    }
+}
+
+/// Размер, при котором исходник полностью закрывает целевой кадр
+fn scale_to_cover(source: Resolution, target: Resolution) -> Resolution {
+    let factor = (target.width as f64 / source.width as f64)
+        .max(target.height as f64 / source.height as f64);
+    
+    Resolution::new(
+        even_ceil(source.width as f64 * factor).max(target.width),
+        even_ceil(source.height as f64 * factor).max(target.height),
+    )
+}
+
+/// Размер, при котором исходник целиком помещается в целевой кадр
+fn scale_to_fit(source: Resolution, target: Resolution) -> Resolution {
+    let factor = (target.width as f64 / source.width as f64)
+        .min(target.height as f64 / source.height as f64);
+    
+    Resolution::new(
+        even_round(source.width as f64 * factor).min(target.width),
+        even_round(source.height as f64 * factor).min(target.height),
+    )
+}
+
+/// Округляет размер вверх до четного (требование yuv420p)
+fn even_ceil(value: f64) -> u32 {
+    let value = value.ceil() as u32;
+    (value + value % 2).max(2)
+}
+
+/// Округляет размер до ближайшего четного
+fn even_round(value: f64) -> u32 {
+    ((value / 2.0).round() as u32 * 2).max(2)
+}
+
+/// Длительность результата: фрагмент или исходник целиком
+fn clip_duration(task: &FileTask, media_info: &MediaInfo) -> f64 {
+    task.segment.map_or(media_info.duration, |segment| segment.duration())
+}
+
 /// Строит аргументы для FFmpeg команды
-fn build_ffmpeg_args(input_path: &Path, output_path: &Path) -> Vec<String> {
-    let input_str = input_path.to_string_lossy().to_string();
-    let output_str = output_path.to_string_lossy().to_string();
-    
-    vec![
-        "-i".to_string(),
-        input_str.clone(),
-        "-i".to_string(),
-        input_str,
-        "-filter_complex".to_string(),
-        FFMPEG_FILTER_COMPLEX.to_string(),
-        "-map".to_string(),
-        "[out]".to_string(),
-        "-map".to_string(),
-        "0:a".to_string(),
+///
+/// Выходы задачи идут в том же порядке, что и `settings.variants`.
+/// Для фрагмента `-ss`/`-t` ставятся перед `-i`, чтобы FFmpeg искал по ключевым кадрам.
+/// Субтитры задачи вшиваются в каждый выход после компоновки кадра: пословные
+/// (ASS своего выхода), иначе файл рядом с исходником или результат распознавания.
+/// Из исходника берется одна звуковая дорожка - `task.audio_track` (по умолчанию первая).
+/// Если у исходника нет звука, дорожка либо не мапится, либо берется из `anullsrc`
+/// (вторым входом), в зависимости от `settings.missing_audio`.
+/// Заставка и финальная карточка склеиваются с оформленным клипом через `concat`.
+/// Второй исходник разделенного экрана - следующий вход с тем же фрагментом;
+/// смесь звука обоих исходников собирается в графе.
+fn build_ffmpeg_args(task: &FileTask, media_info: &MediaInfo, settings: &RenderSettings) -> Vec<String> {
+    let input_str = task.input.to_string_lossy().to_string();
+    let output_paths = &task.outputs;
+    
+    // Компоновка считается по кадру без черных полос
+    let source = task
+        .crop_rect()
+        .map_or(Resolution::new(media_info.width(), media_info.height()), |crop| crop.resolution());
+    let targets: Vec<Resolution> = settings.variants.iter().map(|v| v.resolution).collect();
+    
+    let mut args = vec![
         "-y".to_string(), // Перезаписывать выходные файлы без запроса
-        output_str,
-    ]
+    ];
+    
+    if let Some(segment) = task.segment {
+        args.extend([
+            "-ss".to_string(),
+            format_seconds(segment.start),
+            "-t".to_string(),
+            format_seconds(segment.duration()),
+        ]);
+    }
+    
+    args.extend(["-i".to_string(), input_str]);
+    
+    let duration = clip_duration(task, media_info);
+    let mut input_count = 1;
+    let music = settings.music.as_ref().zip(task.music.as_ref());
+    
+    // Второй исходник разделенного экрана начинается с того же места, что и первый
+    let split = match (&settings.split, &task.split_input, &task.split_media_info) {
+        (Some(split), Some(path), Some(split_media)) => {
+            let start = task.segment.map(|segment| format_seconds(segment.start));
+            let options: Vec<&str> = start.iter().flat_map(|start| ["-ss", start.as_str()]).collect();
+            let input = push_extra_input(&mut args, &mut input_count, &options, duration, &path.to_string_lossy());
+            Some((split, input, split_media))
+        }
+        _ => None,
+    };
+    
+    // Голос - выбранная дорожка исходника (в разделенном экране - верхнего, нижнего или их смесь);
+    // тишина из anullsrc нужна, только если нет и музыки
+    let mut voice_chains = Vec::new();
+    let source_voice = match voice_source(task, media_info, settings) {
+        Some((_, _, media)) if !media.has_audio() => None,
+        Some((path, track, _)) => match split {
+            Some((_, input, _)) if path != task.input => Some(format!("{}:a:{}", input, track)),
+            _ => Some(format!("0:a:{}", track)),
+        },
+        None => {
+            let bottom = split.map_or(0, |(_, input, _)| input);
+            voice_chains.push(format!(
+                "[0:a:{}][{}:a:0]amix=inputs=2:duration=shortest:normalize=0[splitmix]",
+                task.audio_track.unwrap_or(0),
+                bottom
+            ));
+            Some("splitmix".to_string())
+        }
+    };
+    let source_audio = source_voice.is_some();
+    let voice = if source_audio {
+        source_voice
+    } else if settings.missing_audio == MissingAudio::Silence && music.is_none() {
+        let index = push_extra_input(&mut args, &mut input_count, &["-f", "lavfi"], duration, SILENT_AUDIO_SOURCE);
+        Some(format!("{}:a", index))
+    } else {
+        None
+    };
+    let extra_audio = !source_audio && voice.is_some();
+    
+    // Фон: преобладающий цвет измерен заранее, картинка и видео фона - дополнительные входы
+    let mut background_input = |options: &[&str], path: &Path| {
+        Background::Input(push_extra_input(&mut args, &mut input_count, options, duration, &path.to_string_lossy()))
+    };
+    let background = match &settings.background {
+        _ if split.is_some() || !needs_background(source, &targets) => Background::Blur,
+        _ if settings.reframe => task.reframe.as_ref().map_or(Background::Blur, Background::Crop),
+        BackgroundMode::Blur => Background::Blur,
+        BackgroundMode::Color(color) => Background::Color(*color),
+        BackgroundMode::Dominant => task.background_color.map_or(Background::Blur, Background::Color),
+        BackgroundMode::Mirror => Background::Mirror,
+        BackgroundMode::Gradient(top, bottom) => Background::Gradient(*top, *bottom),
+        BackgroundMode::Image(path) => {
+            // Зацикленная картинка по умолчанию идет с 25 fps; частота задается по исходнику
+            let framerate = media_info.fps().map(format_fps);
+            let mut options: Vec<&str> = framerate.iter().flat_map(|rate| ["-framerate", rate.as_str()]).collect();
+            options.extend(["-loop", "1"]);
+            background_input(&options, path)
+        }
+        BackgroundMode::Video(path) => background_input(&["-stream_loop", "-1"], path),
+    };
+    
+    // Черные полосы срезаются до компоновки: графы получают обрезанные потоки
+    let mut chains = Vec::new();
+    let source_stream = crop_stream("0:v", task.crop_rect(), "cropsrc", &mut chains);
+    let composed = match split {
+        Some((split, input, split_media)) => {
+            let bottom = task
+                .split_crop
+                .map_or(Resolution::new(split_media.width(), split_media.height()), |crop| crop.resolution());
+            let bottom_stream = crop_stream(&format!("{}:v", input), task.split_crop, "cropbottom", &mut chains);
+            build_split_filter_complex(source, &source_stream, bottom, &bottom_stream, &targets, split.ratio, media_info.fps())
+        }
+        None => build_filter_complex(source, &source_stream, &targets, background, media_info.fps()),
+    };
+    chains.push(composed);
+    let mut graph = chains.join(";");
+    
+    // Видео каждого выхода: выход компоновки или, с логотипом, заголовком и субтитрами, выход их цепочки
+    let mut video_streams: Vec<String> = (0..output_paths.len())
+        .map(|index| if output_paths.len() == 1 { "out".to_string() } else { format!("out{}", index) })
+        .collect();
+    // Файл рядом с исходником размечен по времени исходника, распознанный - по времени клипа
+    let subtitles = match (&task.subtitles, &task.transcript) {
+        (Some(path), _) => Some((path, task.segment.map(|segment| segment.start))),
+        (None, Some(path)) => Some((path, None)),
+        (None, None) => None,
+    };
+    let subtitles_filter = subtitles.map(|(path, offset)| build_subtitles_filter(path, &settings.subtitles, offset));
+    let title = settings.title.as_ref().map(|title| (title, render_title(title, task)));
+    let watermarks = match &settings.watermark {
+        Some(watermark) => {
+            let input = push_extra_input(&mut args, &mut input_count, &[], 0.0, &watermark.path.to_string_lossy());
+            let mut chains = Vec::new();
+            let streams = build_watermark_chains(input, watermark, &targets, &mut chains);
+            graph.push(';');
+            graph.push_str(&chains.join(";"));
+            streams
+        }
+        None => Vec::new(),
+    };
+    for (index, stream) in video_streams.iter_mut().enumerate() {
+        let mut inputs = format!("[{}]", stream);
+        let mut filters = Vec::new();
+        if let (Some(watermark), Some(logo)) = (&settings.watermark, watermarks.get(index)) {
+            let margin = (targets[index].width as f64 * watermark.margin).round() as u32;
+            let (x, y) = watermark.position.overlay_position(margin);
+            inputs.push_str(&format!("[{}]", logo));
+            filters.push(format!("overlay={}:{}", x, y));
+        }
+        if let Some(filter) = title.as_ref().and_then(|(title, text)| build_title_filter(text, title, targets[index])) {
+            filters.push(filter);
+        }
+        match task.karaoke.get(index) {
+            Some(ass) => filters.push(format!("ass=filename={}", escape_filter_value(&ass.to_string_lossy()))),
+            None => filters.extend(subtitles_filter.clone()),
+        }
+        if filters.is_empty() {
+            continue;
+        }
+        
+        let label = format!("sub{}", index);
+        graph.push_str(&format!(";{}{}[{}]", inputs, filters.join(","), label));
+        *stream = label;
+    }
+    
+    // Звук каждого выхода: прямой map дорожки или выход аудиоцепочки графа
+    let (audio_streams, audio_in_graph) = match music {
+        Some((music_settings, track)) => {
+            let track = track.to_string_lossy();
+            let music_input = push_extra_input(&mut args, &mut input_count, &["-stream_loop", "-1"], duration, &track);
+            // Нормализуется только голос: первый проход измерял его, музыка ложится поверх
+            let voice_filter = settings
+                .loudnorm
+                .as_ref()
+                .filter(|_| source_audio)
+                .map(|target| build_loudnorm_filter(target, task.loudnorm.as_ref()));
+            
+            let mut chains = std::mem::take(&mut voice_chains);
+            let mixed = build_music_chains(
+                voice.as_deref(),
+                voice_filter.as_deref(),
+                music_input,
+                music_settings,
+                duration,
+                &mut chains,
+            );
+            let labels: Vec<String> = (0..output_paths.len()).map(|index| format!("aout{}", index)).collect();
+            let streams = split_stream(&mixed, "asplit", &labels, &mut chains);
+            
+            graph.push(';');
+            graph.push_str(&chains.join(";"));
+            (streams.into_iter().map(|label| format!("[{}]", label)).collect(), true)
+        }
+        // Смесь разделенного экрана - выход графа: громкость выравнивается в графе, а не через -af
+        None if !voice_chains.is_empty() => {
+            let mut chains = std::mem::take(&mut voice_chains);
+            let mut mixed = voice.clone().unwrap_or_default();
+            if let Some(target) = &settings.loudnorm {
+                chains.push(format!("[{}]{}[splitnorm]", mixed, build_loudnorm_filter(target, task.loudnorm.as_ref())));
+                mixed = "splitnorm".to_string();
+            }
+            let labels: Vec<String> = (0..output_paths.len()).map(|index| format!("aout{}", index)).collect();
+            let streams = split_stream(&mixed, "asplit", &labels, &mut chains);
+            
+            graph.push(';');
+            graph.push_str(&chains.join(";"));
+            (streams.into_iter().map(|label| format!("[{}]", label)).collect(), true)
+        }
+        None => (vec![voice.clone().unwrap_or_default(); output_paths.len()], false),
+    };
+    
+    // Заставка и финальная карточка приклеиваются к каждому выходу после оформления кадра
+    let (audio_streams, audio_in_graph) = if settings.intro.is_some() || settings.outro.is_some() {
+        let with_audio = audio_streams.iter().any(|stream| !stream.is_empty());
+        let fps = media_info.fps();
+        let mut chains = Vec::new();
+        let mut bumper = |name: &str, clip: &Option<BrandClip>, chains: &mut Vec<String>| {
+            clip.as_ref().map(|clip| {
+                let input = push_extra_input(&mut args, &mut input_count, &[], 0.0, &clip.path.to_string_lossy());
+                build_bumper_chains(name, input, clip, &targets, fps, with_audio, chains)
+            })
+        };
+        let intro = bumper("intro", &settings.intro, &mut chains);
+        let outro = bumper("outro", &settings.outro, &mut chains);
+        
+        // Без музыки громкость голоса выравнивается здесь: -af не применим к выходу графа
+        let voice_filter = settings
+            .loudnorm
+            .as_ref()
+            .filter(|_| source_audio && !audio_in_graph)
+            .map(|target| format!("{},", build_loudnorm_filter(target, task.loudnorm.as_ref())))
+            .unwrap_or_default();
+        
+        let mut streams = Vec::new();
+        for (index, video) in video_streams.iter_mut().enumerate() {
+            // Клип приводится к той же постоянной частоте, что заставка и карточка:
+            // у переменной частоты исходника concat склеил бы части с разной временной базой
+            let mut main = match fps {
+                Some(fps) => {
+                    chains.push(format!("[{}]fps={}[mainv{}]", video, format_fps(fps), index));
+                    format!("[mainv{}]", index)
+                }
+                None => format!("[{}]", video),
+            };
+            if with_audio {
+                let audio = &audio_streams[index];
+                let source = if audio.starts_with('[') { audio.clone() } else { format!("[{}]", audio) };
+                chains.push(format!("{}{}{}[maina{}]", source, voice_filter, concat_audio_format(duration), index));
+                main.push_str(&format!("[maina{}]", index));
+            }
+            
+            let parts: Vec<&String> = intro
+                .iter()
+                .map(|labels| &labels[index])
+                .chain(std::iter::once(&main))
+                .chain(outro.iter().map(|labels| &labels[index]))
+                .collect();
+            let audio_label = format!("cata{}", index);
+            chains.push(format!(
+                "{}concat=n={}:v=1:a={}[catv{}]{}",
+                parts.iter().map(|part| part.as_str()).collect::<String>(),
+                parts.len(),
+                u8::from(with_audio),
+                index,
+                if with_audio { format!("[{}]", audio_label) } else { String::new() }
+            ));
+            
+            *video = format!("catv{}", index);
+            streams.push(if with_audio { format!("[{}]", audio_label) } else { String::new() });
+        }
+        
+        graph.push(';');
+        graph.push_str(&chains.join(";"));
+        (streams, with_audio)
+    } else {
+        (audio_streams, audio_in_graph)
+    };
+    
+    args.push("-filter_complex".to_string());
+    args.push(graph);
+    
+    debug_assert_eq!(output_paths.len(), targets.len());
+    for (index, output_path) in output_paths.iter().enumerate() {
+        args.extend(["-map".to_string(), format!("[{}]", video_streams[index])]);
+        match audio_streams.get(index).filter(|stream| !stream.is_empty()) {
+            Some(audio) => args.extend(["-map".to_string(), audio.clone()]),
+            None => args.push("-an".to_string()),
+        }
+        if let (false, true, Some(target)) = (audio_in_graph, source_audio, &settings.loudnorm) {
+            args.extend(["-af".to_string(), build_loudnorm_filter(target, task.loudnorm.as_ref())]);
+        }
+        if extra_audio || audio_in_graph {
+            // Тишина и зацикленная музыка не должны удлинять клип, если длительность неизвестна
+            args.push("-shortest".to_string());
+        }
+        args.push(output_path.to_string_lossy().to_string());
+    }
+    
+    args
+}
+
+/// Добавляет дополнительный вход (`anullsrc`, музыка) и возвращает его номер
+///
+/// При известной длительности клипа вход обрезается по ней через `-t`.
+fn push_extra_input(args: &mut Vec<String>, input_count: &mut usize, options: &[&str], duration: f64, source: &str) -> usize {
+    args.extend(options.iter().map(|option| option.to_string()));
+    if duration > 0.0 {
+        args.extend(["-t".to_string(), format_seconds(duration)]);
+    }
+    args.extend(["-i".to_string(), source.to_string()]);
+    
+    *input_count += 1;
+    *input_count - 1
+}
+
+/// Строит аудиоцепочки музыкальной подложки и возвращает метку итогового звука
+///
+/// Музыка приводится к общему формату, приглушается по громкости, плавно
+/// появляется и затухает. Если есть голос, музыка проседает под ним через
+/// `sidechaincompress` (голос - управляющий сигнал) и смешивается с ним;
+/// длительность результата - по голосу.
+fn build_music_chains(
+    voice: Option<&str>,
+    voice_filter: Option<&str>,
+    music_input: usize,
+    music: &MusicSettings,
+    duration: f64,
+    chains: &mut Vec<String>,
+) -> String {
+    let mut music_chain = format!(
+        "[{}:a]aformat=sample_rates={}:channel_layouts=stereo,volume={},afade=t=in:st=0:d={}",
+        music_input, LOUDNORM_SAMPLE_RATE, music.volume, music.fade
+    );
+    if duration > music.fade * 2.0 {
+        music_chain.push_str(&format!(
+            ",afade=t=out:st={}:d={}",
+            format_seconds(duration - music.fade),
+            music.fade
+        ));
+    }
+    
+    chains.push(format!("{}[music]", music_chain));
+    
+    let voice = match voice {
+        Some(voice) => voice,
+        None => return "music".to_string(),
+    };
+    
+    let voice_filter = voice_filter.map(|filter| format!("{},", filter)).unwrap_or_default();
+    chains.push(format!("[{}]{}asplit=2[voice][sc]", voice, voice_filter));
+    chains.push(format!("[music][sc]{}[ducked]", MUSIC_DUCKING_FILTER));
+    chains.push("[voice][ducked]amix=inputs=2:duration=first:dropout_transition=0:normalize=0[mix]".to_string());
+    
+    "mix".to_string()
+}
+
+/// Строит фильтр `subtitles` для SRT/VTT с заданным оформлением
+///
+/// libass рендерит такие субтитры в сетке высотой `SUBTITLE_PLAY_RES_Y`, поэтому
+/// размеры стиля переводятся в нее и не зависят от разрешения выхода. Текст
+/// выравнивается по центру снизу над зоной интерфейса платформы.
+///
+/// Субтитры размечены по времени исходника, а кадры фрагмента начинаются с нуля:
+/// при `offset` кадры на время фильтра сдвигаются на начало фрагмента.
+pub fn build_subtitles_filter(path: &Path, style: &SubtitleStyle, offset: Option<f64>) -> String {
+    let scale = SUBTITLE_PLAY_RES_Y / SUBTITLE_REFERENCE_HEIGHT;
+    let round = |value: f64| (value * 100.0).round() / 100.0;
+    
+    let force_style = format!(
+        "FontName={},FontSize={},PrimaryColour={},OutlineColour={},BorderStyle=1,Outline={},Shadow=0,Alignment=2,MarginV={}",
+        style.font,
+        round(style.size * scale),
+        ass_color(style.color),
+        ass_color(style.outline_color),
+        round(style.outline * scale),
+        (style.safe_zone * SUBTITLE_PLAY_RES_Y).round() as u32,
+    );
+    let filter = format!(
+        "subtitles=filename={}:force_style={}",
+        escape_filter_value(&path.to_string_lossy()),
+        escape_filter_value(&force_style)
+    );
+    
+    match offset.filter(|offset| *offset > 0.0) {
+        Some(offset) => format!("setpts=PTS+{}/TB,{},setpts=PTS-STARTPTS", format_seconds(offset), filter),
+        None => filter,
+    }
+}
+
+/// Готовит заставку или финальную карточку для склейки с каждым выходом
+///
+/// Кадр вписывается в выход с полями, частота кадров приводится к исходнику.
+/// Звук приводится к общему формату и выравнивается по длительности клипа;
+/// клип без звука получает тишину. Возвращает входы `concat` каждого выхода:
+/// `[видео]` или `[видео][звук]`.
+fn build_bumper_chains(
+    name: &str,
+    input: usize,
+    clip: &BrandClip,
+    targets: &[Resolution],
+    fps: Option<f64>,
+    with_audio: bool,
+    chains: &mut Vec<String>,
+) -> Vec<String> {
+    let labels: Vec<String> = (0..targets.len()).map(|index| format!("{}src{}", name, index)).collect();
+    let sources = split_stream(&format!("{}:v", input), "split", &labels, chains);
+    
+    let audio = if with_audio {
+        let duration = clip.media.duration;
+        let source = if clip.media.has_audio() {
+            format!("[{}:a:0]{}", input, concat_audio_format(duration))
+        } else {
+            format!("{},atrim=duration={}", SILENT_AUDIO_SOURCE, format_seconds(duration))
+        };
+        chains.push(format!("{}[{}asrc]", source, name));
+        let labels: Vec<String> = (0..targets.len()).map(|index| format!("{}a{}", name, index)).collect();
+        split_stream(&format!("{}asrc", name), "asplit", &labels, chains)
+    } else {
+        Vec::new()
+    };
+    
+    let source = Resolution::new(clip.media.width(), clip.media.height());
+    let fps = fps_filter(fps);
+    sources
+        .iter()
+        .zip(targets)
+        .enumerate()
+        .map(|(index, (stream, target))| {
+            let fit = scale_to_fit(source, *target);
+            chains.push(format!(
+                "[{}]scale={}:{},pad={}:{}:(ow-iw)/2:(oh-ih)/2,setsar=1{}[{}v{}]",
+                stream, fit.width, fit.height, target.width, target.height, fps, name, index
+            ));
+            let audio = audio.get(index).map(|label| format!("[{}]", label)).unwrap_or_default();
+            format!("[{}v{}]{}", name, index, audio)
+        })
+        .collect()
+}
+
+/// Фильтр `fps` с частотой кадров исходника для конца цепочки (`,fps=30`); без частоты - пусто
+fn fps_filter(fps: Option<f64>) -> String {
+    fps.map(|fps| format!(",fps={}", format_fps(fps))).unwrap_or_default()
+}
+
+/// Частота кадров для аргументов FFmpeg: до тысячных (`29.97`, `30`)
+fn format_fps(fps: f64) -> String {
+    ((fps * 1000.0).round() / 1000.0).to_string()
+}
+
+/// Формат звука частей склейки: общие частота и раскладка, длительность - по видео части
+fn concat_audio_format(duration: f64) -> String {
+    let mut filter = format!("aformat=sample_rates={}:channel_layouts=stereo", LOUDNORM_SAMPLE_RATE);
+    if duration > 0.0 {
+        filter.push_str(&format!(",apad,atrim=duration={}", format_seconds(duration)));
+    }
+    filter
+}
+
+/// Строит цепочки подготовки водяного знака и возвращает метки логотипа для каждого выхода
+///
+/// Изображение декодируется один раз, получает прозрачность `opacity`
+/// и масштабируется к ширине каждого выхода. `overlay` повторяет
+/// единственный кадр картинки до конца видео.
+fn build_watermark_chains(
+    input: usize,
+    watermark: &WatermarkSettings,
+    targets: &[Resolution],
+    chains: &mut Vec<String>,
+) -> Vec<String> {
+    let mut source = "format=rgba".to_string();
+    if watermark.opacity < 1.0 {
+        source.push_str(&format!(",colorchannelmixer=aa={}", watermark.opacity));
+    }
+    chains.push(format!("[{}:v]{}[wmsrc]", input, source));
+    
+    let labels: Vec<String> = (0..targets.len()).map(|index| format!("wmsrc{}", index)).collect();
+    let sources = split_stream("wmsrc", "split", &labels, chains);
+    
+    sources
+        .iter()
+        .zip(targets)
+        .enumerate()
+        .map(|(index, (source, target))| {
+            let label = format!("wm{}", index);
+            let width = even_ceil(target.width as f64 * watermark.scale);
+            chains.push(format!("[{}]scale={}:-1[{}]", source, width, label));
+            label
+        })
+        .collect()
+}
+
+/// Текст заголовка задачи по шаблону: имя исходника, заголовок из списка нарезки, метка части
+pub fn render_title(title: &TitleSettings, task: &FileTask) -> String {
+    let stem = task.input.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    title.render(&stem, task.title.as_deref(), task.part.as_deref())
+}
+
+/// Строит цепочку `drawtext` заголовка для кадра заданного размера
+///
+/// `drawtext` не переносит строки сам: текст разбивается по словам под ширину
+/// кадра (по средней ширине символа), каждая строка - отдельный фильтр,
+/// отцентрованный по горизонтали. Подстановки `%{...}` отключены, текст
+/// выводится как есть. `None` - заголовок пустой.
+pub fn build_title_filter(text: &str, title: &TitleSettings, frame: Resolution) -> Option<String> {
+    let scale = frame.height as f64 / SUBTITLE_REFERENCE_HEIGHT;
+    let size = (title.size * scale).round().max(1.0);
+    let width = frame.width as f64 * (1.0 - 2.0 * TITLE_SIDE_MARGIN);
+    let max_chars = ((width / (size * TITLE_CHAR_WIDTH)).floor() as usize).max(1);
+    
+    let lines = wrap_text(text, max_chars, TITLE_MAX_LINES);
+    if lines.is_empty() {
+        return None;
+    }
+    
+    let font = title
+        .font_file
+        .as_ref()
+        .map(|path| format!("fontfile={}:", escape_filter_value(&path.to_string_lossy())))
+        .unwrap_or_default();
+    let top = frame.height as f64 * TITLE_TOP_MARGIN;
+    let filters: Vec<String> = lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            format!(
+                "drawtext={}text={}:expansion=none:fontsize={}:fontcolor=white:borderw={}:bordercolor=black:\
+                 x=(w-text_w)/2:y={}",
+                font,
+                escape_filter_value(line),
+                size,
+                (TITLE_BORDER * scale).round().max(1.0),
+                (top + index as f64 * size * TITLE_LINE_HEIGHT).round()
+            )
+        })
+        .collect();
+    
+    Some(filters.join(","))
+}
+
+/// Переносит текст по словам в строки не длиннее `max_chars` символов
+///
+/// Слишком длинные слова режутся, строки сверх `max_lines` отбрасываются,
+/// а последняя оставшаяся заканчивается многоточием.
+pub fn wrap_text(text: &str, max_chars: usize, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    
+    for word in text.split_whitespace() {
+        let chars: Vec<char> = word.chars().collect();
+        for piece in chars.chunks(max_chars) {
+            let piece: String = piece.iter().collect();
+            let length = current.chars().count();
+            if length == 0 {
+                current = piece;
+            } else if length + 1 + piece.chars().count() <= max_chars {
+                current.push(' ');
+                current.push_str(&piece);
+            } else {
+                lines.push(std::mem::replace(&mut current, piece));
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            let kept: String = last.chars().take(max_chars.saturating_sub(1)).collect();
+            *last = format!("{}…", kept.trim_end());
+        }
+    }
+    
+    lines
+}
+
+/// Экранирует значение опции фильтра для `-filter_complex`
+///
+/// Экранирование двухуровневое: сначала для разбора опций фильтра
+/// (`\`, `'`, `:`), затем для разбора самого графа (`\`, `'`, `[`, `]`, `,`, `;`).
+pub fn escape_filter_value(value: &str) -> String {
+    let escape = |value: &str, special: &[char]| {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    
+    let option = escape(value, &['\\', '\'', ':']);
+    escape(&option, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+/// Строит второй проход `loudnorm` по статистике первого
+///
+/// С измерениями фильтр работает в линейном режиме и точно попадает в цель;
+/// без них остается однопроходная динамическая нормализация. `loudnorm`
+/// всегда выдает 192 кГц, поэтому звук пересэмплируется обратно.
+pub fn build_loudnorm_filter(target: &LoudnessTarget, measured: Option<&LoudnormStats>) -> String {
+    let mut filter = format!("loudnorm=I={}:TP={}:LRA={}", target.integrated, target.true_peak, target.range);
+    
+    if let Some(stats) = measured {
+        filter.push_str(&format!(
+            ":measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+            stats.input_i, stats.input_tp, stats.input_lra, stats.input_thresh, stats.target_offset
+        ));
+    }
+    
+    format!("{}:print_format=json,aresample={}", filter, LOUDNORM_SAMPLE_RATE)
+}
+
+/// Рассчитывает таймаут FFmpeg по длительности результата
+///
+/// Длинные исходники получают пропорционально больше времени,
+/// но не меньше базового `FFMPEG_TIMEOUT`.
+pub fn timeout_for_duration(seconds: f64) -> Duration {
+    let scaled = Duration::from_secs_f64(seconds.max(0.0)) * FFMPEG_TIMEOUT_PER_MEDIA_SECOND;
+    scaled.max(FFMPEG_TIMEOUT)
 }
 
 /// Строит строковое представление FFmpeg команды для логирования
-fn build_ffmpeg_command_string(input_path: &Path, output_path: &Path) -> String {
-    let args = build_ffmpeg_args(input_path, output_path);
+fn build_ffmpeg_command_string(args: &[String]) -> String {
     format!("{} {}", FFMPEG_EXECUTABLE, args.join(" "))
 }
 
@@ -277,51 +1435,815 @@ pub fn validate_input_file(path: &Path) -> FfmpegResult<()> {
     Ok(())
 }
 
-/// Оценивает примерный размер выходного файла на основе входного
-pub async fn estimate_output_size(input_path: &Path) -> FfmpegResult<u64> {
-    match tokio::fs::metadata(input_path).await {
-        Ok(metadata) => {
-            let input_size = metadata.len();
-            // Примерная оценка: выходной файл будет примерно 80-120% от размера входного
-            // из-за изменения разрешения и компрессии
-            Ok((input_size as f64 * 1.0) as u64)
-        }
-        Err(_) => Err(FfmpegError::invalid_format(input_path.to_path_buf())),
-    }
+/// Оценивает примерный размер выходного файла по данным ffprobe
+pub fn estimate_output_size(media_info: &MediaInfo) -> u64 {
+    // Примерная оценка: битрейт выходного файла сопоставим с битрейтом входного
+    // из-за изменения разрешения и компрессии
+    let bit_rate = media_info.bit_rate.unwrap_or(crate::config::FALLBACK_BIT_RATE);
+    (bit_rate as f64 * media_info.duration / 8.0) as u64
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{BackgroundMode, SourceCrop, WatermarkPosition};
+    use crate::utils::TimeRange;
     use tempfile::TempDir;
     use std::fs::File;
     
+    /// Граф фильтров из аргументов FFmpeg (значение `-filter_complex`)
+    fn filter_graph(args: &[String]) -> &str {
+        &args[args.iter().position(|a| a == "-filter_complex").unwrap() + 1]
+    }
+    
+    /// Настройки с двумя форматами: вертикальным 9:16 и квадратом 1:1
+    fn two_variant_settings() -> RenderSettings {
+        RenderSettings {
+            variants: vec![
+                OutputVariant { label: "9x16".to_string(), resolution: Resolution::new(1080, 1920) },
+                OutputVariant { label: "1x1".to_string(), resolution: Resolution::new(1080, 1080) },
+            ],
+            ..RenderSettings::default()
+        }
+    }
+    
     #[test]
     fn test_build_ffmpeg_args() {
-        let input = PathBuf::from("input.mp4");
-        let output = PathBuf::from("output.mp4");
+        let task = FileTask::new(PathBuf::from("input.mp4"), vec![PathBuf::from("output.mp4")]);
         
-        let args = build_ffmpeg_args(&input, &output);
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true);
         
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        
+        assert!(!args.contains(&"-ss".to_string()));
         assert!(args.contains(&"-i".to_string()));
         assert!(args.contains(&"input.mp4".to_string()));
         assert!(args.contains(&"output.mp4".to_string()));
         assert!(args.contains(&"-filter_complex".to_string()));
-        assert!(args.contains(&FFMPEG_FILTER_COMPLEX.to_string()));
+        assert!(args.contains(&build_filter_complex(Resolution::new(1920, 1080), "0:v", &[DEFAULT_RESOLUTION], Background::Blur, None)));
+    }
+    
+    #[test]
+    fn test_filter_complex_for_16_9_source() {
+        let graph = build_filter_complex(Resolution::new(1920, 1080), "0:v", &[DEFAULT_RESOLUTION], Background::Blur, None);
+        
+        assert_eq!(
+            graph,
+            "[0:v]split=2[src][fgsrc];[src]scale=2276:1280,crop=720:1280,boxblur=4[bg];[fgsrc]scale=720:406[fg];[bg][fg]overlay=(W-w)/2:(H-h)/2,setsar=1[out]"
+        );
+    }
+    
+    #[test]
+    fn test_standard_layout_decodes_input_once() {
+        // Раньше вход открывался и декодировался дважды (`-i input -i input`);
+        // теперь на один запуск приходится ровно одно декодирование
+        let task = FileTask::new(PathBuf::from("input.mp4"), vec![PathBuf::from("output.mp4")]);
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true);
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        
+        assert_eq!(args.iter().filter(|a| *a == "-i").count(), 1);
+        assert_eq!(args.iter().filter(|a| *a == "input.mp4").count(), 1);
+        
+        let graph = filter_graph(&args);
+        assert_eq!(graph.matches("[0:v]").count(), 1);
+        assert!(!graph.contains("[1:v]"));
+    }
+    
+    #[test]
+    fn test_layout_selection() {
+        let target = DEFAULT_RESOLUTION;
+        
+        assert_eq!(FrameLayout::choose(Resolution::new(1920, 1080), target), FrameLayout::BlurredBackground);
+        assert_eq!(FrameLayout::choose(Resolution::new(1440, 1080), target), FrameLayout::BlurredBackground);
+        assert_eq!(FrameLayout::choose(Resolution::new(2560, 1080), target), FrameLayout::BlurredBackground);
+        assert_eq!(FrameLayout::choose(Resolution::new(1080, 1080), target), FrameLayout::BlurredBackground);
+        assert_eq!(FrameLayout::choose(Resolution::new(1080, 1920), target), FrameLayout::ScaleOnly);
+        assert_eq!(FrameLayout::choose(Resolution::new(1080, 1440), target), FrameLayout::Fit);
+    }
+    
+    #[test]
+    fn test_background_covers_and_foreground_keeps_aspect() {
+        let target = DEFAULT_RESOLUTION;
+        
+        for source in [
+            Resolution::new(1920, 1080),
+            Resolution::new(1440, 1080),
+            Resolution::new(2560, 1080),
+            Resolution::new(1080, 1080),
+            Resolution::new(1080, 1440),
+        ] {
+            let cover = scale_to_cover(source, target);
+            assert!(cover.width >= target.width && cover.height >= target.height);
+            
+            let fit = scale_to_fit(source, target);
+            assert!(fit.width <= target.width && fit.height <= target.height);
+            assert_eq!(fit.width % 2, 0);
+            assert_eq!(fit.height % 2, 0);
+            
+            // Погрешность только от округления до четных размеров
+            let distortion = (fit.aspect_ratio() / source.aspect_ratio() - 1.0).abs();
+            assert!(distortion < 0.01, "{} distorted to {}", source, fit);
+        }
+    }
+    
+    #[test]
+    fn test_vertical_source_is_not_blurred() {
+        let media = MediaInfo::synthetic(1080, 1920, 30.0, true);
+        let task = FileTask::new(PathBuf::from("in.mp4"), vec![PathBuf::from("out.mp4")]);
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        
+        assert_eq!(args.iter().filter(|a| *a == "-i").count(), 1);
+        assert!(args.contains(&"[0:v]scale=720:1280,setsar=1[out]".to_string()));
+        
+        let graph = build_filter_complex(Resolution::new(1080, 1440), "0:v", &[DEFAULT_RESOLUTION], Background::Blur, None);
+        assert!(!graph.contains("boxblur"));
+        assert!(graph.contains("pad=720:1280"));
+    }
+    
+    #[test]
+    fn test_rotated_phone_video_is_vertical() {
+        let task = FileTask::new(PathBuf::from("IMG_0042.MOV"), vec![PathBuf::from("out.mp4")]);
+        
+        // Кадр 1920x1080 с поворотом на 90 градусов декодируется вертикальным - фон не нужен
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true).rotated(90);
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        assert!(args.contains(&"[0:v]scale=720:1280,setsar=1[out]".to_string()));
+        
+        let media = MediaInfo::synthetic(1440, 1080, 30.0, true).rotated(270);
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        assert!(args.contains(&"[0:v]scale=720:960,pad=720:1280:(ow-iw)/2:(oh-ih)/2,setsar=1[out]".to_string()));
+        
+        // Перевернутый горизонтальный кадр остается горизонтальным
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true).rotated(180);
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        assert!(args.contains(&build_filter_complex(Resolution::new(1920, 1080), "0:v", &[DEFAULT_RESOLUTION], Background::Blur, None)));
+    }
+    
+    #[test]
+    fn test_filter_complex_for_custom_resolution() {
+        let target = Resolution::new(1080, 1920);
+        let settings = RenderSettings {
+            variants: vec![OutputVariant { label: target.to_string(), resolution: target }],
+            ..RenderSettings::default()
+        };
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true);
+        let task = FileTask::new(PathBuf::from("in.mp4"), vec![PathBuf::from("out.mp4")]);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        
+        let graph = build_filter_complex(Resolution::new(1920, 1080), "0:v", &[target], Background::Blur, None);
+        assert!(args.contains(&graph));
+        assert!(graph.contains("scale=3414:1920,crop=1080:1920"));
+        assert!(graph.contains("[fgsrc]scale=1080:608[fg]"));
+        
+        // Квадратный вывод из 16:9 - фон закрывает кадр по высоте
+        let square = build_filter_complex(Resolution::new(1920, 1080), "0:v", &[Resolution::new(1080, 1080)], Background::Blur, None);
+        assert!(square.contains("scale=1920:1080,crop=1080:1080"));
+        assert!(square.contains("[fgsrc]scale=1080:608[fg]"));
+    }
+    
+    #[test]
+    fn test_background_color_and_dominant() {
+        let task = FileTask::new(PathBuf::from("in.mp4"), vec![PathBuf::from("out.mp4")]);
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true);
+        let mut settings = RenderSettings { background: BackgroundMode::Color(0x112233), ..RenderSettings::default() };
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        
+        assert_eq!(args.iter().filter(|a| *a == "-i").count(), 1);
+        assert!(args.contains(&"[0:v]scale=720:406,pad=720:1280:(ow-iw)/2:(oh-ih)/2:color=0x112233,setsar=1[out]".to_string()));
+        
+        // Преобладающий цвет измеряется перед рендером; без измерения остается размытие
+        settings.background = BackgroundMode::Dominant;
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        assert!(args.contains(&build_filter_complex(Resolution::new(1920, 1080), "0:v", &[DEFAULT_RESOLUTION], Background::Blur, None)));
+        
+        let mut measured = task.clone();
+        measured.background_color = Some(0xA0B0C0);
+        let args = build_ffmpeg_args(&measured, &media, &settings);
+        assert!(args.contains(&"[0:v]scale=720:406,pad=720:1280:(ow-iw)/2:(oh-ih)/2:color=0xA0B0C0,setsar=1[out]".to_string()));
+    }
+    
+    #[test]
+    fn test_background_mirror() {
+        let task = FileTask::new(PathBuf::from("in.mp4"), vec![PathBuf::from("out.mp4")]);
+        let settings = RenderSettings { background: BackgroundMode::Mirror, ..RenderSettings::default() };
+        let args = build_ffmpeg_args(&task, &MediaInfo::synthetic(1920, 1080, 30.0, true), &settings);
+        
+        // Поля по 437 строк выше видео высотой 406 - по две копии с каждой стороны
+        assert!(args.contains(&"[0:v]scale=720:406,setsar=1,split=5[mir_0][mir_1][mir_2][mir_3][mir_4];\
+            [mir_1]vflip[mir_1f];[mir_3]vflip[mir_3f];\
+            [mir_0][mir_1f][mir_2][mir_3f][mir_4]vstack=inputs=5,crop=720:1280,setsar=1[out]".to_string()));
+        
+        // Поля слева и справа отражаются по горизонтали
+        let graph = build_filter_complex(Resolution::new(1440, 1080), "0:v", &[Resolution::new(1920, 1080)], Background::Mirror, None);
+        assert_eq!(
+            graph,
+            "[0:v]scale=1440:1080,setsar=1,split=3[mir_0][mir_1][mir_2];[mir_0]hflip[mir_0f];[mir_2]hflip[mir_2f];\
+             [mir_0f][mir_1][mir_2f]hstack=inputs=3,crop=1920:1080,setsar=1[out]"
+        );
+    }
+    
+    #[test]
+    fn test_reframe_crop() {
+        let mut task = FileTask::new(PathBuf::from("in.mp4"), vec![PathBuf::from("out.mp4")]);
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true);
+        let settings = RenderSettings { reframe: true, ..RenderSettings::default() };
+        
+        // Без траектории (движение не отслежено) остается размытый фон
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        assert!(args.contains(&build_filter_complex(Resolution::new(1920, 1080), "0:v", &[DEFAULT_RESOLUTION], Background::Blur, None)));
+        
+        // Окно 608x1080 едет от центра к правому краю за две секунды и упирается в край кадра
+        task.reframe = Some(ReframeTrack { points: vec![(0.5, 0.5), (2.5, 0.9), (4.5, 0.9)] });
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        assert!(args.contains(
+            &"[0:v]crop=w=608:h=1080:x='656+(328)*clip(t-0.500,0,2.000)':y=(ih-oh)/2,scale=720:1280,setsar=1[out]".to_string()
+        ));
+        
+        // Неподвижная траектория - постоянное окно; для более широкого формата режется высота
+        let still = ReframeTrack { points: vec![(0.5, 0.1)] };
+        let graph = build_filter_complex(Resolution::new(1440, 1080), "0:v", &[Resolution::new(1920, 1080)], Background::Crop(&still), None);
+        assert_eq!(graph, "[0:v]crop=w=1440:h=810:x='0':y=(ih-oh)/2,scale=1920:1080,setsar=1[out]");
+    }
+    
+    #[test]
+    fn test_crop_black_bars() {
+        let mut task = FileTask::new(PathBuf::from("in.mp4"), vec![PathBuf::from("out.mp4")]);
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true);
+        
+        // Полосы срезаются до split, компоновка считается по кадру 1920x800
+        task.crop = Some(SourceCrop::Rect(CropRect { width: 1920, height: 800, x: 0, y: 140 }));
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        assert!(args.contains(
+            &"[0:v]crop=1920:800:0:140[cropsrc];[cropsrc]split=2[src][fgsrc];\
+              [src]scale=3072:1280,crop=720:1280,boxblur=4[bg];[fgsrc]scale=720:300[fg];[bg][fg]overlay=(W-w)/2:(H-h)/2,setsar=1[out]"
+                .to_string()
+        ));
+        
+        task.crop = Some(SourceCrop::Full);
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        assert!(args.contains(&build_filter_complex(Resolution::new(1920, 1080), "0:v", &[DEFAULT_RESOLUTION], Background::Blur, None)));
+    }
+    
+    #[test]
+    fn test_background_gradient() {
+        let task = FileTask::new(PathBuf::from("in.mp4"), vec![PathBuf::from("out.mp4")]);
+        let settings = RenderSettings { background: BackgroundMode::Gradient(0x303048, 0x101018), ..RenderSettings::default() };
+        let args = build_ffmpeg_args(&task, &MediaInfo::synthetic(1920, 1080, 30.0, true), &settings);
+        
+        assert_eq!(args.iter().filter(|a| *a == "-i").count(), 1);
+        // Градиент идет с частотой кадров исходника, а не 25 fps по умолчанию
+        assert!(args.contains(&"color=c=black:s=1x1280:r=30,format=gbrp,\
+            geq=r=48+(16-48)*Y/H:g=48+(16-48)*Y/H:b=72+(24-72)*Y/H,scale=720:1280,setsar=1[bg];\
+            [0:v]scale=720:406[fg];[bg][fg]overlay=(W-w)/2:(H-h)/2:shortest=1,setsar=1[out]".to_string()));
+    }
+    
+    #[test]
+    fn test_background_image_and_video() {
+        let task = FileTask::new(PathBuf::from("in.mp4"), vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")]);
+        let mut settings = RenderSettings {
+            background: BackgroundMode::Image(PathBuf::from("bg.png")),
+            ..two_variant_settings()
+        };
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        
+        // Картинка повторяется на всю длину клипа с частотой кадров исходника и раздается обоим форматам
+        assert_eq!(&args[3..11], &["-framerate", "30", "-loop", "1", "-t", "30.000", "-i", "bg.png"]);
+        assert!(graph.starts_with("[0:v]split=2[src0][src1];[1:v]split=2[bgsrc0][bgsrc1];"));
+        assert!(graph.contains(
+            ";[bgsrc0]scale=1080:1920:force_original_aspect_ratio=increase,crop=1080:1920,setsar=1,fps=30[bg0];\
+             [src0]scale=1080:608[fg0];[bg0][fg0]overlay=(W-w)/2:(H-h)/2:shortest=1,setsar=1[out0];"
+        ));
+        assert!(graph.contains("[bgsrc1]scale=1080:1080:force_original_aspect_ratio=increase,crop=1080:1080"));
+        
+        // Видео фона со своей частотой кадров приводится к частоте исходника
+        settings.background = BackgroundMode::Video(PathBuf::from("loop.mp4"));
+        let args = build_ffmpeg_args(&task, &media.clone().with_fps(60.0), &settings);
+        assert_eq!(&args[3..9], &["-stream_loop", "-1", "-t", "30.000", "-i", "loop.mp4"]);
+        assert!(filter_graph(&args).contains("crop=1080:1920,setsar=1,fps=60[bg0];"));
+        
+        // Вертикальному исходнику фон не нужен - лишний вход не добавляется
+        let args = build_ffmpeg_args(&task, &MediaInfo::synthetic(1080, 1920, 30.0, true), &settings);
+        assert_eq!(args.iter().filter(|a| *a == "-i").count(), 1);
+    }
+    
+    #[test]
+    fn test_multiple_outputs_from_single_graph() {
+        let settings = RenderSettings {
+            variants: vec![
+                OutputVariant { label: "9x16".to_string(), resolution: Resolution::new(1080, 1920) },
+                OutputVariant { label: "1x1".to_string(), resolution: Resolution::new(1080, 1080) },
+                OutputVariant { label: "4x5".to_string(), resolution: Resolution::new(1080, 1350) },
+            ],
+            ..RenderSettings::default()
+        };
+        let outputs = vec![
+            PathBuf::from("clip-short-9x16.mp4"),
+            PathBuf::from("clip-short-1x1.mp4"),
+            PathBuf::from("clip-short-4x5.mp4"),
+        ];
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true);
+        
+        let task = FileTask::new(PathBuf::from("clip.mp4"), outputs.clone());
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        
+        assert!(graph.starts_with("[0:v]split=6[src0][fgsrc0][src1][fgsrc1][src2][fgsrc2];"));
+        assert_eq!(args.iter().filter(|a| *a == "-i").count(), 1);
+        for (index, output) in outputs.iter().enumerate() {
+            assert!(graph.contains(&format!("[out{}]", index)));
+            
+            let map_position = args.iter().position(|a| *a == format!("[out{}]", index)).unwrap();
+            assert_eq!(args[map_position - 1], "-map");
+            assert_eq!(args[map_position + 3], output.to_string_lossy());
+        }
+        assert!(graph.contains("crop=1080:1920"));
+        assert!(graph.contains("crop=1080:1080"));
+        assert!(graph.contains("crop=1080:1350"));
     }
     
     #[test]
     fn test_ffmpeg_command_creation() {
         let input = PathBuf::from("test_input.mp4");
         let output = PathBuf::from("test_output.mp4");
+        let task = FileTask::new(input.clone(), vec![output.clone()]);
         
-        let cmd = FfmpegCommand::new(input.clone(), output.clone());
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true);
+        let cmd = FfmpegCommand::new(&task, &media, &RenderSettings::default()).unwrap();
         
         assert_eq!(cmd.input_path, input);
-        assert_eq!(cmd.output_path, output);
+        assert_eq!(cmd.output_paths, vec![output]);
         assert!(cmd.command_string.contains("ffmpeg"));
         assert!(cmd.command_string.contains("test_input.mp4"));
         assert!(cmd.command_string.contains("test_output.mp4"));
+        assert_eq!(cmd.timeout, FFMPEG_TIMEOUT);
+    }
+    
+    #[test]
+    fn test_segment_args_seek_before_input() {
+        let mut task = FileTask::new(PathBuf::from("input.mp4"), vec![PathBuf::from("input-short-part02.mp4")]);
+        task.segment = Some(TimeRange::new(60.0, 120.0));
+        
+        let media = MediaInfo::synthetic(1920, 1080, 3600.0, true);
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        
+        assert_eq!(&args[..7], &["-y", "-ss", "60.000", "-t", "60.000", "-i", "input.mp4"]);
+        
+        // Таймаут считается по длительности фрагмента, а не всего исходника
+        let cmd = FfmpegCommand::new(&task, &media, &RenderSettings::default()).unwrap();
+        assert_eq!(cmd.timeout, FFMPEG_TIMEOUT);
+    }
+    
+    #[test]
+    fn test_loudnorm_second_pass() {
+        let target = LoudnessTarget::new(-14.0, -1.0).unwrap();
+        let stats = LoudnormStats {
+            input_i: -27.61,
+            input_tp: -4.47,
+            input_lra: 18.06,
+            input_thresh: -39.2,
+            output_i: -14.03,
+            output_tp: -1.0,
+            target_offset: 0.03,
+        };
+        
+        let mut task = FileTask::new(PathBuf::from("talk.mp4"), vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")]);
+        task.loudnorm = Some(stats);
+        let settings = RenderSettings {
+            loudnorm: Some(target),
+            ..two_variant_settings()
+        };
+        let media = MediaInfo::synthetic(1920, 1080, 60.0, true);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        
+        let filter = build_loudnorm_filter(&target, Some(&stats));
+        assert_eq!(
+            filter,
+            "loudnorm=I=-14:TP=-1:LRA=11:measured_I=-27.61:measured_TP=-4.47:measured_LRA=18.06:measured_thresh=-39.2:offset=0.03:linear=true:print_format=json,aresample=48000"
+        );
+        // Фильтр звука ставится для каждого выхода
+        assert_eq!(args.iter().filter(|a| **a == filter).count(), 2);
+        assert_eq!(&args[args.len() - 3..], &["-af", filter.as_str(), "b.mp4"]);
+        
+        // Без замеров - однопроходный режим
+        assert_eq!(
+            build_loudnorm_filter(&target, None),
+            "loudnorm=I=-14:TP=-1:LRA=11:print_format=json,aresample=48000"
+        );
+        
+        // Тихая дорожка из anullsrc не нормализуется
+        let media = MediaInfo::synthetic(1920, 1080, 60.0, false);
+        assert!(!build_ffmpeg_args(&task, &media, &settings).contains(&"-af".to_string()));
+    }
+    
+    #[test]
+    fn test_music_bed_with_ducking() {
+        let mut task = FileTask::new(PathBuf::from("talk.mp4"), vec![PathBuf::from("talk-short.mp4")]);
+        task.segment = Some(TimeRange::new(60.0, 90.0));
+        task.music = Some(PathBuf::from("music/track.mp3"));
+        let settings = RenderSettings {
+            music: Some(MusicSettings { volume: 0.25, fade: 1.5 }),
+            ..RenderSettings::default()
+        };
+        let media = MediaInfo::synthetic(1920, 1080, 600.0, true);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        
+        // Музыка - второй вход, зацикленный и обрезанный по длине клипа
+        assert_eq!(
+            &args[7..13],
+            &["-stream_loop", "-1", "-t", "30.000", "-i", "music/track.mp3"]
+        );
+        
+        let graph = filter_graph(&args);
+        assert!(graph.contains(
+            "[1:a]aformat=sample_rates=48000:channel_layouts=stereo,volume=0.25,afade=t=in:st=0:d=1.5,afade=t=out:st=28.500:d=1.5[music]"
+        ));
+        assert!(graph.contains("[0:a:0]asplit=2[voice][sc]"));
+        assert!(graph.contains(&format!("[music][sc]{}[ducked]", MUSIC_DUCKING_FILTER)));
+        assert!(graph.ends_with("[voice][ducked]amix=inputs=2:duration=first:dropout_transition=0:normalize=0[mix]"));
+        assert_eq!(&args[args.len() - 6..], &["-map", "[out]", "-map", "[mix]", "-shortest", "talk-short.mp4"]);
+        
+        // Без музыки в настройках трек задачи игнорируется
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        assert!(!args.contains(&"-stream_loop".to_string()));
+        
+        // Выбранная дорожка мапится вместо всех звуковых потоков
+        task.audio_track = Some(2);
+        let args = build_ffmpeg_args(&task, &media, &RenderSettings::default());
+        assert!(args.contains(&"0:a:2".to_string()));
+        assert!(!args.contains(&"0:a".to_string()));
+    }
+    
+    #[test]
+    fn test_music_bed_variants() {
+        let target = LoudnessTarget::new(-14.0, -1.0).unwrap();
+        let mut task = FileTask::new(PathBuf::from("talk.mp4"), vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")]);
+        task.music = Some(PathBuf::from("track.mp3"));
+        let settings = RenderSettings {
+            loudnorm: Some(target),
+            music: Some(MusicSettings { volume: 0.3, fade: 1.5 }),
+            ..two_variant_settings()
+        };
+        
+        // Голос нормализуется внутри графа до смешивания, звук раздается выходам через asplit
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, true);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        assert!(graph.contains(&format!("[0:a:0]{},asplit=2[voice][sc]", build_loudnorm_filter(&target, None))));
+        assert!(graph.ends_with("[mix]asplit=2[aout0][aout1]"));
+        assert!(!args.contains(&"-af".to_string()));
+        assert!(args.contains(&"[aout1]".to_string()));
+        
+        // Исходник без звука: только музыка, без anullsrc и приглушения
+        let media = MediaInfo::synthetic(1920, 1080, 30.0, false);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        assert!(!args.contains(&SILENT_AUDIO_SOURCE.to_string()));
+        assert!(!graph.contains("sidechaincompress"));
+        assert!(graph.ends_with("[music]asplit=2[aout0][aout1]"));
+    }
+    
+    #[test]
+    fn test_missing_audio_policies() {
+        let mut task = FileTask::new(PathBuf::from("broll.mp4"), vec![PathBuf::from("broll-short.mp4")]);
+        task.segment = Some(TimeRange::new(10.0, 25.0));
+        let media = MediaInfo::synthetic(1920, 1080, 60.0, false);
+        let settings = |missing_audio| RenderSettings { missing_audio, ..RenderSettings::default() };
+        
+        // Тихая дорожка идет вторым входом той же длины, что и фрагмент
+        let args = build_ffmpeg_args(&task, &media, &settings(MissingAudio::Silence));
+        let lavfi = args.iter().position(|a| a == "lavfi").unwrap();
+        assert_eq!(&args[lavfi + 1..lavfi + 5], &["-t", "15.000", "-i", SILENT_AUDIO_SOURCE]);
+        assert_eq!(
+            &args[args.len() - 6..],
+            &["-map", "[out]", "-map", "1:a", "-shortest", "broll-short.mp4"]
+        );
+        
+        let args = build_ffmpeg_args(&task, &media, &settings(MissingAudio::Omit));
+        assert!(!args.contains(&"lavfi".to_string()));
+        assert!(!args.iter().any(|a| a.ends_with(":a")));
+        assert_eq!(&args[args.len() - 4..], &["-map", "[out]", "-an", "broll-short.mp4"]);
+        
+        assert!(matches!(
+            FfmpegCommand::new(&task, &media, &settings(MissingAudio::Error)),
+            Err(FfmpegError::NoAudioStream { .. })
+        ));
+        
+        // Исходник со звуком не затрагивается ни одним из режимов
+        let media = MediaInfo::synthetic(1920, 1080, 60.0, true);
+        let args = build_ffmpeg_args(&task, &media, &settings(MissingAudio::Omit));
+        assert!(args.contains(&"0:a:0".to_string()));
+        assert!(FfmpegCommand::new(&task, &media, &settings(MissingAudio::Error)).is_ok());
+    }
+    
+    #[test]
+    fn test_subtitles_burned_into_each_output() {
+        let mut task = FileTask::new(PathBuf::from("talk.mp4"), vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")]);
+        task.subtitles = Some(PathBuf::from("talk.srt"));
+        let settings = two_variant_settings();
+        let media = MediaInfo::synthetic(1920, 1080, 60.0, true);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        
+        // Размеры переведены в сетку libass: 64px и 4px при высоте 1920, отступ 20% кадра
+        let filter = build_subtitles_filter(Path::new("talk.srt"), &SubtitleStyle::default(), None);
+        assert_eq!(
+            filter,
+            "subtitles=filename=talk.srt:force_style=FontName=Arial\\,FontSize=9.6\\,PrimaryColour=&H00FFFFFF\\,OutlineColour=&H00000000\\,BorderStyle=1\\,Outline=0.6\\,Shadow=0\\,Alignment=2\\,MarginV=58"
+        );
+        assert!(graph.contains(&format!("[out0]{}[sub0]", filter)));
+        assert!(graph.contains(&format!("[out1]{}[sub1]", filter)));
+        assert_eq!(&args[args.len() - 5..], &["-map", "[sub1]", "-map", "0:a:0", "b.mp4"]);
+        assert!(!args.contains(&"[out0]".to_string()));
+        
+        // Фрагмент: кадры сдвигаются к времени исходника на время рендера субтитров
+        task.segment = Some(TimeRange::new(60.0, 90.0));
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        assert!(graph.contains("[out0]setpts=PTS+60.000/TB,subtitles=filename=talk.srt:"));
+        assert!(graph.contains("MarginV=58,setpts=PTS-STARTPTS[sub0]"));
+        
+        // Распознанные субтитры уже размечены по времени клипа - без сдвига
+        task.subtitles = None;
+        task.transcript = Some(PathBuf::from("talk-short-part01.srt"));
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        assert!(graph.contains("[out0]subtitles=filename=talk-short-part01.srt:"));
+        assert!(!graph.contains("setpts"));
+    }
+    
+    #[test]
+    fn test_karaoke_ass_per_output() {
+        let mut task = FileTask::new(PathBuf::from("talk.mp4"), vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")]);
+        task.subtitles = Some(PathBuf::from("talk.srt"));
+        task.segment = Some(TimeRange::new(60.0, 90.0));
+        task.karaoke = vec![PathBuf::from("out/a.ass"), PathBuf::from("out/b.ass")];
+        let settings = two_variant_settings();
+        let media = MediaInfo::synthetic(1920, 1080, 600.0, true);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        
+        // Каждый выход получает свой ASS, уже размеченный по времени клипа
+        assert!(graph.contains("[out0]ass=filename=out/a.ass[sub0]"));
+        assert!(graph.contains("[out1]ass=filename=out/b.ass[sub1]"));
+        assert!(!graph.contains("subtitles="));
+        assert!(!graph.contains("setpts"));
+    }
+    
+    #[test]
+    fn test_title_overlay_per_output() {
+        let mut task = FileTask::new(PathBuf::from("talk.mp4"), vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")]);
+        task.title = Some("Why 100% of cats: [fail], it's true".to_string());
+        task.karaoke = vec![PathBuf::from("a.ass"), PathBuf::from("b.ass")];
+        let settings = RenderSettings {
+            title: Some(TitleSettings::new("{title}", Some(PathBuf::from("/fonts/Bold.ttf")), 72.0).unwrap()),
+            ..two_variant_settings()
+        };
+        let media = MediaInfo::synthetic(1920, 1080, 600.0, true);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        
+        // Вертикальный кадр уже - заголовок переносится на две строки, квадратный - в одну
+        let title = settings.title.as_ref().unwrap();
+        let vertical = build_title_filter(&render_title(title, &task), title, Resolution::new(1080, 1920)).unwrap();
+        assert_eq!(vertical.matches("drawtext=").count(), 2);
+        assert!(vertical.starts_with(
+            "drawtext=fontfile=/fonts/Bold.ttf:text=Why 100% of cats\\\\::expansion=none:fontsize=72:"
+        ));
+        assert!(vertical.contains(":x=(w-text_w)/2:y=154,"));
+        
+        // Текст экранируется для опций фильтра, затем для графа
+        assert!(vertical.ends_with(
+            "text=\\[fail\\]\\, it\\\\\\\'s true:expansion=none:fontsize=72:fontcolor=white:borderw=4:\
+             bordercolor=black:x=(w-text_w)/2:y=244"
+        ));
+        
+        // Заголовок рисуется до субтитров в цепочке каждого выхода
+        assert!(graph.contains(&format!("[out0]{},ass=filename=a.ass[sub0]", vertical)));
+        let square = graph.rsplit("[out1]").next().unwrap();
+        assert_eq!(square.split("ass=").next().unwrap().matches("drawtext=").count(), 1);
+        assert!(square.contains(":fontsize=41:"));
+    }
+    
+    #[test]
+    fn test_watermark_overlay_per_output() {
+        let task = FileTask::new(PathBuf::from("talk.mp4"), vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")]);
+        let mut settings = RenderSettings {
+            variants: vec![
+                OutputVariant { label: "9x16".to_string(), resolution: Resolution::new(1080, 1920) },
+                OutputVariant { label: "1x1".to_string(), resolution: Resolution::new(720, 720) },
+            ],
+            watermark: Some(WatermarkSettings {
+                path: PathBuf::from("logo.png"),
+                position: WatermarkPosition::BottomLeft,
+                margin: 0.05,
+                scale: 0.2,
+                opacity: 0.5,
+            }),
+            ..RenderSettings::default()
+        };
+        // Без звука первым добавляется вход тишины, логотип - следующий вход
+        let media = MediaInfo::synthetic(1920, 1080, 60.0, false);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        
+        assert_eq!(args[args.iter().position(|a| a == "logo.png").unwrap() - 1], "-i");
+        assert!(graph.contains(";[2:v]format=rgba,colorchannelmixer=aa=0.5[wmsrc];[wmsrc]split=2[wmsrc0][wmsrc1]"));
+        
+        // Логотип масштабируется к ширине каждого выхода, отступ - доля ширины
+        assert!(graph.contains(";[wmsrc0]scale=216:-1[wm0];[wmsrc1]scale=144:-1[wm1]"));
+        assert!(graph.contains(";[out0][wm0]overlay=54:H-h-54[sub0]"));
+        assert!(graph.contains(";[out1][wm1]overlay=36:H-h-36[sub1]"));
+        
+        // Непрозрачный логотип в единственном выходе обходится без split и прозрачности
+        settings.variants.truncate(1);
+        if let Some(watermark) = settings.watermark.as_mut() {
+            watermark.opacity = 1.0;
+            watermark.position = WatermarkPosition::TopRight;
+        }
+        let task = FileTask::new(PathBuf::from("talk.mp4"), vec![PathBuf::from("a.mp4")]);
+        let args = build_ffmpeg_args(&task, &MediaInfo::synthetic(1920, 1080, 60.0, true), &settings);
+        let graph = filter_graph(&args);
+        assert!(graph.contains(";[1:v]format=rgba[wmsrc];[wmsrc]scale=216:-1[wm0];[out][wm0]overlay=W-w-54:54[sub0]"));
+    }
+    
+    #[test]
+    fn test_intro_outro_concat() {
+        let task = FileTask::new(PathBuf::from("talk.mp4"), vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")]);
+        let clip = |path: &str, width, height, duration, audio| {
+            BrandClip::from_media(Path::new(path), MediaInfo::synthetic(width, height, duration, audio)).unwrap()
+        };
+        let mut settings = RenderSettings {
+            loudnorm: Some(LoudnessTarget::new(-14.0, -1.0).unwrap()),
+            intro: Some(clip("intro.mp4", 1920, 1080, 1.0, false)),
+            outro: Some(clip("outro.mov", 1080, 1920, 3.0, true)),
+            ..two_variant_settings()
+        };
+        let media = MediaInfo::synthetic(1920, 1080, 20.0, true);
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        
+        assert_eq!(args.iter().filter(|a| *a == "-i").count(), 3);
+        assert_eq!(args[args.iter().position(|a| a == "intro.mp4").unwrap() - 1], "-i");
+        
+        // Заставка без звука получает тишину своей длительности, кадр вписывается в каждый выход
+        assert!(graph.contains(";[1:v]split=2[introsrc0][introsrc1];"));
+        assert!(graph.contains(";anullsrc=channel_layout=stereo:sample_rate=48000,atrim=duration=1.000[introasrc];"));
+        assert!(graph.contains(";[introsrc1]scale=1080:608,pad=1080:1080:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=30[introv1]"));
+        assert!(graph.contains(";[2:a:0]aformat=sample_rates=48000:channel_layouts=stereo,apad,atrim=duration=3.000[outroasrc];"));
+        
+        // Громкость голоса выравнивается в графе, звук клипа - по длительности видео
+        assert!(graph.contains(";[0:a:0]loudnorm=I=-14:TP=-1:"));
+        assert!(graph.contains("aresample=48000,aformat=sample_rates=48000:channel_layouts=stereo,apad,atrim=duration=20.000[maina1];"));
+        assert!(graph.contains(";[out1]fps=30[mainv1];"));
+        assert!(graph.ends_with(
+            ";[introv1][introa1][mainv1][maina1][outrov1][outroa1]concat=n=3:v=1:a=1[catv1][cata1]"
+        ));
+        assert_eq!(&args[args.len() - 6..], &["-map", "[catv1]", "-map", "[cata1]", "-shortest", "b.mp4"]);
+        assert!(!args.contains(&"-af".to_string()));
+        
+        // Без звука в выходе склеивается только видео
+        settings.missing_audio = MissingAudio::Omit;
+        settings.outro = None;
+        let args = build_ffmpeg_args(&task, &MediaInfo::synthetic(1920, 1080, 20.0, false), &settings);
+        let graph = filter_graph(&args);
+        assert!(!graph.contains("introasrc"));
+        assert!(graph.ends_with(";[out1]fps=30[mainv1];[introv1][mainv1]concat=n=2:v=1:a=0[catv1]"));
+        assert_eq!(&args[args.len() - 4..], &["-map", "[catv1]", "-an", "b.mp4"]);
+        
+        assert!(BrandClip::from_media(Path::new("x.mp4"), MediaInfo::synthetic(1080, 1920, 0.0, true)).is_err());
+        assert!(BrandClip::from_media(Path::new("x.mp4"), MediaInfo::synthetic(0, 0, 1.0, true)).is_err());
+    }
+    
+    #[test]
+    fn test_split_screen_layout() {
+        let mut task = FileTask::new(PathBuf::from("cam.mp4"), vec![PathBuf::from("out.mp4")]);
+        task.segment = Some(TimeRange::new(10.0, 40.0));
+        task.split_input = Some(PathBuf::from("game.mp4"));
+        task.split_media_info = Some(MediaInfo::synthetic(1280, 720, 60.0, true).with_fps(60.0));
+        let mut settings = RenderSettings {
+            split: Some(SplitSettings { ratio: 0.4, audio: SplitAudio::Top }),
+            background: BackgroundMode::Mirror,
+            ..RenderSettings::default()
+        };
+        let media = MediaInfo::synthetic(1920, 1080, 60.0, true);
+        
+        // Второй исходник режется тем же фрагментом; части закрывают свою долю кадра, фон не нужен.
+        // Нижний исходник в 60 fps приводится к 30 fps верхнего
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        assert_eq!(&args[1..13], &[
+            "-ss", "10.000", "-t", "30.000", "-i", "cam.mp4", "-ss", "10.000", "-t", "30.000", "-i", "game.mp4",
+        ]);
+        assert_eq!(
+            filter_graph(&args),
+            "[0:v]scale=912:512,crop=720:512,setsar=1,format=yuv420p,fps=30[top];\
+             [1:v]scale=1366:768,crop=720:768,setsar=1,format=yuv420p,fps=30[bottom];\
+             [top][bottom]vstack=inputs=2:shortest=1[out]"
+        );
+        assert_eq!(&args[args.len() - 5..], &["-map", "[out]", "-map", "0:a:0", "out.mp4"]);
+        
+        // Черные полосы обоих исходников срезаются до компоновки
+        task.crop = Some(SourceCrop::Rect(CropRect { width: 1920, height: 800, x: 0, y: 140 }));
+        task.split_crop = Some(CropRect { width: 960, height: 720, x: 160, y: 0 });
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        assert!(graph.starts_with("[0:v]crop=1920:800:0:140[cropsrc];[1:v]crop=960:720:160:0[cropbottom];[cropsrc]scale="));
+        assert!(graph.contains(";[cropbottom]scale=1024:768,crop=720:768,"));
+        task.crop = None;
+        task.split_crop = None;
+        
+        settings.split = Some(SplitSettings { ratio: 0.5, audio: SplitAudio::Bottom });
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        assert_eq!(&args[args.len() - 5..], &["-map", "[out]", "-map", "1:a:0", "out.mp4"]);
+        
+        // Смесь собирается и нормализуется в графе
+        settings.split = Some(SplitSettings { ratio: 0.5, audio: SplitAudio::Mix });
+        settings.loudnorm = Some(LoudnessTarget::new(-14.0, -1.0).unwrap());
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        assert!(graph.contains(";[0:a:0][1:a:0]amix=inputs=2:duration=shortest:normalize=0[splitmix];[splitmix]loudnorm=I=-14:"));
+        assert_eq!(&args[args.len() - 6..], &["-map", "[out]", "-map", "[splitnorm]", "-shortest", "out.mp4"]);
+        assert!(!args.contains(&"-af".to_string()));
+        
+        // Без звука в нижнем исходнике смешивать нечего
+        task.split_media_info = Some(MediaInfo::synthetic(1280, 720, 60.0, false));
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        assert!(!filter_graph(&args).contains("amix"));
+        let voice = args.iter().position(|a| a == "0:a:0").unwrap();
+        assert_eq!(args[voice + 1], "-af");
+        
+        // Для нескольких форматов оба исходника раздаются через split
+        settings.variants = two_variant_settings().variants;
+        task.outputs.push(PathBuf::from("square.mp4"));
+        let args = build_ffmpeg_args(&task, &media, &settings);
+        let graph = filter_graph(&args);
+        assert!(graph.starts_with("[0:v]split=2[topsrc0][topsrc1];[1:v]split=2[bottomsrc0][bottomsrc1];"));
+        assert!(graph.contains(";[topsrc1]scale=1080:608,crop=1080:540,setsar=1,format=yuv420p,fps=30[top1];"));
+        assert!(graph.contains(";[top1][bottom1]vstack=inputs=2:shortest=1[out1]"));
+        
+        // Исходник, не прошедший анализ, - ошибка команды
+        task.split_media_info = None;
+        assert!(FfmpegCommand::new(&task, &media, &settings).is_err());
+    }
+    
+    #[test]
+    fn test_title_template_and_wrapping() {
+        let title = TitleSettings::new("{stem} - {part}", None, 72.0).unwrap();
+        let mut task = FileTask::new(PathBuf::from("/in/My talk.mp4"), vec![PathBuf::from("a.mp4")]);
+        assert_eq!(render_title(&title, &task), "My talk -");
+        task.part = Some("part02".to_string());
+        assert_eq!(render_title(&title, &task), "My talk - part02");
+        
+        // Без заголовка из списка нарезки подставляется имя файла
+        let title = TitleSettings::new("{title}", None, 72.0).unwrap();
+        assert_eq!(render_title(&title, &task), "My talk");
+        assert!(TitleSettings::new("{name}", None, 72.0).is_err());
+        
+        // Подстановки не раскрываются повторно внутри вставленных значений
+        let title = TitleSettings::new("{title} {part}", None, 72.0).unwrap();
+        assert_eq!(title.render("clip", Some("Why {part} matters"), Some("{stem}")), "Why {part} matters {stem}");
+        assert!(build_title_filter("   ", &title, Resolution::new(1080, 1920)).is_none());
+        
+        assert_eq!(wrap_text("one two three four", 9, 3), vec!["one two", "three", "four"]);
+        assert_eq!(wrap_text("abcdefghij", 4, 3), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap_text("aa bb cc dd", 2, 2), vec!["aa", "b…"]);
+        assert!(wrap_text("", 10, 3).is_empty());
+    }
+    
+    #[test]
+    fn test_subtitle_style_and_escaping() {
+        let style = SubtitleStyle {
+            font: "DejaVu Sans".to_string(),
+            size: 96.0,
+            color: 0xFFCC00,
+            outline_color: 0x102030,
+            outline: 0.0,
+            safe_zone: 0.25,
+        };
+        let filter = build_subtitles_filter(Path::new("clip.vtt"), &style, Some(0.0));
+        assert!(filter.starts_with("subtitles=filename=clip.vtt:force_style=FontName=DejaVu Sans\\,FontSize=14.4\\,"));
+        assert!(filter.contains("PrimaryColour=&H0000CCFF"));
+        assert!(filter.contains("OutlineColour=&H00302010"));
+        assert!(filter.contains("Outline=0\\,"));
+        assert!(filter.ends_with("MarginV=72"));
+        
+        // Путь экранируется для опций фильтра, затем для графа
+        assert_eq!(escape_filter_value("/data/it's: a [b],c;d.srt"), "/data/it\\\\\\'s\\\\: a \\[b\\]\\,c\\;d.srt");
+        assert_eq!(escape_filter_value("C:\\subs\\a.srt"), "C\\\\:\\\\\\\\subs\\\\\\\\a.srt");
+    }
+    
+    #[test]
+    fn test_timeout_scales_with_media_duration() {
+        assert_eq!(timeout_for_duration(10.0), FFMPEG_TIMEOUT);
+        assert_eq!(timeout_for_duration(3600.0), Duration::from_secs(3600 * 4));
+    }
+    
+    #[test]
+    fn test_estimate_output_size() {
+        let media = MediaInfo::synthetic(1920, 1080, 8.0, true);
+        // 4 Мбит/с * 8 секунд = 4 МБ
+        assert_eq!(estimate_output_size(&media), 4_000_000);
     }
     
     #[test]
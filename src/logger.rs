@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use tracing::{info, warn, error, debug};
 use crate::error::{LoggingError, LoggingResult};
@@ -55,6 +55,12 @@ impl FileProcessingLogger {
         }
     }
     
+    /// Логирует результат анализа входного файла через ffprobe
+    pub fn log_media_info(&self, media_info: &crate::probe::MediaInfo) {
+        info!("PROBE: {}", media_info.summary());
+        debug!("Media info for {}: {:?}", self.filename, media_info);
+    }
+    
     /// Логирует выполняемую FFmpeg команду
     pub fn log_ffmpeg_command(&self, command: &str) {
         info!("CMD: {}", command);
@@ -62,20 +68,22 @@ impl FileProcessingLogger {
     }
     
     /// Логирует успешное завершение обработки
-    pub fn log_success(&self, input_path: &PathBuf, output_path: &PathBuf) {
+    pub fn log_success(&self, input_path: &Path, output_paths: &[PathBuf]) {
         let duration = self.start_time.elapsed();
         info!(
             "SUCCESS: {} -> {} ({})",
             input_path.file_name().unwrap_or_default().to_string_lossy(),
-            output_path.file_name().unwrap_or_default().to_string_lossy(),
+            format_file_names(output_paths),
             format_duration(duration)
         );
-        debug!("File processing completed in {:?}: {} -> {}", 
-               duration, input_path.display(), output_path.display());
+        for output_path in output_paths {
+            debug!("File processing completed in {:?}: {} -> {}", 
+                   duration, input_path.display(), output_path.display());
+        }
     }
     
     /// Логирует ошибку обработки файла
-    pub fn log_error(&self, input_path: &PathBuf, output_path: &PathBuf, error_message: &str) {
+    pub fn log_error(&self, input_path: &Path, output_path: &Path, error_message: &str) {
         let duration = self.start_time.elapsed();
         error!(
             "ERROR: {} -> {} ({})",
@@ -94,14 +102,27 @@ impl FileProcessingLogger {
     }
 }
 
+/// Успешно обработанная задача: файл целиком или одна его часть
+pub struct ProcessedOutput {
+    pub input: PathBuf,
+    /// Метка части (`part01`, ...), если исходник нарезан
+    pub part: Option<String>,
+    pub outputs: Vec<PathBuf>,
+    pub duration: Duration,
+    /// Громкость до и после нормализации, если она включена
+    pub loudness: Option<crate::analysis::LoudnessResult>,
+}
+
 /// Структура для сбора и отображения финальной статистики
 pub struct ProcessingSummary {
     pub total_files: usize,
     pub successful: usize,
     pub failed: usize,
     pub total_duration: Duration,
-    pub successful_files: Vec<(PathBuf, PathBuf, Duration)>,
-    pub failed_files: Vec<(PathBuf, String)>,
+    pub successful_files: Vec<ProcessedOutput>,
+    pub failed_files: Vec<(PathBuf, Option<String>, String)>,
+    /// Сколько секунд тишины вырезано из каждого исходника
+    pub removed_silence: Vec<(PathBuf, f64)>,
 }
 
 impl ProcessingSummary {
@@ -114,19 +135,77 @@ impl ProcessingSummary {
             total_duration: Duration::ZERO,
             successful_files: Vec::new(),
             failed_files: Vec::new(),
+            removed_silence: Vec::new(),
         }
     }
     
-    /// Добавляет успешно обработанный файл
-    pub fn add_success(&mut self, input: PathBuf, output: PathBuf, duration: Duration) {
+    /// Добавляет успешно обработанный файл или его часть
+    pub fn add_success(
+        &mut self,
+        input: PathBuf,
+        part: Option<String>,
+        outputs: Vec<PathBuf>,
+        duration: Duration,
+        loudness: Option<crate::analysis::LoudnessResult>,
+    ) {
         self.successful += 1;
-        self.successful_files.push((input, output, duration));
+        self.successful_files.push(ProcessedOutput { input, part, outputs, duration, loudness });
     }
     
-    /// Добавляет файл с ошибкой
-    pub fn add_failure(&mut self, input: PathBuf, error_message: String) {
+    /// Добавляет файл (или часть файла) с ошибкой
+    pub fn add_failure(&mut self, input: PathBuf, part: Option<String>, error_message: String) {
         self.failed += 1;
-        self.failed_files.push((input, error_message));
+        self.failed_files.push((input, part, error_message));
+    }
+    
+    /// Запоминает, сколько секунд тишины вырезано из исходника
+    ///
+    /// Вызывается после учета результатов задач: цифра попадает в отчет, только
+    /// если все клипы исходника собраны успешно.
+    pub fn add_removed_silence(&mut self, input: PathBuf, seconds: f64) {
+        let rendered = self.successful_files.iter().any(|entry| entry.input == input);
+        let failed = self.failed_files.iter().any(|(failed, ..)| *failed == input);
+        if rendered && !failed {
+            self.removed_silence.push((input, seconds));
+        }
+    }
+    
+    /// Группирует успешные задачи по исходному файлу
+    ///
+    /// Исходники идут в порядке первого появления, части внутри - по метке.
+    pub fn successes_by_source(&self) -> Vec<(&Path, Vec<&ProcessedOutput>)> {
+        let mut groups: Vec<(&Path, Vec<&ProcessedOutput>)> = Vec::new();
+        
+        for entry in &self.successful_files {
+            match groups.iter_mut().find(|(input, _)| *input == entry.input.as_path()) {
+                Some((_, parts)) => parts.push(entry),
+                None => groups.push((entry.input.as_path(), vec![entry])),
+            }
+        }
+        
+        for (_, parts) in &mut groups {
+            parts.sort_by(|a, b| a.part.cmp(&b.part));
+        }
+        
+        groups
+    }
+    
+    /// Возвращает количество исходных файлов, упомянутых в сводке
+    pub fn source_count(&self) -> usize {
+        let mut sources: Vec<&PathBuf> = self.successful_files
+            .iter()
+            .map(|entry| &entry.input)
+            .chain(self.failed_files.iter().map(|(input, _, _)| input))
+            .collect();
+        sources.sort();
+        sources.dedup();
+        sources.len()
+    }
+    
+    /// Есть ли в сводке нарезанные на части исходники
+    fn has_parts(&self) -> bool {
+        self.successful_files.iter().any(|entry| entry.part.is_some())
+            || self.failed_files.iter().any(|(_, part, _)| part.is_some())
     }
     
     /// Устанавливает общую длительность обработки
@@ -145,36 +224,92 @@ impl ProcessingSummary {
         
         if !self.successful_files.is_empty() {
             info!("Successfully processed files:");
-            for (input, output, duration) in &self.successful_files {
-                info!("  ✓ {} -> {} ({})", 
-                     input.file_name().unwrap_or_default().to_string_lossy(),
-                     output.file_name().unwrap_or_default().to_string_lossy(),
-                     format_duration(*duration));
+            for (input, parts) in self.successes_by_source() {
+                let name = input.file_name().unwrap_or_default().to_string_lossy();
+                match parts.as_slice() {
+                    [single] if single.part.is_none() => {
+                        info!("  ✓ {} -> {} ({})", 
+                             name,
+                             format_file_names(&single.outputs),
+                             format_duration(single.duration));
+                    }
+                    _ => {
+                        info!("  ✓ {} ({} parts)", name, parts.len());
+                        for entry in parts {
+                            info!("      {} -> {} ({})", 
+                                 entry.part.as_deref().unwrap_or_default(),
+                                 format_file_names(&entry.outputs),
+                                 format_duration(entry.duration));
+                        }
+                    }
+                }
+            }
+        }
+        
+        let normalized: Vec<&ProcessedOutput> =
+            self.successful_files.iter().filter(|entry| entry.loudness.is_some()).collect();
+        if !normalized.is_empty() {
+            info!("Loudness normalization:");
+            for entry in &normalized {
+                info!("  {}: {}", format_task_name(&entry.input, entry.part.as_deref()), format_loudness(entry));
+            }
+        }
+        
+        if !self.removed_silence.is_empty() {
+            info!("Silence removed:");
+            for (input, seconds) in &self.removed_silence {
+                info!("  {}: {:.1}s", input.file_name().unwrap_or_default().to_string_lossy(), seconds);
             }
         }
         
         if !self.failed_files.is_empty() {
             warn!("Files with errors:");
-            for (input, error) in &self.failed_files {
-                error!("  ✗ {}: {}", 
-                      input.file_name().unwrap_or_default().to_string_lossy(),
-                      error);
+            for (input, part, error) in &self.failed_files {
+                error!("  ✗ {}: {}", format_task_name(input, part.as_deref()), error);
             }
         }
         
         // Также выводим в консоль для пользователя
         println!("\n=== PROCESSING SUMMARY ===");
-        println!("Total files processed: {}", self.total_files);
+        if self.has_parts() {
+            println!("Source files: {}", self.source_count());
+            println!("Total parts processed: {}", self.total_files);
+        } else {
+            println!("Total files processed: {}", self.total_files);
+        }
         println!("Successful: {} ✓", self.successful);
         println!("Failed: {} ✗", self.failed);
         println!("Total time: {}", format_duration(self.total_duration));
         
+        if self.has_parts() && self.successful > 0 {
+            println!("\nParts by source file:");
+            for (input, parts) in self.successes_by_source() {
+                println!("  {}:", input.file_name().unwrap_or_default().to_string_lossy());
+                for entry in parts {
+                    println!("    ✓ {}", format_file_names(&entry.outputs));
+                }
+            }
+        }
+        
+        if !normalized.is_empty() {
+            println!("\nLoudness normalization:");
+            for entry in &normalized {
+                println!("  {}: {}", format_task_name(&entry.input, entry.part.as_deref()), format_loudness(entry));
+            }
+        }
+        
+        if !self.removed_silence.is_empty() {
+            let total: f64 = self.removed_silence.iter().map(|(_, seconds)| seconds).sum();
+            println!("\nSilence removed ({:.1}s total):", total);
+            for (input, seconds) in &self.removed_silence {
+                println!("  {}: {:.1}s", input.file_name().unwrap_or_default().to_string_lossy(), seconds);
+            }
+        }
+        
         if self.failed > 0 {
             println!("\nFiles with errors:");
-            for (input, error) in &self.failed_files {
-                println!("  ✗ {}: {}", 
-                        input.file_name().unwrap_or_default().to_string_lossy(),
-                        error);
+            for (input, part, error) in &self.failed_files {
+                println!("  ✗ {}: {}", format_task_name(input, part.as_deref()), error);
             }
         }
         
@@ -199,12 +334,19 @@ impl Default for ProcessingSummary {
 }
 
 /// Логирует информацию о запуске приложения
-pub fn log_startup_info(input_dir: &PathBuf, output_dir: &PathBuf, thread_count: usize) {
+pub fn log_startup_info(
+    input_dir: &PathBuf,
+    output_dir: &PathBuf,
+    thread_count: usize,
+    variants: &[crate::config::OutputVariant],
+) {
     info!("=== SHORTS CUTTER STARTED ===");
     info!("Input directory: {}", input_dir.display());
     info!("Output directory: {}", output_dir.display());
     info!("Thread count: {}", thread_count);
-    info!("FFmpeg filter: {}", crate::config::FFMPEG_FILTER_COMPLEX);
+    for variant in variants {
+        info!("Output format: {} ({})", variant.label, variant.resolution);
+    }
 }
 
 /// Логирует информацию о найденных файлах
@@ -221,6 +363,68 @@ pub fn log_shutdown_signal() {
     warn!("{}", crate::config::messages::GRACEFUL_SHUTDOWN);
 }
 
+/// Выводит план нарезки для `--dry-run`: фрагменты, выходные файлы и ошибки подготовки
+pub fn print_planned_clips(tasks: &[crate::utils::FileTask], failures: &[crate::worker::TaskResult]) {
+    println!("Planned clips:");
+    for task in tasks {
+        let range = match task.segment {
+            Some(segment) => format!("{} ({:.1}s)", segment, segment.duration()),
+            None => "whole file".to_string(),
+        };
+        match &task.title {
+            Some(title) => println!("  {}  {}  \"{}\"", task.display_name(), range, title),
+            None => println!("  {}  {}", task.display_name(), range),
+        }
+        for output in &task.outputs {
+            println!("    -> {}", output.file_name().unwrap_or_default().to_string_lossy());
+        }
+        if let Some(music) = &task.music {
+            println!("    music: {}", music.file_name().unwrap_or_default().to_string_lossy());
+        }
+        if let Some(subtitles) = &task.subtitles {
+            println!("    subtitles: {}", subtitles.file_name().unwrap_or_default().to_string_lossy());
+        }
+    }
+    
+    if !failures.is_empty() {
+        println!("\nCannot be planned:");
+        for failure in failures {
+            if let crate::worker::TaskResult::Failure { input, part, error, .. } = failure {
+                println!("  {}: {}", format_task_name(input, part.as_deref()), error);
+            }
+        }
+    }
+}
+
+/// Форматирует громкость до и после нормализации для отчета
+fn format_loudness(entry: &ProcessedOutput) -> String {
+    match &entry.loudness {
+        Some(loudness) => format!(
+            "{:.1} LUFS / {:.1} dBTP -> {:.1} LUFS / {:.1} dBTP",
+            loudness.measured_i, loudness.measured_tp, loudness.final_i, loudness.final_tp
+        ),
+        None => "not normalized".to_string(),
+    }
+}
+
+/// Форматирует имя задачи: имя файла и, если есть, метка части
+fn format_task_name(input: &Path, part: Option<&str>) -> String {
+    let name = input.file_name().unwrap_or_default().to_string_lossy();
+    match part {
+        Some(part) => format!("{} [{}]", name, part),
+        None => name.to_string(),
+    }
+}
+
+/// Форматирует список файлов как перечень имен через запятую
+fn format_file_names(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.file_name().unwrap_or_default().to_string_lossy().to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 /// Форматирует duration в человекочитаемый вид
 fn format_duration(duration: Duration) -> String {
     let total_seconds = duration.as_secs();
@@ -258,12 +462,15 @@ mod tests {
         
         summary.add_success(
             PathBuf::from("test1.mp4"), 
-            PathBuf::from("test1-short.mp4"),
-            Duration::from_secs(10)
+            None,
+            vec![PathBuf::from("test1-short.mp4")],
+            Duration::from_secs(10),
+            None
         );
         
         summary.add_failure(
             PathBuf::from("test2.mp4"),
+            None,
             "FFmpeg error".to_string()
         );
         
@@ -274,4 +481,67 @@ mod tests {
         assert_eq!(summary.total_files, 2);
         assert_eq!(summary.exit_code(), crate::config::exit_codes::PARTIAL_SUCCESS);
     }
+    
+    #[test]
+    fn test_summary_groups_parts_by_source() {
+        let mut summary = ProcessingSummary::new();
+        
+        for (input, part) in [("talk.mp4", "part02"), ("intro.mp4", ""), ("talk.mp4", "part01")] {
+            let part = (!part.is_empty()).then(|| part.to_string());
+            summary.add_success(PathBuf::from(input), part, vec![PathBuf::from("out.mp4")], Duration::from_secs(1), None);
+        }
+        summary.add_failure(PathBuf::from("talk.mp4"), Some("part03".to_string()), "FFmpeg error".to_string());
+        summary.set_total_duration(Duration::from_secs(3));
+        
+        let groups = summary.successes_by_source();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, Path::new("talk.mp4"));
+        let parts: Vec<_> = groups[0].1.iter().map(|e| e.part.as_deref().unwrap()).collect();
+        assert_eq!(parts, vec!["part01", "part02"]);
+        assert_eq!(groups[1].1.len(), 1);
+        
+        assert_eq!(summary.total_files, 4);
+        assert_eq!(summary.source_count(), 2);
+        assert_eq!(format_task_name(Path::new("talk.mp4"), Some("part03")), "talk.mp4 [part03]");
+    }
+    
+    #[test]
+    fn test_format_loudness() {
+        let mut summary = ProcessingSummary::new();
+        let loudness = crate::analysis::LoudnessResult {
+            measured_i: -27.61,
+            measured_tp: -4.47,
+            final_i: -14.03,
+            final_tp: -1.0,
+        };
+        summary.add_success(PathBuf::from("talk.mp4"), None, vec![], Duration::from_secs(1), Some(loudness));
+        summary.add_success(PathBuf::from("vlog.mp4"), None, vec![], Duration::from_secs(1), None);
+        
+        assert_eq!(
+            format_loudness(&summary.successful_files[0]),
+            "-27.6 LUFS / -4.5 dBTP -> -14.0 LUFS / -1.0 dBTP"
+        );
+        assert_eq!(format_loudness(&summary.successful_files[1]), "not normalized");
+    }
+    
+    #[test]
+    fn test_summary_records_removed_silence() {
+        let mut summary = ProcessingSummary::new();
+        assert!(summary.removed_silence.is_empty());
+        
+        let outputs = vec![PathBuf::from("out.mp4")];
+        summary.add_success(PathBuf::from("talk.mp4"), None, outputs.clone(), Duration::from_secs(1), None);
+        summary.add_success(PathBuf::from("vlog.mp4"), Some("part01".to_string()), outputs, Duration::from_secs(1), None);
+        summary.add_failure(PathBuf::from("vlog.mp4"), Some("part02".to_string()), "boom".to_string());
+        let (successful, failed) = (summary.successful, summary.failed);
+        
+        // Для исходника с неудачной частью и для несобранного исходника тишина не показывается
+        summary.add_removed_silence(PathBuf::from("talk.mp4"), 12.5);
+        summary.add_removed_silence(PathBuf::from("vlog.mp4"), 3.0);
+        summary.add_removed_silence(PathBuf::from("lost.mp4"), 4.0);
+        
+        assert_eq!(summary.removed_silence, vec![(PathBuf::from("talk.mp4"), 12.5)]);
+        // Запись о тишине не влияет на счетчики обработанных файлов
+        assert_eq!((summary.successful, summary.failed), (successful, failed));
+    }
 }
@@ -1,8 +1,14 @@
+mod analysis;
+mod captions;
 mod cli;
 mod config;
 mod error;
 mod ffmpeg;
 mod logger;
+mod manifest;
+mod planner;
+mod probe;
+mod transcribe;
 mod utils;
 mod worker;
 
@@ -60,7 +66,21 @@ async fn run() -> Result<i32> {
     }
     
     // Логируем информацию о запуске
-    logger::log_startup_info(&validated_args.input, &validated_args.output, validated_args.threads);
+    logger::log_startup_info(
+        &validated_args.input,
+        &validated_args.output,
+        validated_args.threads,
+        &validated_args.variants,
+    );
+    
+    // Заставка и финальная карточка анализируются один раз для всех клипов
+    let mut render_settings = validated_args.render_settings();
+    if let Some(path) = &validated_args.intro {
+        render_settings.intro = Some(ffmpeg::BrandClip::load(path).await?);
+    }
+    if let Some(path) = &validated_args.outro {
+        render_settings.outro = Some(ffmpeg::BrandClip::load(path).await?);
+    }
     
     // Ищем видеофайлы для обработки
     let video_files = utils::find_video_files(&validated_args.input)?;
@@ -71,23 +91,70 @@ async fn run() -> Result<i32> {
         return Ok(config::exit_codes::CRITICAL_ERROR);
     }
     
-    // Создаем задачи обработки
-    let tasks = utils::create_file_tasks(video_files, &validated_args.output);
+    // Создаем задачи обработки; для разделенного экрана файлы сначала собираются в пары
+    let file_count = video_files.len();
+    let mut pair_failures = Vec::new();
+    let tasks = if validated_args.split.is_some() {
+        let pairs = planner::select_split_pairs(
+            &video_files,
+            &validated_args.input,
+            validated_args.split_pairs.as_ref(),
+            &mut pair_failures,
+        );
+        utils::create_split_tasks(pairs, &validated_args.output, &validated_args.variants)
+    } else {
+        utils::create_file_tasks(video_files, &validated_args.output, &validated_args.variants)
+    };
     
     println!("{}", config::messages::PROCESSING_STARTED);
-    println!("Found {} files to process", tasks.len());
+    println!("Found {} files to process", file_count);
+    
+    // Анализируем входные файлы и нарезаем их на части согласно стратегии
+    let plan_settings = planner::PlanSettings {
+        strategy: validated_args.cut_strategy.clone(),
+        input_dir: validated_args.input.clone(),
+        output_dir: validated_args.output.clone(),
+        variants: validated_args.variants.clone(),
+        threads: validated_args.threads,
+        audio: validated_args.audio.clone(),
+    };
+    let mut plan = planner::build_plan(tasks, &plan_settings).await;
+    plan.failures.extend(pair_failures);
+    
+    // Музыкальные треки раздаются клипам по кругу
+    utils::assign_music(&mut plan.tasks, &validated_args.music_tracks);
+    
+    if plan.tasks.len() != file_count {
+        println!("Planned {} clips", plan.tasks.len());
+    }
+    
+    // В режиме --dry-run только показываем план
+    if validated_args.dry_run {
+        println!();
+        logger::print_planned_clips(&plan.tasks, &plan.failures);
+        return Ok(if plan.failures.is_empty() {
+            config::exit_codes::SUCCESS
+        } else {
+            config::exit_codes::PARTIAL_SUCCESS
+        });
+    }
+    
     println!("Using {} parallel threads", validated_args.threads);
     println!();
     
     // Создаем worker pool и запускаем обработку
-    let worker_pool = WorkerPool::new(validated_args.threads);
+    let worker_pool = WorkerPool::new(validated_args.threads, render_settings);
     
     info!("Starting parallel processing with {} workers", validated_args.threads);
     
-    let processing_results = worker_pool.execute_tasks(tasks).await?;
+    let mut processing_results = worker_pool.execute_tasks(plan.tasks).await?;
+    processing_results.add_failures(plan.failures);
     
     // Генерируем финальный отчет
-    let summary = processing_results.to_processing_summary();
+    let mut summary = processing_results.to_processing_summary();
+    for (input, seconds) in plan.removed_silence {
+        summary.add_removed_silence(input, seconds);
+    }
     summary.print_final_report();
     
     println!("
@@ -0,0 +1,575 @@
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+
+use crate::config::SourceCrop;
+use crate::error::{ManifestError, ManifestResult};
+use crate::utils::{parse_time_spec, TimeRange};
+
+/// Одна строка списка нарезки: фрагмент исходного файла
+#[derive(Debug, Clone, PartialEq)]
+pub struct CutRow {
+    /// Номер строки (для CSV) или записи (для JSON), начиная с 1
+    pub row: usize,
+    /// Имя входного файла или путь относительно входной директории
+    pub file: String,
+    pub range: TimeRange,
+    pub title: Option<String>,
+    /// Обрезка черных полос вместо найденной автоматически
+    pub crop: Option<SourceCrop>,
+    /// Входной файл строки; заполняется `CutList::resolve`
+    pub input: Option<PathBuf>,
+}
+
+/// Список нарезки из `--cuts`: фрагменты по файлам и строки, которые не удалось разобрать
+#[derive(Debug, Clone, PartialEq)]
+pub struct CutList {
+    pub path: PathBuf,
+    pub rows: Vec<CutRow>,
+    /// Ошибки отдельных строк - попадают в финальный отчет
+    pub errors: Vec<ManifestError>,
+}
+
+impl CutList {
+    /// Загружает список нарезки; формат определяется по расширению (`.json` или CSV)
+    pub fn load(path: &Path) -> ManifestResult<Self> {
+        let content = read_list(path)?;
+        
+        if is_json(path) {
+            Self::parse_json(&content, path)
+        } else {
+            Self::parse_csv(&content, path)
+        }
+    }
+    
+    /// Разбирает CSV вида `file,start,end[,title[,crop]]`
+    ///
+    /// Строка заголовка (со словом `start` во второй колонке), пустые строки
+    /// и строки, начинающиеся с `#`, пропускаются.
+    pub fn parse_csv(content: &str, path: &Path) -> ManifestResult<Self> {
+        let mut list = Self::empty(path);
+        
+        for (row, record) in csv_records(content, path, "start")? {
+            let field = |i: usize| record.get(i).filter(|value| !value.is_empty());
+            list.push_row(row, field(0), field(1), field(2), field(3), field(4));
+        }
+        
+        Ok(list)
+    }
+    
+    /// Разбирает JSON-массив записей `{"file", "start", "end", "title", "crop"}`
+    ///
+    /// Время задается числом секунд или строкой (`"1m30s"`, `"00:01:30"`),
+    /// обрезка - строкой `"W:H:X:Y"` или `"none"`.
+    pub fn parse_json(content: &str, path: &Path) -> ManifestResult<Self> {
+        let entries: Vec<RawCutEntry> = serde_json::from_str(content)
+            .map_err(|e| ManifestError::malformed(path.to_path_buf(), e.to_string()))?;
+        
+        let mut list = Self::empty(path);
+        
+        for (index, entry) in entries.iter().enumerate() {
+            list.push_row(
+                index + 1,
+                entry.file.as_deref(),
+                entry.start.as_ref().map(RawTime::as_text).as_deref(),
+                entry.end.as_ref().map(RawTime::as_text).as_deref(),
+                entry.title.as_deref(),
+                entry.crop.as_deref(),
+            );
+        }
+        
+        Ok(list)
+    }
+    
+    /// Привязывает строки к входным файлам (см. `find_input`)
+    ///
+    /// Строки, файла которых нет во входной директории или имя которых
+    /// неоднозначно, переносятся в ошибки.
+    pub fn resolve(&mut self, root: &Path, files: &[PathBuf]) {
+        for mut row in std::mem::take(&mut self.rows) {
+            match find_input(&row.file, root, files) {
+                FileMatch::Found(input) => {
+                    row.input = Some(input.clone());
+                    self.rows.push(row);
+                }
+                FileMatch::Missing => self.errors.push(ManifestError::unknown_file(&row.file, row.row)),
+                FileMatch::Ambiguous(count) => self.errors.push(ManifestError::ambiguous_file(&row.file, row.row, count)),
+            }
+        }
+    }
+    
+    /// Возвращает строки, привязанные к входному файлу, в порядке списка
+    pub fn rows_for(&self, input: &Path) -> Vec<&CutRow> {
+        self.rows.iter().filter(|row| row.input.as_deref() == Some(input)).collect()
+    }
+    
+    fn empty(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            rows: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+    
+    /// Проверяет поля строки и добавляет ее в список строк или ошибок
+    fn push_row(
+        &mut self,
+        row: usize,
+        file: Option<&str>,
+        start: Option<&str>,
+        end: Option<&str>,
+        title: Option<&str>,
+        crop: Option<&str>,
+    ) {
+        match parse_row(row, file, start, end, title, crop) {
+            Ok(cut) => self.rows.push(cut),
+            Err(e) => self.errors.push(e),
+        }
+    }
+}
+
+/// Пара исходников разделенного экрана
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairRow {
+    /// Номер строки (для CSV) или записи (для JSON), начиная с 1
+    pub row: usize,
+    /// Исходник верхней части кадра: имя файла или путь относительно входной директории
+    pub top: String,
+    /// Исходник нижней части кадра
+    pub bottom: String,
+}
+
+/// Список пар из `--split-pairs`: какие исходники собираются в разделенный экран
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairList {
+    pub path: PathBuf,
+    pub rows: Vec<PairRow>,
+    /// Ошибки отдельных строк - попадают в финальный отчет
+    pub errors: Vec<ManifestError>,
+}
+
+impl PairList {
+    /// Загружает список пар; формат определяется по расширению (`.json` или CSV)
+    pub fn load(path: &Path) -> ManifestResult<Self> {
+        let content = read_list(path)?;
+        
+        if is_json(path) {
+            Self::parse_json(&content, path)
+        } else {
+            Self::parse_csv(&content, path)
+        }
+    }
+    
+    /// Разбирает CSV вида `top,bottom`
+    ///
+    /// Строка заголовка (со словом `bottom` во второй колонке), пустые строки
+    /// и строки, начинающиеся с `#`, пропускаются.
+    pub fn parse_csv(content: &str, path: &Path) -> ManifestResult<Self> {
+        let mut list = Self::empty(path);
+        
+        for (row, record) in csv_records(content, path, "bottom")? {
+            list.push_row(row, record.get(0), record.get(1));
+        }
+        
+        Ok(list)
+    }
+    
+    /// Разбирает JSON-массив записей `{"top", "bottom"}`
+    pub fn parse_json(content: &str, path: &Path) -> ManifestResult<Self> {
+        let entries: Vec<RawPairEntry> = serde_json::from_str(content)
+            .map_err(|e| ManifestError::malformed(path.to_path_buf(), e.to_string()))?;
+        
+        let mut list = Self::empty(path);
+        
+        for (index, entry) in entries.iter().enumerate() {
+            list.push_row(index + 1, entry.top.as_deref(), entry.bottom.as_deref());
+        }
+        
+        Ok(list)
+    }
+    
+    /// Находит файлы пар среди входных файлов (см. `find_input`)
+    ///
+    /// Имена выходов берутся по верхнему файлу, поэтому верхний файл может
+    /// встретиться только в одной паре. Пары с ненайденными, неоднозначными
+    /// или повторными файлами возвращаются ошибками вместе с ошибками разбора.
+    pub fn resolve(&self, root: &Path, files: &[PathBuf]) -> (Vec<(PathBuf, PathBuf)>, Vec<ManifestError>) {
+        let mut pairs: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let mut errors = self.errors.clone();
+        
+        for row in &self.rows {
+            let error = match (find_input(&row.top, root, files), find_input(&row.bottom, root, files)) {
+                (FileMatch::Found(top), FileMatch::Found(_)) if pairs.iter().any(|(used, _)| used == top) => {
+                    format!("top file '{}' is already used by another pair and would overwrite its output", row.top)
+                }
+                (FileMatch::Found(top), FileMatch::Found(bottom)) => {
+                    pairs.push((top.clone(), bottom.clone()));
+                    continue;
+                }
+                (FileMatch::Found(_), bottom) => bottom.describe(&row.bottom),
+                (top, _) => top.describe(&row.top),
+            };
+            errors.push(ManifestError::invalid_pair(row.row, error));
+        }
+        
+        (pairs, errors)
+    }
+    
+    fn empty(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            rows: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+    
+    /// Проверяет поля строки и добавляет ее в список строк или ошибок
+    fn push_row(&mut self, row: usize, top: Option<&str>, bottom: Option<&str>) {
+        let top = top.map(str::trim).filter(|top| !top.is_empty());
+        let bottom = bottom.map(str::trim).filter(|bottom| !bottom.is_empty());
+        
+        let error = match (top, bottom) {
+            (Some(top), Some(bottom)) if top == bottom => format!("'{}' is paired with itself", top),
+            (Some(top), Some(bottom)) => {
+                self.rows.push(PairRow { row, top: top.to_string(), bottom: bottom.to_string() });
+                return;
+            }
+            (None, _) => "missing top file".to_string(),
+            (_, None) => "missing bottom file".to_string(),
+        };
+        self.errors.push(ManifestError::invalid_pair(row, error));
+    }
+}
+
+/// Результат поиска файла из списка среди входных файлов
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileMatch<'a> {
+    Found(&'a PathBuf),
+    Missing,
+    /// Голое имя совпало с несколькими файлами в поддиректориях
+    Ambiguous(usize),
+}
+
+impl FileMatch<'_> {
+    /// Причина, по которой файл не найден, для отчета
+    fn describe(&self, name: &str) -> String {
+        match self {
+            Self::Ambiguous(count) => {
+                format!("'{}' matches {} files in the input directory; use a path relative to it", name, count)
+            }
+            _ => format!("file '{}' not found in the input directory", name),
+        }
+    }
+}
+
+/// Ищет файл из списка среди входных файлов
+///
+/// Путь из списка сравнивается с путем файла относительно входной директории
+/// целиком: `day1/clip.mp4` - только `<input>/day1/clip.mp4`. Голое имя, которого
+/// нет в корне, ищется по имени файла в поддиректориях и должно найтись ровно один раз.
+fn find_input<'a>(name: &str, root: &Path, files: &'a [PathBuf]) -> FileMatch<'a> {
+    let name = Path::new(name);
+    if let Some(file) = files.iter().find(|file| file.strip_prefix(root).is_ok_and(|relative| relative == name)) {
+        return FileMatch::Found(file);
+    }
+    if name.components().count() > 1 {
+        return FileMatch::Missing;
+    }
+    
+    let named: Vec<&PathBuf> = files.iter().filter(|file| file.file_name() == Some(name.as_os_str())).collect();
+    match named.as_slice() {
+        [] => FileMatch::Missing,
+        [file] => FileMatch::Found(file),
+        many => FileMatch::Ambiguous(many.len()),
+    }
+}
+
+/// Читает файл списка
+fn read_list(path: &Path) -> ManifestResult<String> {
+    std::fs::read_to_string(path).map_err(|e| ManifestError::unreadable(path.to_path_buf(), e.to_string()))
+}
+
+/// Список в формате JSON (по расширению), иначе CSV
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+/// Разбирает CSV-список построчно и возвращает записи с номерами строк
+///
+/// Одна запись - одна строка файла, чтобы ошибки указывали точный номер строки.
+/// Первая запись со словом `header` во второй колонке считается заголовком;
+/// пустые строки и строки, начинающиеся с `#`, пропускаются.
+fn csv_records(content: &str, path: &Path, header: &str) -> ManifestResult<Vec<(usize, csv::StringRecord)>> {
+    let mut records = Vec::new();
+    let mut header_checked = false;
+    
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(line.as_bytes());
+        
+        let record = match reader.records().next() {
+            Some(record) => record.map_err(|e| {
+                ManifestError::malformed(path.to_path_buf(), format!("line {}: {}", index + 1, e))
+            })?,
+            None => continue,
+        };
+        
+        let is_header = !header_checked && record.get(1).is_some_and(|field| field.eq_ignore_ascii_case(header));
+        header_checked = true;
+        if !is_header {
+            records.push((index + 1, record));
+        }
+    }
+    
+    Ok(records)
+}
+
+/// Разбирает поля одной строки списка нарезки
+fn parse_row(
+    row: usize,
+    file: Option<&str>,
+    start: Option<&str>,
+    end: Option<&str>,
+    title: Option<&str>,
+    crop: Option<&str>,
+) -> ManifestResult<CutRow> {
+    let file = file
+        .map(str::trim)
+        .filter(|file| !file.is_empty())
+        .ok_or_else(|| ManifestError::invalid_range("", row, "missing file name"))?;
+    
+    let parse_time = |value: Option<&str>, name: &str| {
+        let value = value.ok_or_else(|| ManifestError::invalid_range(file, row, format!("missing {}", name)))?;
+        parse_time_spec(value)
+            .ok_or_else(|| ManifestError::invalid_range(file, row, format!("cannot parse {} '{}'", name, value)))
+    };
+    
+    let start = parse_time(start, "start")?;
+    let end = parse_time(end, "end")?;
+    
+    if end <= start {
+        return Err(ManifestError::invalid_range(
+            file,
+            row,
+            format!("end {:.3}s is not after start {:.3}s", end, start),
+        ));
+    }
+    
+    let crop = match crop.map(str::trim).filter(|crop| !crop.is_empty()) {
+        Some(crop) => Some(SourceCrop::parse(crop).map_err(|reason| ManifestError::invalid_crop(file, row, reason))?),
+        None => None,
+    };
+    
+    Ok(CutRow {
+        row,
+        file: file.to_string(),
+        range: TimeRange::new(start, end),
+        title: title.map(str::trim).filter(|title| !title.is_empty()).map(String::from),
+        crop,
+        input: None,
+    })
+}
+
+/// Запись JSON-списка нарезки в исходном виде
+#[derive(Debug, Deserialize)]
+struct RawCutEntry {
+    file: Option<String>,
+    start: Option<RawTime>,
+    end: Option<RawTime>,
+    title: Option<String>,
+    crop: Option<String>,
+}
+
+/// Запись JSON-списка пар в исходном виде
+#[derive(Debug, Deserialize)]
+struct RawPairEntry {
+    top: Option<String>,
+    bottom: Option<String>,
+}
+
+/// Время в JSON: число секунд или строка
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawTime {
+    Seconds(f64),
+    Text(String),
+}
+
+impl RawTime {
+    fn as_text(&self) -> String {
+        match self {
+            RawTime::Seconds(seconds) => seconds.to_string(),
+            RawTime::Text(text) => text.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CropRect, Resolution};
+    use std::fs;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_parse_csv() {
+        let csv = "\
+file,start,end,title
+talk.mp4,00:01:00,00:01:45,Best joke
+# комментарий
+talk.mp4, 300 , 5m30s
+day1/interview.mp4,10,20,\"Intro, part one\"
+
+broken.mp4,1m,
+reversed.mp4,40,20,Oops
+archive.mp4,0,30,,1920:800:0:140
+bars.mp4,0,10,,wide
+odd.mp4,0,10,,1919:800:0:140
+huge.mp4,0,10,,4294967294:10:2:0
+";
+        let list = CutList::parse_csv(csv, Path::new("cuts.csv")).unwrap();
+        
+        assert_eq!(list.rows.len(), 5);
+        assert_eq!(list.rows[0].range, TimeRange::new(60.0, 105.0));
+        assert_eq!(list.rows[0].title.as_deref(), Some("Best joke"));
+        assert_eq!(list.rows[0].row, 2);
+        assert_eq!(list.rows[1].range, TimeRange::new(300.0, 330.0));
+        assert_eq!(list.rows[1].title, None);
+        assert_eq!(list.rows[2].title.as_deref(), Some("Intro, part one"));
+        assert_eq!(list.rows[2].crop, None);
+        assert_eq!(list.rows[3].title, None);
+        assert_eq!(list.rows[3].crop, Some(SourceCrop::Rect(CropRect { width: 1920, height: 800, x: 0, y: 140 })));
+        
+        // Прямоугольник за пределами кадра отсеивается при рендере, без переполнения
+        let huge = list.rows[4].crop.and_then(|crop| crop.rect()).unwrap();
+        assert!(!huge.fits(Resolution::new(1920, 1080)));
+        assert!(list.rows[3].crop.and_then(|crop| crop.rect()).unwrap().fits(Resolution::new(1920, 1080)));
+        
+        assert_eq!(list.errors.len(), 4);
+        assert!(matches!(&list.errors[0], ManifestError::InvalidRange { file, row: 7, .. } if file == "broken.mp4"));
+        assert!(matches!(&list.errors[1], ManifestError::InvalidRange { file, row: 8, .. } if file == "reversed.mp4"));
+        assert!(matches!(&list.errors[2], ManifestError::InvalidCrop { file, row: 10, .. } if file == "bars.mp4"));
+        assert!(matches!(&list.errors[3], ManifestError::InvalidCrop { file, row: 11, reason } if reason.contains("even")));
+    }
+    
+    #[test]
+    fn test_parse_json() {
+        let json = r#"[
+            {"file": "talk.mp4", "start": 60, "end": "1m45s", "title": "Best joke", "crop": "none"},
+            {"file": "talk.mp4", "start": "00:05:00", "end": 330.5},
+            {"file": "talk.mp4", "start": "later", "end": 10}
+        ]"#;
+        let list = CutList::parse_json(json, Path::new("cuts.json")).unwrap();
+        
+        assert_eq!(list.rows.len(), 2);
+        assert_eq!(list.rows[0].range, TimeRange::new(60.0, 105.0));
+        assert_eq!(list.rows[0].crop, Some(SourceCrop::Full));
+        assert_eq!(list.rows[1].range, TimeRange::new(300.0, 330.5));
+        assert!(matches!(&list.errors[0], ManifestError::InvalidRange { row: 3, .. }));
+        
+        assert!(matches!(
+            CutList::parse_json("{\"file\": 1}", Path::new("cuts.json")),
+            Err(ManifestError::Malformed { .. })
+        ));
+    }
+    
+    #[test]
+    fn test_rows_for_input() {
+        let csv = "talk.mp4,0,10\nday1/talk.mp4,10,20\nnote.mp4,0,5\nclip.mp4,0,5\nday9/talk.mp4,0,5\n";
+        let files: Vec<PathBuf> = ["/input/talk.mp4", "/input/day1/talk.mp4", "/input/day1/note.mp4", "/input/a/clip.mp4", "/input/b/clip.mp4"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let mut list = CutList::parse_csv(csv, Path::new("cuts.csv")).unwrap();
+        list.resolve(Path::new("/input"), &files);
+        
+        // Путь сравнивается целиком от входной директории; голое имя из поддиректории - если оно единственное
+        assert_eq!(list.rows_for(&files[0]).len(), 1);
+        assert_eq!(list.rows_for(&files[1]).len(), 1);
+        assert_eq!(list.rows_for(&files[2]).len(), 1);
+        assert!(list.rows_for(&files[3]).is_empty());
+        assert_eq!(list.errors, vec![
+            ManifestError::ambiguous_file("clip.mp4", 4, 2),
+            ManifestError::unknown_file("day9/talk.mp4", 5),
+        ]);
+    }
+    
+    #[test]
+    fn test_load_detects_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let csv_path = temp_dir.path().join("cuts.csv");
+        let json_path = temp_dir.path().join("cuts.JSON");
+        fs::write(&csv_path, "talk.mp4,0,10\n").unwrap();
+        fs::write(&json_path, r#"[{"file": "talk.mp4", "start": 0, "end": 10}]"#).unwrap();
+        
+        assert_eq!(CutList::load(&csv_path).unwrap().rows.len(), 1);
+        assert_eq!(CutList::load(&json_path).unwrap().rows.len(), 1);
+        assert!(matches!(
+            CutList::load(&temp_dir.path().join("missing.csv")),
+            Err(ManifestError::Unreadable { .. })
+        ));
+    }
+    
+    #[test]
+    fn test_parse_pair_list() {
+        let csv = "\
+top,bottom
+facecam.mp4, gameplay.mp4
+# комментарий
+day1/cam.mp4,day1/game.mp4
+solo.mp4,
+same.mp4,same.mp4
+";
+        let list = PairList::parse_csv(csv, Path::new("pairs.csv")).unwrap();
+        
+        assert_eq!(list.rows.len(), 2);
+        assert_eq!(list.rows[0], PairRow { row: 2, top: "facecam.mp4".to_string(), bottom: "gameplay.mp4".to_string() });
+        assert_eq!(list.rows[1].top, "day1/cam.mp4");
+        assert_eq!(list.errors, vec![
+            ManifestError::invalid_pair(5, "missing bottom file"),
+            ManifestError::invalid_pair(6, "'same.mp4' is paired with itself"),
+        ]);
+        
+        let json = r#"[{"top": "cam.mp4", "bottom": "game.mp4"}, {"bottom": "game.mp4"}]"#;
+        let list = PairList::parse_json(json, Path::new("pairs.json")).unwrap();
+        assert_eq!(list.rows.len(), 1);
+        assert_eq!(list.errors, vec![ManifestError::invalid_pair(2, "missing top file")]);
+    }
+    
+    #[test]
+    fn test_resolve_pairs() {
+        let csv = "\
+facecam.mp4,gameplay.mp4
+day1/cam.mp4,day1/game.mp4
+cam.mp4,missing.mp4
+facecam.mp4,day1/game.mp4
+day1/cam.mp4,game.mp4
+";
+        let list = PairList::parse_csv(csv, Path::new("pairs.csv")).unwrap();
+        let files: Vec<PathBuf> = ["/in/facecam.mp4", "/in/gameplay.mp4", "/in/day1/cam.mp4", "/in/day1/game.mp4", "/in/day2/game.mp4"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        
+        let (pairs, errors) = list.resolve(Path::new("/in"), &files);
+        assert_eq!(pairs, vec![
+            (files[0].clone(), files[1].clone()),
+            (files[2].clone(), files[3].clone()),
+        ]);
+        // Повторный верхний файл перезаписал бы выход первой пары
+        assert_eq!(errors, vec![
+            ManifestError::invalid_pair(3, "file 'missing.mp4' not found in the input directory"),
+            ManifestError::invalid_pair(4, "top file 'facecam.mp4' is already used by another pair and would overwrite its output"),
+            ManifestError::invalid_pair(5, "'game.mp4' matches 2 files in the input directory; use a path relative to it"),
+        ]);
+    }
+}
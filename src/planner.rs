@@ -0,0 +1,1102 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+
+use crate::analysis::{detect_scene_changes, detect_silences, measure_loudness, LoudnessSample};
+use crate::config::{
+    AudioSelection, OutputVariant, CUT_RANGE_TOLERANCE, LOUDNESS_FLOOR_LUFS, MIN_SPEECH_SEGMENT, MIN_TAIL_SEGMENT,
+    SILENCE_EDGE_TOLERANCE,
+};
+use crate::error::{FfmpegError, ManifestError};
+use crate::manifest::{CutList, PairList};
+use crate::probe::probe_media;
+use crate::utils::{create_language_tasks, create_segment_tasks, pair_split_inputs, FileTask, TimeRange};
+use crate::worker::TaskResult;
+
+/// Стратегия нарезки входного файла на шортсы
+#[derive(Debug, Clone, PartialEq)]
+pub enum CutStrategy {
+    /// Файл целиком становится одним шортсом
+    Whole,
+    /// Файл режется на части фиксированной длины
+    FixedLength { seconds: f64 },
+    /// Файл режется по сменам сцен, найденным предварительным проходом FFmpeg
+    Scenes(SceneCutSettings),
+    /// Фрагменты берутся из списка нарезки (`--cuts`); файлы вне списка пропускаются
+    CutList(Arc<CutList>),
+    /// Тишина, найденная `silencedetect`, обрезается по краям или служит точками нарезки
+    Silence(SilenceCutSettings),
+    /// Из файла вырезаются самые громкие непересекающиеся окна по шкале `ebur128`
+    Highlights(HighlightSettings),
+}
+
+/// Параметры поиска ярких моментов по громкости
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HighlightSettings {
+    /// Сколько фрагментов выбрать из каждого файла
+    pub count: usize,
+    /// Длина фрагмента в секундах
+    pub length: f64,
+}
+
+/// Выбранное окно и его средняя громкость
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Highlight {
+    pub range: TimeRange,
+    /// Средняя кратковременная громкость окна в LUFS
+    pub loudness: f64,
+}
+
+/// Что делать с найденной тишиной
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SilenceMode {
+    /// Обрезать тишину в начале и в конце файла
+    Trim,
+    /// Резать файл на части по паузам, выбрасывая сами паузы
+    Split,
+}
+
+impl SilenceMode {
+    /// Разбирает режим из аргумента CLI (`trim` или `split`)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "trim" => Some(Self::Trim),
+            "split" => Some(Self::Split),
+            _ => None,
+        }
+    }
+}
+
+/// Параметры обработки тишины
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilenceCutSettings {
+    pub mode: SilenceMode,
+    /// Уровень шума в dB для `silencedetect=noise=`
+    pub noise_db: f64,
+    /// Минимальная длительность паузы в секундах
+    pub min_silence: f64,
+}
+
+/// Параметры нарезки по сценам
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneCutSettings {
+    /// Порог `select='gt(scene,X)'`
+    pub threshold: f64,
+    /// Минимальная длина части в секундах
+    pub min_length: f64,
+    /// Максимальная длина части в секундах
+    pub max_length: f64,
+}
+
+/// Параметры построения плана обработки
+#[derive(Debug, Clone)]
+pub struct PlanSettings {
+    pub strategy: CutStrategy,
+    /// Входная директория: пути из списка нарезки считаются от нее
+    pub input_dir: PathBuf,
+    pub output_dir: PathBuf,
+    pub variants: Vec<OutputVariant>,
+    /// Сколько файлов анализировать через ffprobe одновременно
+    pub threads: usize,
+    /// Выбор звуковой дорожки (`--audio-track`, `--per-language`)
+    pub audio: AudioSelection,
+}
+
+/// План обработки: задачи для worker pool и ошибки, случившиеся при подготовке
+#[derive(Debug)]
+pub struct ProcessingPlan {
+    pub tasks: Vec<FileTask>,
+    pub failures: Vec<TaskResult>,
+    /// Сколько секунд тишины вырезано из каждого исходника
+    pub removed_silence: Vec<(PathBuf, f64)>,
+}
+
+/// Результат планирования одного исходника
+#[derive(Debug, Default)]
+struct SourcePlan {
+    tasks: Vec<FileTask>,
+    /// Фрагменты, отклоненные после анализа (например, строки `--cuts` за пределами файла)
+    rejected: Vec<String>,
+    /// Сколько секунд тишины вырезано из исходника
+    removed_silence: Option<f64>,
+}
+
+/// Анализирует входные файлы и разворачивает их в задачи согласно стратегии
+///
+/// ffprobe и аналитические проходы запускаются параллельно (не больше `threads`
+/// процессов), порядок задач сохраняется. Файлы, которые не удалось
+/// проанализировать, попадают в `failures`.
+pub async fn build_plan(tasks: Vec<FileTask>, settings: &PlanSettings) -> ProcessingPlan {
+    let mut plan = ProcessingPlan {
+        tasks: Vec::new(),
+        failures: Vec::new(),
+        removed_silence: Vec::new(),
+    };
+    
+    // Строки списка нарезки привязываются к входным файлам до анализа
+    let mut shared_settings = settings.clone();
+    let tasks = match &mut shared_settings.strategy {
+        CutStrategy::CutList(list) => {
+            select_listed_tasks(tasks, Arc::make_mut(list), &settings.input_dir, &mut plan.failures)
+        }
+        _ => tasks,
+    };
+    
+    let semaphore = Arc::new(Semaphore::new(settings.threads.max(1)));
+    let shared_settings = Arc::new(shared_settings);
+    let mut jobs = JoinSet::new();
+    
+    for (index, task) in tasks.into_iter().enumerate() {
+        let semaphore = Arc::clone(&semaphore);
+        let settings = Arc::clone(&shared_settings);
+        
+        jobs.spawn(async move {
+            let _permit = semaphore.acquire().await.unwrap();
+            let start_time = Instant::now();
+            let input = task.input.clone();
+            
+            let result = plan_source(task, &settings).await;
+            
+            (index, input, result, start_time.elapsed())
+        });
+    }
+    
+    let mut planned = Vec::new();
+    while let Some(joined) = jobs.join_next().await {
+        match joined {
+            Ok(entry) => planned.push(entry),
+            Err(e) => error!("Planning task join error: {}", e),
+        }
+    }
+    planned.sort_by_key(|(index, ..)| *index);
+    
+    for (_, input, result, duration) in planned {
+        match result {
+            Ok(source_plan) => {
+                for task in source_plan.tasks {
+                    let part = task.part.clone();
+                    match select_audio_tracks(task, settings) {
+                        Ok(tasks) => plan.tasks.extend(tasks),
+                        Err(error_msg) => {
+                            error!("ERROR: {}: {}", input.file_name().unwrap_or_default().to_string_lossy(), error_msg);
+                            plan.failures.push(TaskResult::Failure {
+                                input: input.clone(),
+                                part,
+                                error: error_msg,
+                                duration,
+                            });
+                        }
+                    }
+                }
+                if let Some(seconds) = source_plan.removed_silence {
+                    plan.removed_silence.push((input.clone(), seconds));
+                }
+                for error_msg in source_plan.rejected {
+                    error!("ERROR: {}: {}", input.file_name().unwrap_or_default().to_string_lossy(), error_msg);
+                    plan.failures.push(TaskResult::Failure {
+                        input: input.clone(),
+                        part: None,
+                        error: error_msg,
+                        duration,
+                    });
+                }
+            }
+            Err(error_msg) => {
+                error!("ERROR: {}: {}", input.file_name().unwrap_or_default().to_string_lossy(), error_msg);
+                
+                plan.failures.push(TaskResult::Failure {
+                    input,
+                    part: None,
+                    error: error_msg,
+                    duration,
+                });
+            }
+        }
+    }
+    
+    plan
+}
+
+/// Привязывает строки списка нарезки к входным файлам и оставляет только файлы из списка
+///
+/// Строки, которые не удалось разобрать, и строки с ненайденными или
+/// неоднозначными файлами сразу попадают в `failures` от имени списка.
+fn select_listed_tasks(
+    tasks: Vec<FileTask>,
+    list: &mut CutList,
+    input_dir: &Path,
+    failures: &mut Vec<TaskResult>,
+) -> Vec<FileTask> {
+    let inputs: Vec<PathBuf> = tasks.iter().map(|task| task.input.clone()).collect();
+    list.resolve(input_dir, &inputs);
+    
+    for error in &list.errors {
+        error!("ERROR: {}", error);
+        failures.push(TaskResult::Failure {
+            input: list.path.clone(),
+            part: None,
+            error: error.to_string(),
+            duration: std::time::Duration::ZERO,
+        });
+    }
+    
+    tasks
+        .into_iter()
+        .filter(|task| {
+            let listed = !list.rows_for(&task.input).is_empty();
+            if !listed {
+                info!("Skipping {}: not listed in {}", task.input_filename(), list.path.display());
+            }
+            listed
+        })
+        .collect()
+}
+
+/// Собирает входные файлы в пары разделенного экрана: по списку `--split-pairs` или по именам
+///
+/// Ошибки списка пар сразу попадают в `failures`; файлы без пары пропускаются.
+pub fn select_split_pairs(
+    files: &[PathBuf],
+    input_dir: &Path,
+    list: Option<&PairList>,
+    failures: &mut Vec<TaskResult>,
+) -> Vec<(PathBuf, PathBuf)> {
+    let (pairs, skipped) = match list {
+        Some(list) => {
+            let (pairs, errors) = list.resolve(input_dir, files);
+            for error in errors {
+                error!("ERROR: {}", error);
+                failures.push(TaskResult::Failure {
+                    input: list.path.clone(),
+                    part: None,
+                    error: error.to_string(),
+                    duration: std::time::Duration::ZERO,
+                });
+            }
+            let skipped = files
+                .iter()
+                .filter(|file| !pairs.iter().any(|(top, bottom)| top == *file || bottom == *file))
+                .cloned()
+                .collect();
+            (pairs, skipped)
+        }
+        None => pair_split_inputs(files),
+    };
+    
+    for file in skipped {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        match list {
+            Some(list) => info!("Skipping {}: not listed in {}", name, list.path.display()),
+            None => warn!("Skipping {}: no split-screen partner (expected NAME.top.mp4 and NAME.bottom.mp4)", name),
+        }
+    }
+    
+    pairs
+}
+
+/// Анализирует один исходник и возвращает задачи для него
+async fn plan_source(mut task: FileTask, settings: &PlanSettings) -> Result<SourcePlan, String> {
+    let duration = match &task.media_info {
+        Some(media_info) => media_info.duration,
+        None => {
+            let media_info = probe_media(&task.input)
+                .await
+                .map_err(|e| format!("Media probe failed: {}", e))?;
+            let duration = media_info.duration;
+            task.media_info = Some(media_info);
+            duration
+        }
+    };
+    
+    // Второй исходник разделенного экрана режется теми же фрагментами: клип не длиннее короткого из двух
+    let duration = match task.split_input.clone() {
+        Some(path) => {
+            let media_info = match task.split_media_info.take() {
+                Some(media_info) => media_info,
+                None => probe_media(&path)
+                    .await
+                    .map_err(|e| format!("Media probe failed for {}: {}", path.display(), e))?,
+            };
+            let split_duration = media_info.duration;
+            task.split_media_info = Some(media_info);
+            
+            if split_duration > 0.0 && (duration <= 0.0 || split_duration < duration) {
+                split_duration
+            } else {
+                duration
+            }
+        }
+        None => duration,
+    };
+    
+    let segments = match &settings.strategy {
+        CutStrategy::Whole => Vec::new(),
+        CutStrategy::FixedLength { seconds } => fixed_segments(duration, *seconds),
+        CutStrategy::CutList(list) => return Ok(plan_listed_cuts(task, list, duration, settings)),
+        CutStrategy::Silence(silence) => return plan_silence_cuts(task, silence, duration, settings).await,
+        CutStrategy::Highlights(highlights) => return plan_highlights(task, highlights, duration, settings).await,
+        CutStrategy::Scenes(scene) => {
+            let cuts = detect_scene_changes(&task.input, scene.threshold, duration)
+                .await
+                .map_err(|e| format!("Scene detection failed: {}", e))?;
+            info!("SCENES: {} -> {} scene changes", task.input_filename(), cuts.len());
+            
+            scene_segments(&cuts, duration, scene.min_length, scene.max_length)
+        }
+    };
+    
+    if settings.strategy != CutStrategy::Whole && duration <= 0.0 {
+        warn!("Unknown duration for {}, processing as a single clip", task.input_filename());
+    }
+    
+    Ok(SourcePlan {
+        tasks: split_task(task, &segments, settings),
+        ..SourcePlan::default()
+    })
+}
+
+/// Назначает задаче звуковую дорожку по `settings.audio`
+///
+/// Для `PerLanguage` задача разворачивается в отдельную задачу на каждый язык
+/// (первая дорожка каждого языка; дорожки без тега - `und`). Исходники без
+/// звука не трогаются - их обрабатывает `--missing-audio`.
+fn select_audio_tracks(mut task: FileTask, settings: &PlanSettings) -> Result<Vec<FileTask>, String> {
+    let streams = match &task.media_info {
+        Some(media_info) if media_info.has_audio() => media_info.audio_streams(),
+        _ => return Ok(vec![task]),
+    };
+    
+    let language = |index: usize| streams[index].language.clone().unwrap_or_else(|| "und".to_string());
+    let not_found = |selector: String| {
+        let available: Vec<String> = (0..streams.len()).map(|i| format!("{}: {}", i, language(i))).collect();
+        FfmpegError::audio_track_not_found(task.input.clone(), selector, available.join(", ")).to_string()
+    };
+    
+    let selected = match &settings.audio {
+        AudioSelection::First => None,
+        AudioSelection::Index(index) if *index < streams.len() => Some(*index),
+        AudioSelection::Index(index) => return Err(not_found(format!("#{}", index))),
+        AudioSelection::Language(tag) => match (0..streams.len()).find(|i| language(*i).eq_ignore_ascii_case(tag)) {
+            Some(index) => Some(index),
+            None => return Err(not_found(format!("'{}'", tag))),
+        },
+        AudioSelection::PerLanguage => {
+            let mut tracks: Vec<(usize, String)> = Vec::new();
+            for index in 0..streams.len() {
+                let tag = language(index).to_ascii_lowercase();
+                if !tracks.iter().any(|(_, known)| *known == tag) {
+                    tracks.push((index, tag));
+                }
+            }
+            
+            let tasks = create_language_tasks(&task, &tracks, &settings.output_dir, &settings.variants);
+            return Ok(tasks);
+        }
+    };
+    
+    task.audio_track = selected;
+    Ok(vec![task])
+}
+
+/// Обрезает или режет исходник по тишине, найденной `silencedetect`
+async fn plan_silence_cuts(
+    mut task: FileTask,
+    silence: &SilenceCutSettings,
+    duration: f64,
+    settings: &PlanSettings,
+) -> Result<SourcePlan, String> {
+    let has_audio = task.media_info.as_ref().is_some_and(|media_info| media_info.has_audio());
+    if !has_audio || duration <= 0.0 {
+        warn!("Skipping silence detection for {}: no audio or unknown duration", task.input_filename());
+        return Ok(SourcePlan {
+            tasks: vec![task],
+            ..SourcePlan::default()
+        });
+    }
+    
+    let silences = detect_silences(&task.input, silence.noise_db, silence.min_silence, duration)
+        .await
+        .map_err(|e| format!("Silence detection failed: {}", e))?;
+    info!("SILENCE: {} -> {} pauses", task.input_filename(), silences.len());
+    
+    let segments = match silence.mode {
+        SilenceMode::Trim => trim_silence(&silences, duration).into_iter().collect(),
+        SilenceMode::Split => speech_segments(&silences, duration, MIN_SPEECH_SEGMENT),
+    };
+    
+    if segments.is_empty() {
+        return Err(format!("No audible content found (noise threshold {}dB)", silence.noise_db));
+    }
+    
+    let removed = removed_silence(&silences, &segments, duration);
+    
+    let tasks = if segments.len() == 1 {
+        // Один фрагмент - обычное имя файла, но с обрезанными краями
+        if segments[0].duration() < duration {
+            task.segment = Some(segments[0]);
+        }
+        vec![task]
+    } else {
+        log_segments(&task.input, &segments);
+        create_segment_tasks(&task, &segments, &settings.output_dir, &settings.variants)
+    };
+    
+    Ok(SourcePlan {
+        tasks,
+        rejected: Vec::new(),
+        removed_silence: Some(removed),
+    })
+}
+
+/// Вырезает из исходника самые громкие фрагменты
+///
+/// Каждое выбранное окно становится отдельной частью, даже если окно одно.
+async fn plan_highlights(
+    task: FileTask,
+    highlights: &HighlightSettings,
+    duration: f64,
+    settings: &PlanSettings,
+) -> Result<SourcePlan, String> {
+    let has_audio = task.media_info.as_ref().is_some_and(|media_info| media_info.has_audio());
+    if !has_audio {
+        return Err("No audio track for loudness analysis".to_string());
+    }
+    
+    if duration <= highlights.length {
+        warn!(
+            "{} is not longer than {}s, processing as a single clip",
+            task.input_filename(),
+            highlights.length
+        );
+        return Ok(SourcePlan {
+            tasks: vec![task],
+            ..SourcePlan::default()
+        });
+    }
+    
+    let timeline = measure_loudness(&task.input, duration)
+        .await
+        .map_err(|e| format!("Loudness analysis failed: {}", e))?;
+    
+    let picked = pick_highlights(&timeline, duration, highlights.count, highlights.length);
+    if picked.is_empty() {
+        return Err("No audible content found for highlights".to_string());
+    }
+    
+    info!("HIGHLIGHTS: {} -> {} windows", task.input_filename(), picked.len());
+    for (index, highlight) in picked.iter().enumerate() {
+        info!("  part {}: {} ({:.1} LUFS)", index + 1, highlight.range, highlight.loudness);
+    }
+    
+    let ranges: Vec<TimeRange> = picked.iter().map(|highlight| highlight.range).collect();
+    Ok(SourcePlan {
+        tasks: create_segment_tasks(&task, &ranges, &settings.output_dir, &settings.variants),
+        ..SourcePlan::default()
+    })
+}
+
+/// Выбирает до `count` самых громких непересекающихся окон длиной `length`
+///
+/// Окно начинается в момент каждого отсчета и оценивается по средней
+/// мощности отсчетов внутри (громкость в LUFS переводится в линейную шкалу,
+/// чтобы короткий крик не тонул в среднем по децибелам). Окна выбираются
+/// жадно от самого громкого, полностью тихие окна не выбираются. Результат
+/// отсортирован по времени.
+pub fn pick_highlights(timeline: &[LoudnessSample], duration: f64, count: usize, length: f64) -> Vec<Highlight> {
+    if timeline.is_empty() || count == 0 || length <= 0.0 || duration < length {
+        return Vec::new();
+    }
+    
+    let power = |sample: &LoudnessSample| {
+        if sample.momentary > LOUDNESS_FLOOR_LUFS {
+            10f64.powf(sample.momentary / 10.0)
+        } else {
+            0.0
+        }
+    };
+    
+    let mut prefix = Vec::with_capacity(timeline.len() + 1);
+    prefix.push(0.0);
+    for sample in timeline {
+        prefix.push(prefix[prefix.len() - 1] + power(sample));
+    }
+    
+    // (начало, средняя мощность) для каждого окна, которое помещается в файл
+    let mut candidates = Vec::new();
+    let mut end_index = 0;
+    for (start_index, sample) in timeline.iter().enumerate() {
+        let start = sample.time.max(0.0);
+        if start + length > duration {
+            break;
+        }
+        end_index = end_index.max(start_index);
+        while end_index < timeline.len() && timeline[end_index].time < start + length {
+            end_index += 1;
+        }
+        
+        let samples = (end_index - start_index) as f64;
+        let mean = (prefix[end_index] - prefix[start_index]) / samples;
+        if mean > 0.0 {
+            candidates.push((start, mean));
+        }
+    }
+    
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.total_cmp(&b.0)));
+    
+    let mut picked: Vec<Highlight> = Vec::new();
+    for (start, mean) in candidates {
+        let range = TimeRange::new(start, start + length);
+        if picked.iter().any(|other| range.start < other.range.end && other.range.start < range.end) {
+            continue;
+        }
+        
+        picked.push(Highlight {
+            range,
+            loudness: 10.0 * mean.log10(),
+        });
+        if picked.len() == count {
+            break;
+        }
+    }
+    
+    picked.sort_by(|a, b| a.range.start.total_cmp(&b.range.start));
+    picked
+}
+
+/// Возвращает диапазон файла без тишины в начале и в конце
+///
+/// `None`, если весь файл - тишина.
+pub fn trim_silence(silences: &[TimeRange], duration: f64) -> Option<TimeRange> {
+    let mut range = TimeRange::new(0.0, duration);
+    
+    for silence in silences {
+        if silence.start <= range.start + SILENCE_EDGE_TOLERANCE && silence.end > range.start {
+            range.start = silence.end;
+        }
+    }
+    for silence in silences.iter().rev() {
+        if silence.end >= range.end - SILENCE_EDGE_TOLERANCE && silence.start < range.end {
+            range.end = silence.start;
+        }
+    }
+    
+    (range.duration() > 0.0).then_some(range)
+}
+
+/// Возвращает фрагменты речи между паузами
+///
+/// Фрагменты короче `min_length` (кашель, щелчок между двумя паузами) отбрасываются.
+pub fn speech_segments(silences: &[TimeRange], duration: f64, min_length: f64) -> Vec<TimeRange> {
+    let mut segments = Vec::new();
+    let mut start = 0.0;
+    
+    for silence in silences {
+        if silence.start > start {
+            segments.push(TimeRange::new(start, silence.start.min(duration)));
+        }
+        start = start.max(silence.end);
+    }
+    if duration > start {
+        segments.push(TimeRange::new(start, duration));
+    }
+    
+    segments.retain(|segment| segment.duration() >= min_length);
+    segments
+}
+
+/// Сколько секунд найденной тишины не вошло в оставленные фрагменты
+///
+/// Считаются только сами паузы в пределах файла: короткие фрагменты речи,
+/// отброшенные `speech_segments`, тишиной не являются.
+pub fn removed_silence(silences: &[TimeRange], kept: &[TimeRange], duration: f64) -> f64 {
+    let overlap = |range: &TimeRange, start: f64, end: f64| (range.end.min(end) - range.start.max(start)).max(0.0);
+    
+    silences
+        .iter()
+        .map(|silence| {
+            let inside_kept: f64 = kept.iter().map(|segment| overlap(silence, segment.start, segment.end)).sum();
+            (overlap(silence, 0.0, duration) - inside_kept).max(0.0)
+        })
+        .sum()
+}
+
+/// Строит задачи по строкам списка нарезки для одного исходника
+///
+/// Каждая строка становится отдельной частью, даже если строка одна.
+/// Фрагменты, выходящие за длительность файла больше чем на `CUT_RANGE_TOLERANCE`,
+/// отклоняются; небольшой выход обрезается по концу файла.
+fn plan_listed_cuts(task: FileTask, list: &CutList, duration: f64, settings: &PlanSettings) -> SourcePlan {
+    let mut plan = SourcePlan::default();
+    let mut ranges = Vec::new();
+    let mut details = Vec::new();
+    
+    for row in list.rows_for(&task.input) {
+        let mut range = row.range;
+        if duration > 0.0 {
+            if range.end > duration + CUT_RANGE_TOLERANCE {
+                plan.rejected.push(
+                    ManifestError::beyond_duration(&row.file, row.row, range.end, duration).to_string()
+                );
+                continue;
+            }
+            range.end = range.end.min(duration);
+        }
+        ranges.push(range);
+        details.push((row.title.clone(), row.crop));
+    }
+    
+    if ranges.is_empty() {
+        return plan;
+    }
+    
+    log_segments(&task.input, &ranges);
+    plan.tasks = create_segment_tasks(&task, &ranges, &settings.output_dir, &settings.variants);
+    for (task, (title, crop)) in plan.tasks.iter_mut().zip(details) {
+        task.title = title;
+        task.crop = crop;
+    }
+    
+    plan
+}
+
+/// Разворачивает задачу по фрагментам; один фрагмент или ни одного - файл целиком
+fn split_task(task: FileTask, segments: &[TimeRange], settings: &PlanSettings) -> Vec<FileTask> {
+    if segments.len() <= 1 {
+        return vec![task];
+    }
+    
+    log_segments(&task.input, segments);
+    create_segment_tasks(&task, segments, &settings.output_dir, &settings.variants)
+}
+
+/// Делит длительность на фрагменты фиксированной длины
+///
+/// Хвост короче `MIN_TAIL_SEGMENT` присоединяется к последнему фрагменту,
+/// чтобы не получать шортсы из нескольких кадров.
+pub fn fixed_segments(duration: f64, length: f64) -> Vec<TimeRange> {
+    if duration <= 0.0 || length <= 0.0 {
+        return Vec::new();
+    }
+    
+    let mut segments = Vec::new();
+    let mut start = 0.0;
+    while start < duration {
+        let end = (start + length).min(duration);
+        segments.push(TimeRange::new(start, end));
+        start = end;
+    }
+    
+    if segments.len() > 1 {
+        let tail = segments[segments.len() - 1];
+        if tail.duration() < MIN_TAIL_SEGMENT {
+            segments.pop();
+            if let Some(last) = segments.last_mut() {
+                last.end = tail.end;
+            }
+        }
+    }
+    
+    segments
+}
+
+/// Объединяет сцены в фрагменты длиной от `min_length` до `max_length`
+///
+/// Фрагмент растет сцена за сценой и закрывается на последней смене сцены,
+/// после которой он уже не короче минимума, - как только следующая сцена
+/// вывела бы его за максимум. Если подходящей смены сцены нет (например,
+/// длинный план без склеек), фрагмент режется принудительно по максимуму.
+/// Последний фрагмент может оказаться короче минимума; хвост короче
+/// `MIN_TAIL_SEGMENT` присоединяется к предыдущему фрагменту.
+pub fn scene_segments(cuts: &[f64], duration: f64, min_length: f64, max_length: f64) -> Vec<TimeRange> {
+    if duration <= 0.0 || max_length <= 0.0 {
+        return Vec::new();
+    }
+    
+    let mut segments: Vec<TimeRange> = Vec::new();
+    let mut start = 0.0;
+    // Смены сцен внутри текущего фрагмента, на которых его можно закрыть
+    let mut pending: Vec<f64> = Vec::new();
+    
+    let boundaries = cuts
+        .iter()
+        .copied()
+        .filter(|&cut| cut > 0.0 && cut < duration)
+        .chain(std::iter::once(duration));
+    
+    for boundary in boundaries {
+        while boundary - start > max_length {
+            let end = pending
+                .iter()
+                .rev()
+                .copied()
+                .find(|&cut| cut - start >= min_length)
+                .unwrap_or(start + max_length);
+            
+            segments.push(TimeRange::new(start, end));
+            start = end;
+            pending.retain(|&cut| cut > start);
+        }
+        
+        if boundary < duration {
+            pending.push(boundary);
+        }
+    }
+    
+    let tail = TimeRange::new(start, duration);
+    if tail.duration() > 0.0 {
+        match segments.last_mut() {
+            Some(last) if tail.duration() < MIN_TAIL_SEGMENT => last.end = tail.end,
+            _ => segments.push(tail),
+        }
+    }
+    
+    segments
+}
+
+/// Логирует фрагменты, на которые разбит исходник
+fn log_segments(input: &Path, segments: &[TimeRange]) {
+    info!(
+        "PLAN: {} -> {} parts",
+        input.file_name().unwrap_or_default().to_string_lossy(),
+        segments.len()
+    );
+    for (index, segment) in segments.iter().enumerate() {
+        info!("  part {}: {}", index + 1, segment);
+    }
+}
+
+#[cfg(test)]
+impl PlanSettings {
+    /// Настройки для тестов: один формат по умолчанию, вывод в `/output`
+    pub fn for_tests(strategy: CutStrategy) -> Self {
+        Self {
+            strategy,
+            input_dir: PathBuf::from("/input"),
+            output_dir: PathBuf::from("/output"),
+            variants: vec![OutputVariant {
+                label: crate::config::DEFAULT_RESOLUTION.to_string(),
+                resolution: crate::config::DEFAULT_RESOLUTION,
+            }],
+            threads: 2,
+            audio: AudioSelection::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SourceCrop;
+    use crate::probe::MediaInfo;
+    
+    fn probed_task(duration: f64) -> FileTask {
+        let mut task = FileTask::new(PathBuf::from("/input/talk.mp4"), vec![PathBuf::from("/output/talk-short.mp4")]);
+        task.media_info = Some(MediaInfo::synthetic(1920, 1080, duration, true));
+        task
+    }
+    
+    /// Задача с исходником, в котором несколько звуковых дорожек с указанными языками
+    fn multilingual_task(languages: &[Option<&str>]) -> FileTask {
+        let mut task = probed_task(60.0);
+        let media_info = task.media_info.as_mut().unwrap();
+        let template = media_info.audio_streams()[0].clone();
+        media_info.streams.retain(|stream| stream.kind != crate::probe::StreamKind::Audio);
+        for (offset, language) in languages.iter().enumerate() {
+            media_info.streams.push(crate::probe::StreamInfo {
+                index: offset + 1,
+                language: language.map(String::from),
+                ..template.clone()
+            });
+        }
+        task
+    }
+    
+    #[test]
+    fn test_select_audio_tracks() {
+        let task = multilingual_task(&[Some("eng"), Some("rus"), Some("eng"), None]);
+        let with_audio = |audio: AudioSelection| PlanSettings { audio, ..PlanSettings::for_tests(CutStrategy::Whole) };
+        
+        let selected = select_audio_tracks(task.clone(), &with_audio(AudioSelection::First)).unwrap();
+        assert_eq!(selected[0].audio_track, None);
+        
+        let selected = select_audio_tracks(task.clone(), &with_audio(AudioSelection::Index(3))).unwrap();
+        assert_eq!(selected[0].audio_track, Some(3));
+        
+        let selected = select_audio_tracks(task.clone(), &with_audio(AudioSelection::Language("RUS".to_string()))).unwrap();
+        assert_eq!(selected[0].audio_track, Some(1));
+        assert_eq!(selected[0].outputs, task.outputs);
+        
+        let error = select_audio_tracks(task.clone(), &with_audio(AudioSelection::Language("deu".to_string()))).unwrap_err();
+        assert!(error.contains("'deu'") && error.contains("0: eng, 1: rus, 2: eng, 3: und"), "{}", error);
+        assert!(select_audio_tracks(task.clone(), &with_audio(AudioSelection::Index(4))).is_err());
+        
+        // Одна задача на язык: первая дорожка каждого языка, без тега - und
+        let selected = select_audio_tracks(task, &with_audio(AudioSelection::PerLanguage)).unwrap();
+        let tracks: Vec<_> = selected.iter().map(|t| (t.audio_track.unwrap(), t.part.clone().unwrap())).collect();
+        assert_eq!(
+            tracks,
+            vec![(0, "eng".to_string()), (1, "rus".to_string()), (3, "und".to_string())]
+        );
+        assert_eq!(selected[1].outputs, vec![PathBuf::from("/output/talk-short-rus.mp4")]);
+        
+        // Исходник без звука не трогается
+        let mut silent = probed_task(60.0);
+        silent.media_info = Some(MediaInfo::synthetic(1920, 1080, 60.0, false));
+        assert_eq!(select_audio_tracks(silent, &with_audio(AudioSelection::Index(2))).unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_fixed_segments() {
+        let segments = fixed_segments(150.0, 60.0);
+        assert_eq!(
+            segments,
+            vec![TimeRange::new(0.0, 60.0), TimeRange::new(60.0, 120.0), TimeRange::new(120.0, 150.0)]
+        );
+        
+        // Короткий хвост присоединяется к последней части
+        let segments = fixed_segments(120.5, 60.0);
+        assert_eq!(segments, vec![TimeRange::new(0.0, 60.0), TimeRange::new(60.0, 120.5)]);
+        
+        assert_eq!(fixed_segments(30.0, 60.0), vec![TimeRange::new(0.0, 30.0)]);
+        assert!(fixed_segments(0.0, 60.0).is_empty());
+    }
+    
+    #[test]
+    fn test_scene_segments_respect_bounds() {
+        // Сцены: 0-10, 10-25, 25-32, 32-70, 70-80, 80-150
+        let cuts = [10.0, 25.0, 32.0, 70.0, 80.0];
+        let segments = scene_segments(&cuts, 150.0, 15.0, 60.0);
+        
+        assert_eq!(
+            segments,
+            vec![
+                TimeRange::new(0.0, 32.0),
+                TimeRange::new(32.0, 80.0),
+                TimeRange::new(80.0, 140.0),
+                TimeRange::new(140.0, 150.0),
+            ]
+        );
+        for segment in &segments[..segments.len() - 1] {
+            assert!(segment.duration() >= 15.0 && segment.duration() <= 60.0, "{}", segment);
+        }
+    }
+    
+    #[test]
+    fn test_scene_segments_edge_cases() {
+        // Сцены 20-50 и 50-55 помещаются в один фрагмент
+        let segments = scene_segments(&[20.0, 50.0], 55.0, 15.0, 40.0);
+        assert_eq!(segments, vec![TimeRange::new(0.0, 20.0), TimeRange::new(20.0, 55.0)]);
+        
+        // Хвост из нескольких кадров не становится отдельным шортсом
+        let segments = scene_segments(&[30.0], 60.5, 15.0, 30.0);
+        assert_eq!(segments, vec![TimeRange::new(0.0, 30.0), TimeRange::new(30.0, 60.5)]);
+        
+        // Без смен сцен файл режется по максимуму
+        let segments = scene_segments(&[], 100.0, 15.0, 40.0);
+        assert_eq!(
+            segments,
+            vec![TimeRange::new(0.0, 40.0), TimeRange::new(40.0, 80.0), TimeRange::new(80.0, 100.0)]
+        );
+        
+        // Короткий файл остается одним фрагментом
+        assert_eq!(scene_segments(&[3.0, 7.0], 12.0, 15.0, 60.0), vec![TimeRange::new(0.0, 12.0)]);
+    }
+    
+    #[test]
+    fn test_split_task_fans_out_segments() {
+        let settings = PlanSettings::for_tests(CutStrategy::FixedLength { seconds: 60.0 });
+        
+        let tasks = split_task(probed_task(150.0), &fixed_segments(150.0, 60.0), &settings);
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[1].outputs, vec![PathBuf::from("/output/talk-short-part02.mp4")]);
+        assert_eq!(tasks[2].segment, Some(TimeRange::new(120.0, 150.0)));
+        assert!(tasks.iter().all(|task| task.media_info.is_some()));
+        
+        // Короткий файл не режется и сохраняет обычное имя
+        let tasks = split_task(probed_task(45.0), &fixed_segments(45.0, 60.0), &settings);
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].segment, None);
+        assert_eq!(tasks[0].outputs, vec![PathBuf::from("/output/talk-short.mp4")]);
+    }
+    
+    #[tokio::test]
+    async fn test_whole_strategy_keeps_task() {
+        let settings = PlanSettings::for_tests(CutStrategy::Whole);
+        let tasks = plan_source(probed_task(600.0), &settings).await.unwrap().tasks;
+        
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].part, None);
+    }
+    
+    #[tokio::test]
+    async fn test_split_screen_plan() {
+        let settings = PlanSettings::for_tests(CutStrategy::FixedLength { seconds: 60.0 });
+        
+        // Части режутся по более короткому из двух исходников
+        let mut task = probed_task(150.0);
+        task.split_input = Some(PathBuf::from("/input/game.mp4"));
+        task.split_media_info = Some(MediaInfo::synthetic(1920, 1080, 100.0, true));
+        let tasks = plan_source(task, &settings).await.unwrap().tasks;
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[1].segment, Some(TimeRange::new(60.0, 100.0)));
+        assert!(tasks.iter().all(|task| task.split_media_info.is_some()));
+        
+        let files: Vec<PathBuf> = ["/in/cam.mp4", "/in/game.mp4", "/in/extra.mp4"].iter().map(PathBuf::from).collect();
+        let list = PairList::parse_csv("cam.mp4,game.mp4\ncam.mp4,lost.mp4\n", Path::new("pairs.csv")).unwrap();
+        let mut failures = Vec::new();
+        let pairs = select_split_pairs(&files, Path::new("/in"), Some(&list), &mut failures);
+        assert_eq!(pairs, vec![(files[0].clone(), files[1].clone())]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].input_path(), &PathBuf::from("pairs.csv"));
+        
+        assert!(select_split_pairs(&files, Path::new("/in"), None, &mut failures).is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_build_plan_reports_probe_failures() {
+        let settings = PlanSettings::for_tests(CutStrategy::FixedLength { seconds: 60.0 });
+        let tasks = vec![
+            probed_task(150.0),
+            FileTask::new(PathBuf::from("/nonexistent/missing.mp4"), vec![PathBuf::from("/output/missing-short.mp4")]),
+        ];
+        
+        let plan = build_plan(tasks, &settings).await;
+        
+        assert_eq!(plan.tasks.len(), 3);
+        assert_eq!(plan.failures.len(), 1);
+        assert_eq!(plan.failures[0].input_path(), &PathBuf::from("/nonexistent/missing.mp4"));
+    }
+    
+    #[tokio::test]
+    async fn test_cut_list_plan() {
+        let csv = "\
+talk.mp4,60,105,Best joke
+talk.mp4,500,700,Too late
+talk.mp4,140,150.05,,none
+missing.mp4,0,10
+talk.mp4,20,10
+";
+        let list = CutList::parse_csv(csv, Path::new("cuts.csv")).unwrap();
+        let settings = PlanSettings::for_tests(CutStrategy::CutList(Arc::new(list)));
+        let other = {
+            let mut task = probed_task(30.0);
+            task.input = PathBuf::from("/input/unlisted.mp4");
+            task
+        };
+        
+        let plan = build_plan(vec![probed_task(150.0), other], &settings).await;
+        
+        assert_eq!(plan.tasks.len(), 2);
+        assert_eq!(plan.tasks[0].segment, Some(TimeRange::new(60.0, 105.0)));
+        assert_eq!(plan.tasks[0].title.as_deref(), Some("Best joke"));
+        assert_eq!(plan.tasks[0].outputs, vec![PathBuf::from("/output/talk-short-part01.mp4")]);
+        // Небольшой выход за конец файла обрезается
+        assert_eq!(plan.tasks[1].segment, Some(TimeRange::new(140.0, 150.0)));
+        assert_eq!(plan.tasks[1].title, None);
+        // Обрезка из списка заменяет поиск полос только для своей строки
+        assert_eq!(plan.tasks[0].crop, None);
+        assert_eq!(plan.tasks[1].crop, Some(SourceCrop::Full));
+        
+        let errors: Vec<&str> = plan.failures
+            .iter()
+            .map(|failure| match failure {
+                TaskResult::Failure { error, .. } => error.as_str(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(errors.len(), 3);
+        assert!(errors.iter().any(|e| e.contains("row 5") && e.contains("not after start")));
+        assert!(errors.iter().any(|e| e.contains("row 4") && e.contains("not found")));
+        assert!(errors.iter().any(|e| e.contains("row 2") && e.contains("only 150.000s long")));
+        // Ошибки строк относятся к самому списку, а не к пустому пути
+        assert!(plan.failures[..2].iter().all(|failure| failure.input_path() == Path::new("cuts.csv")));
+    }
+    
+    #[test]
+    fn test_trim_silence() {
+        let silences = [TimeRange::new(0.0, 2.5), TimeRange::new(14.0, 18.0), TimeRange::new(57.0, 60.0)];
+        assert_eq!(trim_silence(&silences, 60.0), Some(TimeRange::new(2.5, 57.0)));
+        
+        // Пауза в середине не трогается, края без тишины остаются
+        assert_eq!(trim_silence(&[TimeRange::new(14.0, 18.0)], 60.0), Some(TimeRange::new(0.0, 60.0)));
+        
+        // Весь файл - тишина
+        assert_eq!(trim_silence(&[TimeRange::new(0.0, 60.0)], 60.0), None);
+        
+        // Вырезаны только края; пауза внутри оставленного фрагмента не считается
+        assert_eq!(removed_silence(&silences, &[TimeRange::new(2.5, 57.0)], 60.0), 5.5);
+    }
+    
+    #[test]
+    fn test_speech_segments() {
+        let silences = [
+            TimeRange::new(0.0, 2.5),
+            TimeRange::new(14.0, 18.0),
+            TimeRange::new(19.0, 25.0),
+            TimeRange::new(57.0, 60.0),
+        ];
+        
+        // Фрагмент 18-19 короче минимума и отбрасывается
+        assert_eq!(
+            speech_segments(&silences, 60.0, 2.0),
+            vec![TimeRange::new(2.5, 14.0), TimeRange::new(25.0, 57.0)]
+        );
+        assert_eq!(speech_segments(&[], 30.0, 2.0), vec![TimeRange::new(0.0, 30.0)]);
+        
+        // Отброшенная речь 18-19 не считается тишиной; пауза за концом файла обрезается
+        let kept = speech_segments(&silences, 60.0, 2.0);
+        assert_eq!(removed_silence(&silences, &kept, 60.0), 15.5);
+        assert_eq!(removed_silence(&[TimeRange::new(55.0, 65.0)], &[TimeRange::new(0.0, 55.0)], 60.0), 5.0);
+    }
+    
+    /// Шкала громкости с шагом 1 секунда: тихий фон и громкие всплески
+    fn loudness_timeline(duration: usize, loud: &[(usize, usize, f64)]) -> Vec<LoudnessSample> {
+        (0..duration)
+            .map(|second| {
+                let momentary = loud
+                    .iter()
+                    .find(|(start, end, _)| (*start..*end).contains(&second))
+                    .map_or(-40.0, |(_, _, lufs)| *lufs);
+                LoudnessSample { time: second as f64, momentary }
+            })
+            .collect()
+    }
+    
+    #[test]
+    fn test_pick_highlights_without_overlap() {
+        // Самый громкий участок 100-110, второй 20-30, третий 60-70
+        let timeline = loudness_timeline(200, &[(20, 30, -18.0), (60, 70, -20.0), (100, 110, -12.0)]);
+        let picked = pick_highlights(&timeline, 200.0, 2, 10.0);
+        
+        let ranges: Vec<TimeRange> = picked.iter().map(|h| h.range).collect();
+        assert_eq!(ranges, vec![TimeRange::new(20.0, 30.0), TimeRange::new(100.0, 110.0)]);
+        assert!((picked[1].loudness - -12.0).abs() < 1e-9);
+        
+        // Окна не пересекаются, даже если громкий участок длиннее окна
+        let timeline = loudness_timeline(100, &[(10, 40, -15.0)]);
+        let picked = pick_highlights(&timeline, 100.0, 3, 10.0);
+        assert_eq!(picked.len(), 3);
+        for pair in picked.windows(2) {
+            assert!(pair[0].range.end <= pair[1].range.start);
+        }
+        assert!(picked.iter().all(|h| h.range.start >= 10.0 && h.range.end <= 40.0));
+    }
+    
+    #[test]
+    fn test_pick_highlights_edge_cases() {
+        // Полная тишина - ничего не выбирается
+        let silent = loudness_timeline(60, &[(0, 60, LOUDNESS_FLOOR_LUFS)]);
+        assert!(pick_highlights(&silent, 60.0, 3, 10.0).is_empty());
+        
+        // Окно не выходит за конец файла
+        let timeline = loudness_timeline(60, &[(55, 60, -10.0)]);
+        let picked = pick_highlights(&timeline, 60.0, 1, 10.0);
+        assert_eq!(picked[0].range, TimeRange::new(50.0, 60.0));
+        
+        assert!(pick_highlights(&timeline, 5.0, 1, 10.0).is_empty());
+        assert!(pick_highlights(&[], 60.0, 1, 10.0).is_empty());
+    }
+}
@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use serde::Deserialize;
+use tokio::process::Command;
+use tokio::time::timeout;
+use tracing::debug;
+use crate::config::{FFPROBE_EXECUTABLE, FFPROBE_TIMEOUT};
+use crate::error::{FfmpegError, FfmpegResult};
+
+/// Тип потока внутри медиаконтейнера
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Other,
+}
+
+/// Информация об отдельном потоке медиафайла
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    /// Индекс потока в контейнере
+    pub index: usize,
+    pub kind: StreamKind,
+    pub codec_name: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Средняя частота кадров (только для видео)
+    pub fps: Option<f64>,
+    /// Поворот при показе в градусах по часовой стрелке, нормализованный в [0, 360)
+    ///
+    /// Берется из матрицы отображения (side data), в старых файлах - из тега `rotate`.
+    pub rotation: i32,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    /// Язык из тега `language` (если указан)
+    pub language: Option<String>,
+}
+
+/// Типизированная модель медиафайла, построенная по выводу ffprobe
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaInfo {
+    pub format_name: String,
+    /// Длительность в секундах
+    pub duration: f64,
+    /// Общий битрейт контейнера в битах в секунду
+    pub bit_rate: Option<u64>,
+    pub streams: Vec<StreamInfo>,
+}
+
+impl MediaInfo {
+    /// Возвращает основной видеопоток
+    pub fn video_stream(&self) -> Option<&StreamInfo> {
+        self.streams.iter().find(|s| s.kind == StreamKind::Video)
+    }
+    
+    /// Возвращает все аудиопотоки в порядке следования в контейнере
+    pub fn audio_streams(&self) -> Vec<&StreamInfo> {
+        self.streams.iter().filter(|s| s.kind == StreamKind::Audio).collect()
+    }
+    
+    /// Проверяет наличие хотя бы одного аудиопотока
+    pub fn has_audio(&self) -> bool {
+        self.streams.iter().any(|s| s.kind == StreamKind::Audio)
+    }
+    
+    /// Ширина кадра основного видеопотока при показе
+    ///
+    /// FFmpeg поворачивает кадры по метаданным при декодировании, поэтому
+    /// графу достается кадр уже в этой ориентации: при повороте на 90 или 270
+    /// градусов ширина и высота закодированного кадра меняются местами.
+    pub fn width(&self) -> u32 {
+        let stream = self.video_stream();
+        let size = if self.is_sideways() { stream.and_then(|s| s.height) } else { stream.and_then(|s| s.width) };
+        size.unwrap_or(0)
+    }
+    
+    /// Высота кадра основного видеопотока при показе (см. `width`)
+    pub fn height(&self) -> u32 {
+        let stream = self.video_stream();
+        let size = if self.is_sideways() { stream.and_then(|s| s.width) } else { stream.and_then(|s| s.height) };
+        size.unwrap_or(0)
+    }
+    
+    /// Кадр повернут на четверть оборота (телефон снимал вертикально в горизонтальный кадр)
+    fn is_sideways(&self) -> bool {
+        self.rotation() % 180 == 90
+    }
+    
+    /// Частота кадров основного видеопотока
+    pub fn fps(&self) -> Option<f64> {
+        self.video_stream().and_then(|s| s.fps)
+    }
+    
+    /// Поворот основного видеопотока
+    pub fn rotation(&self) -> i32 {
+        self.video_stream().map(|s| s.rotation).unwrap_or(0)
+    }
+    
+    /// Краткое описание для логов
+    pub fn summary(&self) -> String {
+        let video = match self.video_stream() {
+            Some(stream) => format!(
+                "{} {}x{} @ {:.2}fps",
+                stream.codec_name,
+                self.width(),
+                self.height(),
+                self.fps().unwrap_or(0.0)
+            ),
+            None => "no video".to_string(),
+        };
+        
+        let audio = if self.has_audio() {
+            self.audio_streams()
+                .iter()
+                .map(|s| s.codec_name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        } else {
+            "none".to_string()
+        };
+        
+        let rotation = if self.rotation() != 0 {
+            format!(", rotation {}°", self.rotation())
+        } else {
+            String::new()
+        };
+        
+        format!("{}, {:.2}s, audio: {}{}", video, self.duration, audio, rotation)
+    }
+}
+
+/// Запускает ffprobe для файла и строит модель MediaInfo
+pub async fn probe_media(path: &Path) -> FfmpegResult<MediaInfo> {
+    debug!("Probing media file: {}", path.display());
+    
+    let child = Command::new(FFPROBE_EXECUTABLE)
+        .args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams"])
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|_| FfmpegError::CannotSpawnProcess)?;
+    
+    let output = match timeout(FFPROBE_TIMEOUT, child.wait_with_output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(_)) => return Err(FfmpegError::CannotSpawnProcess),
+        Err(_) => return Err(FfmpegError::timeout(FFPROBE_TIMEOUT.as_secs())),
+    };
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().last().unwrap_or("unknown error").trim().to_string();
+        return Err(FfmpegError::probe_failed(path.to_path_buf(), reason));
+    }
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_media_info(&stdout, path)
+}
+
+/// Разбирает JSON-вывод `ffprobe -show_format -show_streams`
+pub fn parse_media_info(json: &str, path: &Path) -> FfmpegResult<MediaInfo> {
+    let raw: RawProbeOutput = serde_json::from_str(json)
+        .map_err(|e| FfmpegError::probe_failed(path.to_path_buf(), format!("invalid ffprobe output: {}", e)))?;
+    
+    let streams: Vec<StreamInfo> = raw.streams.iter().map(StreamInfo::from_raw).collect();
+    
+    if !streams.iter().any(|s| s.kind == StreamKind::Video) {
+        return Err(FfmpegError::no_video_stream(path.to_path_buf()));
+    }
+    
+    let format = raw.format.unwrap_or_default();
+    
+    // Длительность берем из контейнера, а если ее нет - из самого длинного потока
+    let duration = parse_number(format.duration.as_deref())
+        .or_else(|| {
+            raw.streams
+                .iter()
+                .filter_map(|s| parse_number(s.duration.as_deref()))
+                .fold(None, |max: Option<f64>, d| Some(max.map_or(d, |m| m.max(d))))
+        })
+        .filter(|d| *d > 0.0)
+        .ok_or_else(|| FfmpegError::probe_failed(path.to_path_buf(), "unknown duration".to_string()))?;
+    
+    Ok(MediaInfo {
+        format_name: format.format_name.unwrap_or_default(),
+        duration,
+        bit_rate: parse_number(format.bit_rate.as_deref()).map(|b| b as u64),
+        streams,
+    })
+}
+
+impl StreamInfo {
+    fn from_raw(raw: &RawStream) -> Self {
+        let kind = match raw.codec_type.as_deref() {
+            Some("video") => StreamKind::Video,
+            Some("audio") => StreamKind::Audio,
+            Some("subtitle") => StreamKind::Subtitle,
+            _ => StreamKind::Other,
+        };
+        
+        let fps = if kind == StreamKind::Video {
+            parse_frame_rate(raw.avg_frame_rate.as_deref())
+                .or_else(|| parse_frame_rate(raw.r_frame_rate.as_deref()))
+        } else {
+            None
+        };
+        
+        // Матрица отображения задает поворот против часовой стрелки, тег `rotate` - по часовой
+        let display_rotation = raw.side_data_list
+            .iter()
+            .find(|side_data| side_data.side_data_type.as_deref() == Some("Display Matrix"))
+            .and_then(|side_data| side_data.rotation)
+            .filter(|degrees| degrees.is_finite())
+            .map(|degrees| -degrees.round() as i32);
+        let rotation = display_rotation
+            .or_else(|| raw.tags.get("rotate").and_then(|r| r.trim().parse::<i32>().ok()))
+            .map(normalize_rotation)
+            .unwrap_or(0);
+        
+        Self {
+            index: raw.index,
+            kind,
+            codec_name: raw.codec_name.clone().unwrap_or_else(|| "unknown".to_string()),
+            width: raw.width,
+            height: raw.height,
+            fps,
+            rotation,
+            sample_rate: parse_number(raw.sample_rate.as_deref()).map(|r| r as u32),
+            channels: raw.channels,
+            language: raw.tags
+                .get("language")
+                .filter(|lang| !lang.is_empty() && lang.as_str() != "und")
+                .cloned(),
+        }
+    }
+}
+
+/// Разбирает частоту кадров вида `30000/1001`
+fn parse_frame_rate(value: Option<&str>) -> Option<f64> {
+    let value = value?;
+    let fps = match value.split_once('/') {
+        Some((num, den)) => {
+            let num: f64 = num.trim().parse().ok()?;
+            let den: f64 = den.trim().parse().ok()?;
+            if den == 0.0 {
+                return None;
+            }
+            num / den
+        }
+        None => value.trim().parse().ok()?,
+    };
+    
+    if fps > 0.0 && fps.is_finite() {
+        Some(fps)
+    } else {
+        None
+    }
+}
+
+/// Разбирает числовое значение, которое ffprobe отдает строкой
+fn parse_number(value: Option<&str>) -> Option<f64> {
+    value?.trim().parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// Приводит угол поворота к диапазону [0, 360)
+fn normalize_rotation(degrees: i32) -> i32 {
+    degrees.rem_euclid(360)
+}
+
+#[derive(Deserialize)]
+struct RawProbeOutput {
+    #[serde(default)]
+    streams: Vec<RawStream>,
+    format: Option<RawFormat>,
+}
+
+#[derive(Deserialize)]
+struct RawStream {
+    index: usize,
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    side_data_list: Vec<RawSideData>,
+}
+
+#[derive(Deserialize)]
+struct RawSideData {
+    side_data_type: Option<String>,
+    /// Поворот из матрицы отображения: градусы против часовой стрелки
+    rotation: Option<f64>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawFormat {
+    format_name: Option<String>,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+}
+
+#[cfg(test)]
+impl MediaInfo {
+    /// Синтетическая модель для тестов построения команд
+    pub fn synthetic(width: u32, height: u32, duration: f64, has_audio: bool) -> Self {
+        let mut streams = vec![StreamInfo {
+            index: 0,
+            kind: StreamKind::Video,
+            codec_name: "h264".to_string(),
+            width: Some(width),
+            height: Some(height),
+            fps: Some(30.0),
+            rotation: 0,
+            sample_rate: None,
+            channels: None,
+            language: None,
+        }];
+        
+        if has_audio {
+            streams.push(StreamInfo {
+                index: 1,
+                kind: StreamKind::Audio,
+                codec_name: "aac".to_string(),
+                width: None,
+                height: None,
+                fps: None,
+                rotation: 0,
+                sample_rate: Some(48000),
+                channels: Some(2),
+                language: None,
+            });
+        }
+        
+        Self {
+            format_name: "mov,mp4,m4a,3gp,3g2,mj2".to_string(),
+            duration,
+            bit_rate: Some(4_000_000),
+            streams,
+        }
+    }
+    
+    /// Та же модель с поворотом видеопотока из метаданных (съемка телефоном)
+    pub fn rotated(mut self, rotation: i32) -> Self {
+        for stream in self.streams.iter_mut().filter(|s| s.kind == StreamKind::Video) {
+            stream.rotation = normalize_rotation(rotation);
+        }
+        self
+    }
+    
+    /// Та же модель с другой частотой кадров видеопотока
+    pub fn with_fps(mut self, fps: f64) -> Self {
+        for stream in self.streams.iter_mut().filter(|s| s.kind == StreamKind::Video) {
+            stream.fps = Some(fps);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    
+    const SAMPLE_PROBE_JSON: &str = r#"{
+        "streams": [
+            {
+                "index": 0,
+                "codec_name": "h264",
+                "codec_type": "video",
+                "width": 1920,
+                "height": 1080,
+                "r_frame_rate": "30000/1001",
+                "avg_frame_rate": "30000/1001",
+                "duration": "12.345000",
+                "tags": { "language": "und" }
+            },
+            {
+                "index": 1,
+                "codec_name": "aac",
+                "codec_type": "audio",
+                "sample_rate": "48000",
+                "channels": 2,
+                "duration": "12.300000",
+                "tags": { "language": "eng" }
+            }
+        ],
+        "format": {
+            "format_name": "mov,mp4,m4a,3gp,3g2,mj2",
+            "duration": "12.345000",
+            "bit_rate": "5000000"
+        }
+    }"#;
+    
+    #[test]
+    fn test_parse_media_info() {
+        let info = parse_media_info(SAMPLE_PROBE_JSON, &PathBuf::from("clip.mp4")).unwrap();
+        
+        assert_eq!(info.width(), 1920);
+        assert_eq!(info.height(), 1080);
+        assert!((info.duration - 12.345).abs() < 1e-9);
+        assert!((info.fps().unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(info.bit_rate, Some(5_000_000));
+        assert!(info.has_audio());
+        assert_eq!(info.rotation(), 0);
+        
+        let video = info.video_stream().unwrap();
+        assert_eq!(video.codec_name, "h264");
+        assert_eq!(video.language, None);
+        
+        let audio = info.audio_streams();
+        assert_eq!(audio.len(), 1);
+        assert_eq!(audio[0].sample_rate, Some(48000));
+        assert_eq!(audio[0].language.as_deref(), Some("eng"));
+    }
+    
+    #[test]
+    fn test_parse_media_info_without_audio() {
+        let json = r#"{
+            "streams": [
+                { "index": 0, "codec_name": "h264", "codec_type": "video",
+                  "width": 1280, "height": 720, "avg_frame_rate": "25/1",
+                  "tags": { "rotate": "-90" } }
+            ],
+            "format": { "format_name": "mp4", "duration": "3.0" }
+        }"#;
+        
+        let info = parse_media_info(json, &PathBuf::from("broll.mp4")).unwrap();
+        assert!(!info.has_audio());
+        assert_eq!(info.rotation(), 270);
+        assert_eq!(info.fps(), Some(25.0));
+        // Кадр 1280x720, повернутый на четверть оборота, показывается вертикальным
+        assert_eq!((info.width(), info.height()), (720, 1280));
+    }
+    
+    #[test]
+    fn test_parse_display_matrix_rotation() {
+        // Телефон пишет вертикальное видео горизонтальным кадром с матрицей отображения
+        let json = r#"{
+            "streams": [
+                { "index": 0, "codec_name": "hevc", "codec_type": "video",
+                  "width": 1920, "height": 1080, "avg_frame_rate": "30/1",
+                  "tags": { "rotate": "180" },
+                  "side_data_list": [
+                      { "side_data_type": "DOVI configuration record" },
+                      { "side_data_type": "Display Matrix",
+                        "displaymatrix": "\n00000000:            0       65536           0\n00000001:       -65536           0           0\n00000002:            0           0  1073741824\n",
+                        "rotation": -90 }
+                  ] }
+            ],
+            "format": { "format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "15.0" }
+        }"#;
+        
+        let info = parse_media_info(json, &PathBuf::from("IMG_0042.MOV")).unwrap();
+        assert_eq!(info.rotation(), 90);
+        assert_eq!((info.width(), info.height()), (1080, 1920));
+        assert_eq!(info.video_stream().unwrap().width, Some(1920));
+        assert!(info.summary().starts_with("hevc 1080x1920 @ 30.00fps"));
+        
+        // Переворот не меняет стороны местами
+        let upside_down = MediaInfo::synthetic(1920, 1080, 10.0, false).rotated(-180);
+        assert_eq!(upside_down.rotation(), 180);
+        assert_eq!((upside_down.width(), upside_down.height()), (1920, 1080));
+        let counter_clockwise = MediaInfo::synthetic(1920, 1080, 10.0, false).rotated(-90);
+        assert_eq!((counter_clockwise.width(), counter_clockwise.height()), (1080, 1920));
+    }
+    
+    #[test]
+    fn test_parse_media_info_errors() {
+        let path = PathBuf::from("broken.mp4");
+        
+        assert!(matches!(
+            parse_media_info("not json", &path),
+            Err(FfmpegError::ProbeFailed { .. })
+        ));
+        
+        let audio_only = r#"{
+            "streams": [ { "index": 0, "codec_name": "mp3", "codec_type": "audio" } ],
+            "format": { "duration": "10.0" }
+        }"#;
+        assert!(matches!(
+            parse_media_info(audio_only, &path),
+            Err(FfmpegError::NoVideoStream { .. })
+        ));
+        
+        let no_duration = r#"{
+            "streams": [ { "index": 0, "codec_name": "h264", "codec_type": "video" } ],
+            "format": { "format_name": "mp4" }
+        }"#;
+        assert!(matches!(
+            parse_media_info(no_duration, &path),
+            Err(FfmpegError::ProbeFailed { .. })
+        ));
+    }
+    
+    #[test]
+    fn test_parse_frame_rate() {
+        assert_eq!(parse_frame_rate(Some("30/1")), Some(30.0));
+        assert_eq!(parse_frame_rate(Some("0/0")), None);
+        assert_eq!(parse_frame_rate(Some("24")), Some(24.0));
+        assert_eq!(parse_frame_rate(None), None);
+    }
+}
@@ -0,0 +1,482 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
+use serde::Deserialize;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+use tracing::{debug, info};
+
+use crate::analysis::run_analysis_pass;
+use crate::config::{CAPTION_MAX_DURATION, CAPTION_MAX_GAP, CAPTION_MAX_WORDS, TRANSCRIBE_SAMPLE_RATE};
+use crate::error::{ConfigError, ConfigResult, TranscribeError, TranscribeResult};
+use crate::ffmpeg::timeout_for_duration;
+use crate::probe::MediaInfo;
+use crate::utils::{format_seconds, FileTask};
+
+/// Асинхронный результат транскрибатора - путь к созданному файлу
+pub type TranscribeFuture<'a> = Pin<Box<dyn Future<Output = TranscribeResult<PathBuf>> + Send + 'a>>;
+
+/// Локальный движок распознавания речи
+///
+/// Получает звук клипа (WAV 16 кГц, моно) и префикс пути результата, создает
+/// `<prefix>.srt` или `<prefix>.json` с таймингами слов и возвращает путь к нему.
+pub trait Transcriber: std::fmt::Debug + Send + Sync {
+    fn transcribe<'a>(&'a self, audio: &'a Path, output_prefix: &'a Path, limit: Duration) -> TranscribeFuture<'a>;
+}
+
+/// Транскрибатор, который запускает внешнюю команду (например, whisper.cpp)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandTranscriber {
+    /// Программа и ее аргументы; `{audio}` и `{output}` подставляются перед запуском
+    pub args: Vec<String>,
+}
+
+impl CommandTranscriber {
+    /// Разбирает шаблон `--transcribe`
+    ///
+    /// Аргументы разделяются пробелами, одинарные и двойные кавычки группируют
+    /// аргумент с пробелами. Шаблон должен содержать `{audio}` и `{output}`.
+    pub fn parse(template: &str) -> ConfigResult<Self> {
+        let args = split_command_line(template)
+            .ok_or_else(|| ConfigError::invalid_arg(format!("Unterminated quote in transcription command: {}", template)))?;
+        
+        if args.is_empty() {
+            return Err(ConfigError::invalid_arg("Transcription command is empty"));
+        }
+        for placeholder in ["{audio}", "{output}"] {
+            if !args.iter().any(|arg| arg.contains(placeholder)) {
+                return Err(ConfigError::invalid_arg(format!(
+                    "Transcription command must contain {}: {}",
+                    placeholder, template
+                )));
+            }
+        }
+        
+        Ok(Self { args })
+    }
+    
+    /// Подставляет пути в аргументы команды
+    fn command_args(&self, audio: &Path, output_prefix: &Path) -> Vec<String> {
+        let audio = audio.to_string_lossy();
+        let output_prefix = output_prefix.to_string_lossy();
+        
+        self.args
+            .iter()
+            .map(|arg| arg.replace("{audio}", &audio).replace("{output}", &output_prefix))
+            .collect()
+    }
+}
+
+impl Transcriber for CommandTranscriber {
+    fn transcribe<'a>(&'a self, audio: &'a Path, output_prefix: &'a Path, limit: Duration) -> TranscribeFuture<'a> {
+        Box::pin(async move {
+            let args = self.command_args(audio, output_prefix);
+            debug!("Running transcription: {}", args.join(" "));
+            
+            let child = Command::new(&args[0])
+                .args(&args[1..])
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|_| TranscribeError::cannot_spawn(&args[0]))?;
+            
+            let output = match timeout(limit, child.wait_with_output()).await {
+                Ok(Ok(output)) => output,
+                Ok(Err(_)) => return Err(TranscribeError::cannot_spawn(&args[0])),
+                Err(_) => return Err(TranscribeError::timeout(limit.as_secs())),
+            };
+            
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let reason = stderr.lines().last().unwrap_or("unknown error").trim().to_string();
+                return Err(TranscribeError::command_failed(output.status.code().unwrap_or(-1), reason));
+            }
+            
+            ["srt", "json"]
+                .iter()
+                .map(|ext| with_suffix(output_prefix, ext))
+                .find(|path| path.is_file())
+                .ok_or_else(|| TranscribeError::no_output(output_prefix.to_path_buf()))
+        })
+    }
+}
+
+/// Слово с таймингом из JSON-результата распознавания (секунды от начала клипа)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WordTiming {
+    #[serde(alias = "text")]
+    pub word: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// JSON таймингов слов: массив слов или объект с полем `words`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawWordTimings {
+    List(Vec<WordTiming>),
+    Object { words: Vec<WordTiming> },
+}
+
+/// Разбирает JSON с таймингами слов; пустые слова отбрасываются
+pub fn parse_word_timings(content: &str) -> Result<Vec<WordTiming>, String> {
+    let words = match serde_json::from_str(content).map_err(|e| e.to_string())? {
+        RawWordTimings::List(words) | RawWordTimings::Object { words } => words,
+    };
+    
+    Ok(words
+        .into_iter()
+        .map(|word| WordTiming { word: word.word.trim().to_string(), ..word })
+        .filter(|word| !word.word.is_empty())
+        .collect())
+}
+
+/// Собирает SRT из таймингов слов
+///
+/// Новый субтитр начинается, когда в текущем набралось `CAPTION_MAX_WORDS` слов,
+/// он стал длиннее `CAPTION_MAX_DURATION` или перед словом пауза больше `CAPTION_MAX_GAP`.
+pub fn build_srt_from_words(words: &[WordTiming]) -> String {
+    let mut cues: Vec<Vec<&WordTiming>> = Vec::new();
+    
+    for word in words {
+        let starts_new_cue = match cues.last().and_then(|cue| Some((cue.first()?, cue.last()?, cue.len()))) {
+            Some((first, last, count)) => {
+                count >= CAPTION_MAX_WORDS
+                    || word.start - last.end > CAPTION_MAX_GAP
+                    || word.end - first.start > CAPTION_MAX_DURATION
+            }
+            None => true,
+        };
+        
+        if starts_new_cue {
+            cues.push(vec![word]);
+        } else if let Some(cue) = cues.last_mut() {
+            cue.push(word);
+        }
+    }
+    
+    let mut srt = String::new();
+    for (index, cue) in cues.iter().enumerate() {
+        let text: Vec<&str> = cue.iter().map(|word| word.word.as_str()).collect();
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            format_srt_time(cue[0].start),
+            format_srt_time(cue[cue.len() - 1].end),
+            text.join(" ")
+        ));
+    }
+    srt
+}
+
+/// Форматирует время для SRT: `HH:MM:SS,mmm`
+fn format_srt_time(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Путь кэша субтитров клипа: `<основной выход>.srt`
+pub fn transcript_cache_path(task: &FileTask) -> PathBuf {
+    task.primary_output().with_extension("srt")
+}
+
+/// Путь описания звука, из которого распознан кэш: `<основной выход>.transcript.key`
+fn transcript_key_path(task: &FileTask) -> PathBuf {
+    task.primary_output().with_extension("transcript.key")
+}
+
+/// Описание звука, который распознается: исходник, фрагмент и дорожка
+///
+/// Кэш переиспользуется, только если описание совпадает: после смены
+/// `--segment-length`, списка нарезки или выбора дорожки речь распознается заново.
+fn transcript_key(task: &FileTask) -> String {
+    let segment = task.segment.map_or("whole".to_string(), |segment| segment.to_string());
+    format!("input={}\nsegment={}\ntrack={}\n", task.input.display(), segment, task.audio_track.unwrap_or(0))
+}
+
+/// Кэш субтитров клипа, если он распознан из того же звука
+fn cached_transcript(task: &FileTask) -> Option<PathBuf> {
+    let cache = transcript_cache_path(task);
+    let key = std::fs::read_to_string(transcript_key_path(task)).ok()?;
+    (cache.is_file() && key == transcript_key(task)).then_some(cache)
+}
+
+/// Возвращает субтитры клипа (по времени клипа): из кэша рядом с выходом или распознав речь
+///
+/// Звук фрагмента извлекается во временный WAV и передается транскрибатору.
+/// Если движок выдал тайминги слов, они сохраняются в `<основной выход>.words.json`,
+/// а субтитры собираются из слов.
+pub async fn transcribe_task(
+    task: &FileTask,
+    media_info: &MediaInfo,
+    transcriber: &dyn Transcriber,
+) -> TranscribeResult<PathBuf> {
+    if let Some(cache) = cached_transcript(task) {
+        info!("TRANSCRIPT: reusing {}", cache.display());
+        return Ok(cache);
+    }
+    let cache = transcript_cache_path(task);
+    
+    let primary = task.primary_output();
+    let audio = primary.with_extension("transcribe.wav");
+    let output_prefix = primary.with_extension("transcribe");
+    if let Some(parent) = primary.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| TranscribeError::cannot_store(parent.to_path_buf(), e.to_string()))?;
+    }
+    
+    let clip_duration = task.segment.map_or(media_info.duration, |segment| segment.duration());
+    let args = build_transcribe_audio_args(task, &audio);
+    run_analysis_pass("audio extraction", &args, clip_duration).await?;
+    
+    let result = transcriber
+        .transcribe(&audio, &output_prefix, timeout_for_duration(clip_duration))
+        .await;
+    let _ = std::fs::remove_file(&audio);
+    
+    store_transcript(&result?, &cache)?;
+    let key_path = transcript_key_path(task);
+    std::fs::write(&key_path, transcript_key(task))
+        .map_err(|e| TranscribeError::cannot_store(key_path.clone(), e.to_string()))?;
+    info!("TRANSCRIPT: {} -> {}", task.display_name(), cache.display());
+    Ok(cache)
+}
+
+/// Строит аргументы извлечения звука фрагмента в WAV для распознавания
+fn build_transcribe_audio_args(task: &FileTask, audio: &Path) -> Vec<String> {
+    let mut args = vec!["-y".to_string(), "-hide_banner".to_string(), "-nostats".to_string()];
+    
+    if let Some(segment) = task.segment {
+        args.extend([
+            "-ss".to_string(),
+            format_seconds(segment.start),
+            "-t".to_string(),
+            format_seconds(segment.duration()),
+        ]);
+    }
+    
+    args.extend([
+        "-i".to_string(),
+        task.input.to_string_lossy().to_string(),
+        "-map".to_string(),
+        format!("0:a:{}", task.audio_track.unwrap_or(0)),
+        "-ac".to_string(),
+        "1".to_string(),
+        "-ar".to_string(),
+        TRANSCRIBE_SAMPLE_RATE.to_string(),
+        "-c:a".to_string(),
+        "pcm_s16le".to_string(),
+        audio.to_string_lossy().to_string(),
+    ]);
+    args
+}
+
+/// Переносит результат движка в кэш: SRT - как есть, JSON - через сборку субтитров из слов
+fn store_transcript(output: &Path, cache: &Path) -> TranscribeResult<()> {
+    let store_error = |e: std::io::Error| TranscribeError::cannot_store(cache.to_path_buf(), e.to_string());
+    
+    let is_json = output.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if !is_json {
+        return std::fs::rename(output, cache).map_err(store_error);
+    }
+    
+    let content = std::fs::read_to_string(output)
+        .map_err(|e| TranscribeError::invalid_output(output.to_path_buf(), e.to_string()))?;
+    let words = parse_word_timings(&content)
+        .map_err(|reason| TranscribeError::invalid_output(output.to_path_buf(), reason))?;
+    
+    std::fs::write(cache, build_srt_from_words(&words)).map_err(store_error)?;
+    std::fs::rename(output, cache.with_extension("words.json")).map_err(store_error)
+}
+
+/// Добавляет расширение к пути, не заменяя существующее (`clip.transcribe` -> `clip.transcribe.srt`)
+fn with_suffix(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+/// Делит командную строку на аргументы с учетом кавычек; `None` - незакрытая кавычка
+fn split_command_line(line: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+    
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    
+    if quote.is_some() {
+        return None;
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Some(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::utils::TimeRange;
+    use tempfile::TempDir;
+    
+    /// Подставной транскрибатор: пишет заранее заданный результат и считает вызовы
+    #[derive(Debug)]
+    struct FakeTranscriber {
+        calls: AtomicUsize,
+    }
+    
+    impl Transcriber for FakeTranscriber {
+        fn transcribe<'a>(&'a self, _audio: &'a Path, output_prefix: &'a Path, _limit: Duration) -> TranscribeFuture<'a> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move { Ok(with_suffix(output_prefix, "srt")) })
+        }
+    }
+    
+    #[test]
+    fn test_parse_command_template() {
+        let transcriber = CommandTranscriber::parse(
+            "whisper-cli -m 'models/ggml base.bin' -f {audio} -osrt -of {output}"
+        ).unwrap();
+        assert_eq!(transcriber.args[2], "models/ggml base.bin");
+        
+        let args = transcriber.command_args(Path::new("/out/a clip.transcribe.wav"), Path::new("/out/a clip.transcribe"));
+        assert_eq!(args.len(), 8);
+        assert_eq!(args[4], "/out/a clip.transcribe.wav");
+        assert_eq!(args[7], "/out/a clip.transcribe");
+        
+        assert!(CommandTranscriber::parse("whisper-cli -f {audio}").is_err());
+        assert!(CommandTranscriber::parse("   ").is_err());
+        assert!(CommandTranscriber::parse("whisper-cli \"{audio} {output}").is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_command_transcriber_runs_stand_in_script() {
+        let temp_dir = TempDir::new().unwrap();
+        let script = temp_dir.path().join("fake-whisper.sh");
+        std::fs::write(&script, "printf '1\\n00:00:00,000 --> 00:00:01,500\\nHello\\n' > \"$2.srt\"\n").unwrap();
+        
+        let template = format!("sh '{}' {{audio}} {{output}}", script.display());
+        let transcriber = CommandTranscriber::parse(&template).unwrap();
+        let prefix = temp_dir.path().join("clip.transcribe");
+        let output = transcriber
+            .transcribe(Path::new("clip.wav"), &prefix, Duration::from_secs(10))
+            .await
+            .unwrap();
+        
+        assert_eq!(output, temp_dir.path().join("clip.transcribe.srt"));
+        assert!(std::fs::read_to_string(&output).unwrap().contains("Hello"));
+        
+        // Ошибка команды и отсутствие результата
+        let failing = CommandTranscriber::parse("sh -c 'echo no model >&2; exit 3' {audio} {output}").unwrap();
+        let error = failing.transcribe(Path::new("a.wav"), &prefix, Duration::from_secs(10)).await.unwrap_err();
+        assert!(matches!(error, TranscribeError::CommandFailed { code: 3, ref reason } if reason == "no model"));
+        
+        let silent = CommandTranscriber::parse("true {audio} {output}").unwrap();
+        let prefix = temp_dir.path().join("other.transcribe");
+        assert!(matches!(
+            silent.transcribe(Path::new("a.wav"), &prefix, Duration::from_secs(10)).await,
+            Err(TranscribeError::NoOutput { .. })
+        ));
+    }
+    
+    #[test]
+    fn test_build_srt_from_words() {
+        let json = r#"{"words": [
+            {"word": " Hello", "start": 0.0, "end": 0.4},
+            {"word": "world", "start": 0.45, "end": 0.9},
+            {"text": "", "start": 0.9, "end": 0.9},
+            {"text": "again", "start": 2.5, "end": 3.0}
+        ]}"#;
+        let words = parse_word_timings(json).unwrap();
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[0].word, "Hello");
+        
+        // Пауза больше CAPTION_MAX_GAP разделяет субтитры
+        assert_eq!(
+            build_srt_from_words(&words),
+            "1\n00:00:00,000 --> 00:00:00,900\nHello world\n\n2\n00:00:02,500 --> 00:00:03,000\nagain\n\n"
+        );
+        
+        // Длинная непрерывная речь делится по количеству слов
+        let words: Vec<WordTiming> = (0..10)
+            .map(|i| WordTiming { word: format!("w{}", i), start: i as f64 * 0.3, end: i as f64 * 0.3 + 0.25 })
+            .collect();
+        let srt = build_srt_from_words(&words);
+        assert!(srt.contains("w0 w1 w2 w3 w4 w5 w6\n"));
+        assert!(srt.contains("2\n00:00:02,100 --> 00:00:02,950\nw7 w8 w9\n"));
+        
+        assert_eq!(format_srt_time(3725.5), "01:02:05,500");
+        assert!(parse_word_timings("{\"text\": 1}").is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_transcript_cache_and_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut task = FileTask::new(PathBuf::from("talk.mp4"), vec![temp_dir.path().join("talk-short-part02.mp4")]);
+        task.segment = Some(TimeRange::new(60.0, 90.0));
+        let media = MediaInfo::synthetic(1920, 1080, 600.0, true);
+        
+        // Готовый кэш переиспользуется без запуска распознавания, если он распознан из того же звука
+        let cache = transcript_cache_path(&task);
+        assert_eq!(cache, temp_dir.path().join("talk-short-part02.srt"));
+        std::fs::write(&cache, "1\n00:00:00,000 --> 00:00:01,000\nHi\n\n").unwrap();
+        assert_eq!(cached_transcript(&task), None);
+        std::fs::write(transcript_key_path(&task), transcript_key(&task)).unwrap();
+        let transcriber = FakeTranscriber { calls: AtomicUsize::new(0) };
+        assert_eq!(transcribe_task(&task, &media, &transcriber).await.unwrap(), cache);
+        assert_eq!(transcriber.calls.load(Ordering::SeqCst), 0);
+        
+        // Другой фрагмент, дорожка или исходник под тем же именем выхода - кэш устарел
+        let stale = [
+            FileTask { segment: Some(TimeRange::new(60.0, 120.0)), ..task.clone() },
+            FileTask { audio_track: Some(1), ..task.clone() },
+            FileTask { input: PathBuf::from("game.mp4"), ..task.clone() },
+        ];
+        assert!(stale.iter().all(|task| cached_transcript(task).is_none()));
+        
+        // JSON со словами превращается в SRT, тайминги слов сохраняются рядом
+        std::fs::remove_file(&cache).unwrap();
+        let output = temp_dir.path().join("talk-short-part02.transcribe.json");
+        std::fs::write(&output, r#"[{"word": "Hi", "start": 0.1, "end": 0.5}]"#).unwrap();
+        store_transcript(&output, &cache).unwrap();
+        assert_eq!(std::fs::read_to_string(&cache).unwrap(), "1\n00:00:00,100 --> 00:00:00,500\nHi\n\n");
+        assert!(temp_dir.path().join("talk-short-part02.words.json").is_file());
+        assert!(!output.exists());
+        
+        // Звук для распознавания - тот же фрагмент и та же дорожка, моно 16 кГц
+        task.audio_track = Some(1);
+        let args = build_transcribe_audio_args(&task, Path::new("a.wav"));
+        assert_eq!(&args[3..9], &["-ss", "60.000", "-t", "30.000", "-i", "talk.mp4"]);
+        assert_eq!(&args[9..], &["-map", "0:a:1", "-ac", "1", "-ar", "16000", "-c:a", "pcm_s16le", "a.wav"]);
+    }
+}
@@ -1,6 +1,8 @@
 use std::path::{Path, PathBuf};
 use crate::error::{FileSystemError, FileSystemResult};
-use crate::config::DEFAULT_INPUT_EXTENSIONS;
+use crate::config::{CropRect, OutputVariant, SourceCrop, DEFAULT_INPUT_EXTENSIONS, MUSIC_EXTENSIONS, SPLIT_TAGS, SUBTITLE_EXTENSIONS};
+use crate::analysis::{LoudnormStats, ReframeTrack};
+use crate::probe::MediaInfo;
 use tracing::debug;
 
 /// Рекурсивно ищет все файлы с поддерживаемыми расширениями в директории
@@ -40,6 +42,60 @@ fn find_files_recursive(dir: &Path, files: &mut Vec<PathBuf>) -> FileSystemResul
     Ok(())
 }
 
+/// Собирает музыкальные треки для `--music`: файл или все треки директории
+///
+/// Директория не обходится рекурсивно; треки сортируются по имени, чтобы
+/// распределение по клипам было предсказуемым.
+pub fn find_music_files(path: &Path) -> FileSystemResult<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    
+    let entries = std::fs::read_dir(path)
+        .map_err(|_| FileSystemError::cannot_read_dir(path.to_path_buf()))?;
+    
+    let mut tracks: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|track| {
+            track.is_file()
+                && track
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| MUSIC_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    tracks.sort();
+    
+    Ok(tracks)
+}
+
+/// Раздает музыкальные треки клипам по кругу в порядке плана
+pub fn assign_music(tasks: &mut [FileTask], tracks: &[PathBuf]) {
+    if tracks.is_empty() {
+        return;
+    }
+    
+    for (index, task) in tasks.iter_mut().enumerate() {
+        task.music = Some(tracks[index % tracks.len()].clone());
+    }
+}
+
+/// Ищет субтитры рядом с видеофайлом: `clip.srt` или `clip.vtt` для `clip.mp4`
+///
+/// Если лежат оба файла, берется SRT.
+pub fn find_subtitle_file(video: &Path) -> Option<PathBuf> {
+    SUBTITLE_EXTENSIONS
+        .iter()
+        .flat_map(|ext| [ext.to_string(), ext.to_uppercase()])
+        .map(|ext| video.with_extension(ext))
+        .find(|path| path.is_file())
+}
+
+/// Ищет тайминги слов рядом с видеофайлом: `clip.words.json` для `clip.mp4`
+pub fn find_word_timings_file(video: &Path) -> Option<PathBuf> {
+    Some(video.with_extension("words.json")).filter(|path| path.is_file())
+}
+
 /// Проверяет, является ли файл поддерживаемым видеофайлом
 pub fn is_supported_video_file(path: &Path) -> bool {
     if let Some(extension) = path.extension() {
@@ -52,7 +108,10 @@ pub fn is_supported_video_file(path: &Path) -> bool {
 }
 
 /// Генерирует путь к выходному файлу на основе входного файла
-pub fn generate_output_path(input_path: &Path, output_dir: &Path) -> PathBuf {
+///
+/// Каждый тег из `tags` добавляется к имени через дефис после основного суффикса,
+/// например `video-short-9x16.mp4`.
+pub fn generate_output_path(input_path: &Path, output_dir: &Path, tags: &[&str]) -> PathBuf {
     let input_filename = input_path.file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("unknown");
@@ -61,9 +120,12 @@ pub fn generate_output_path(input_path: &Path, output_dir: &Path) -> PathBuf {
         .and_then(|s| s.to_str())
         .unwrap_or("mp4");
     
-    let output_filename = format!("{}{}.{}", 
+    let tags_suffix: String = tags.iter().map(|tag| format!("-{}", tag)).collect();
+    
+    let output_filename = format!("{}{}{}.{}", 
                                  input_filename, 
                                  crate::config::OUTPUT_SUFFIX, 
+                                 tags_suffix,
                                  input_extension);
     
     output_dir.join(output_filename)
@@ -178,36 +240,209 @@ pub fn format_file_size(size: u64) -> String {
     }
 }
 
+/// Разбирает время вида `90`, `90.5`, `60s`, `1m30s`, `1h2m`, `01:30` или `00:01:30.250`
+///
+/// Возвращает количество секунд или `None`, если формат не распознан.
+pub fn parse_time_spec(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    
+    let seconds = if value.contains(':') {
+        // Формат [HH:]MM:SS[.mmm]
+        let parts: Vec<&str> = value.split(':').collect();
+        if parts.len() > 3 {
+            return None;
+        }
+        
+        let mut seconds = 0.0;
+        for (index, part) in parts.iter().enumerate() {
+            let number: f64 = part.trim().parse().ok()?;
+            let is_last = index == parts.len() - 1;
+            if number < 0.0 || (!is_last && number.fract() != 0.0) || (index > 0 && number >= 60.0) {
+                return None;
+            }
+            seconds = seconds * 60.0 + number;
+        }
+        seconds
+    } else if value.ends_with(['h', 'm', 's']) {
+        // Формат с единицами: 1h2m3.5s
+        let mut seconds = 0.0;
+        let mut number = String::new();
+        for c in value.chars() {
+            match c {
+                '0'..='9' | '.' => number.push(c),
+                'h' | 'm' | 's' => {
+                    let amount: f64 = number.parse().ok()?;
+                    number.clear();
+                    seconds += amount * match c {
+                        'h' => 3600.0,
+                        'm' => 60.0,
+                        _ => 1.0,
+                    };
+                }
+                _ => return None,
+            }
+        }
+        seconds
+    } else {
+        value.parse().ok()?
+    };
+    
+    if seconds.is_finite() && seconds >= 0.0 {
+        Some(seconds)
+    } else {
+        None
+    }
+}
+
+/// Форматирует секунды как метку времени для FFmpeg (`-ss`, `-t`)
+pub fn format_seconds(seconds: f64) -> String {
+    format!("{:.3}", seconds)
+}
+
+/// Временной диапазон внутри исходного файла (в секундах)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl TimeRange {
+    /// Создает новый диапазон
+    pub fn new(start: f64, end: f64) -> Self {
+        Self { start, end }
+    }
+    
+    /// Длительность диапазона в секундах
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+impl std::fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", format_seconds(self.start), format_seconds(self.end))
+    }
+}
+
 /// Структура для представления задачи обработки файла
 #[derive(Debug, Clone)]
 pub struct FileTask {
     pub input: PathBuf,
-    pub output: PathBuf,
+    /// Выходные файлы - по одному на каждый формат, в порядке `RenderSettings::variants`
+    pub outputs: Vec<PathBuf>,
+    /// Результат ffprobe для входного файла (заполняется перед построением команды)
+    pub media_info: Option<MediaInfo>,
+    /// Вырезаемый фрагмент исходника; `None` - файл целиком
+    pub segment: Option<TimeRange>,
+    /// Метка части (`part01`, ...), если исходник нарезан на несколько шортсов
+    pub part: Option<String>,
+    /// Заголовок фрагмента из списка нарезки
+    pub title: Option<String>,
+    /// Результат первого прохода `loudnorm` (заполняется перед построением команды)
+    pub loudnorm: Option<LoudnormStats>,
+    /// Музыкальная подложка клипа (`--music`)
+    pub music: Option<PathBuf>,
+    /// Номер звуковой дорожки исходника (среди звуковых); `None` - первая
+    pub audio_track: Option<usize>,
+    /// Субтитры рядом с исходником (`clip.srt`, `clip.vtt`), вшиваемые в кадр
+    pub subtitles: Option<PathBuf>,
+    /// Субтитры из распознавания речи, размеченные по времени клипа (`--transcribe`)
+    pub transcript: Option<PathBuf>,
+    /// Тайминги слов рядом с исходником (`clip.words.json`), по времени исходника
+    pub word_timings: Option<PathBuf>,
+    /// ASS-файлы пословных субтитров (`--karaoke`), по одному на выход
+    pub karaoke: Vec<PathBuf>,
+    /// Преобладающий цвет кадра-пробы для `--background dominant` (заполняется перед построением команды)
+    pub background_color: Option<u32>,
+    /// Обрезка черных полос: из списка нарезки или найденная `cropdetect` перед построением команды
+    pub crop: Option<SourceCrop>,
+    /// Траектория окна кадра для `--reframe` (заполняется перед построением команды)
+    pub reframe: Option<ReframeTrack>,
+    /// Второй исходник разделенного экрана (`--split-screen`) - нижняя часть кадра
+    pub split_input: Option<PathBuf>,
+    /// Результат ffprobe для второго исходника (заполняется при планировании)
+    pub split_media_info: Option<MediaInfo>,
+    /// Черные полосы второго исходника, найденные `cropdetect` перед построением команды
+    pub split_crop: Option<CropRect>,
 }
 
 impl FileTask {
     /// Создает новую задачу обработки файла
-    pub fn new(input: PathBuf, output: PathBuf) -> Self {
-        Self { input, output }
+    pub fn new(input: PathBuf, outputs: Vec<PathBuf>) -> Self {
+        Self {
+            input,
+            outputs,
+            media_info: None,
+            segment: None,
+            part: None,
+            title: None,
+            loudnorm: None,
+            music: None,
+            audio_track: None,
+            subtitles: None,
+            transcript: None,
+            word_timings: None,
+            karaoke: Vec::new(),
+            background_color: None,
+            crop: None,
+            reframe: None,
+            split_input: None,
+            split_media_info: None,
+            split_crop: None,
+        }
+    }
+    
+    /// Прямоугольник исходника без черных полос; `None` - кадр целиком
+    pub fn crop_rect(&self) -> Option<CropRect> {
+        self.crop.and_then(|crop| crop.rect())
     }
     
     /// Валидирует задачу перед обработкой
     pub fn validate(&self) -> FileSystemResult<()> {
         validate_input_file(&self.input)?;
-        
-        // Проверяем директорию назначения
-        if let Some(parent) = self.output.parent() {
-            validate_output_directory(parent)?;
+        if let Some(split_input) = &self.split_input {
+            validate_input_file(split_input)?;
         }
         
-        // Проверяем, что выходной файл не совпадает с входным
-        if self.input == self.output {
-            return Err(FileSystemError::cannot_access(self.output.clone()));
+        for output in &self.outputs {
+            // Проверяем директорию назначения
+            if let Some(parent) = output.parent() {
+                validate_output_directory(parent)?;
+            }
+            
+            // Проверяем, что выходной файл не совпадает с входными
+            if self.input == *output || self.split_input.as_ref() == Some(output) {
+                return Err(FileSystemError::cannot_access(output.clone()));
+            }
         }
         
         Ok(())
     }
     
+    /// Путь, от которого строятся имена выходов
+    ///
+    /// Для разделенного экрана метка `top` убирается из имени: `clip.top.mp4` -> `clip.mp4`.
+    pub fn output_base(&self) -> PathBuf {
+        let stem = self.input.file_stem().map(Path::new);
+        let base = stem
+            .filter(|_| self.split_input.is_some())
+            .filter(|stem| stem.extension().is_some_and(|tag| tag.eq_ignore_ascii_case(SPLIT_TAGS.0)))
+            .and_then(|stem| stem.file_stem());
+        
+        match (base, self.input.extension()) {
+            (Some(base), Some(ext)) => self.input.with_file_name(Path::new(base).with_extension(ext)),
+            _ => self.input.clone(),
+        }
+    }
+    
+    /// Возвращает основной (первый) выходной файл
+    pub fn primary_output(&self) -> &Path {
+        self.outputs.first().map(PathBuf::as_path).unwrap_or(Path::new(""))
+    }
+    
     /// Возвращает имя входного файла для отображения
     pub fn input_filename(&self) -> String {
         self.input
@@ -217,9 +452,17 @@ impl FileTask {
             .to_string()
     }
     
+    /// Возвращает имя задачи для логов: имя входного файла и метка части
+    pub fn display_name(&self) -> String {
+        match &self.part {
+            Some(part) => format!("{} [{}]", self.input_filename(), part),
+            None => self.input_filename(),
+        }
+    }
+    
     /// Возвращает имя выходного файла для отображения
     pub fn output_filename(&self) -> String {
-        self.output
+        self.primary_output()
             .file_name()
             .and_then(|s| s.to_str())
             .unwrap_or("unknown")
@@ -228,12 +471,154 @@ impl FileTask {
 }
 
 /// Создает список задач обработки на основе найденных файлов
-pub fn create_file_tasks(input_files: Vec<PathBuf>, output_dir: &Path) -> Vec<FileTask> {
+///
+/// Если форматов несколько, каждый выход получает метку формата в имени.
+/// Субтитры и тайминги слов, лежащие рядом с исходником, привязываются к задаче.
+pub fn create_file_tasks(input_files: Vec<PathBuf>, output_dir: &Path, variants: &[OutputVariant]) -> Vec<FileTask> {
     input_files
         .into_iter()
         .map(|input_path| {
-            let output_path = generate_output_path(&input_path, output_dir);
-            FileTask::new(input_path, output_path)
+            let outputs = task_output_paths(&input_path, output_dir, variants, &[]);
+            FileTask {
+                subtitles: find_subtitle_file(&input_path),
+                word_timings: find_word_timings_file(&input_path),
+                ..FileTask::new(input_path, outputs)
+            }
+        })
+        .collect()
+}
+
+/// Собирает пары разделенного экрана по именам файлов: `clip.top.mp4` и `clip.bottom.mp4`
+///
+/// Пара ищется в той же директории, метки сравниваются без учета регистра.
+/// Возвращает пары (верх, низ) и файлы, для которых пары не нашлось.
+pub fn pair_split_inputs(files: &[PathBuf]) -> (Vec<(PathBuf, PathBuf)>, Vec<PathBuf>) {
+    let (top_tag, bottom_tag) = SPLIT_TAGS;
+    
+    // Имя без метки и метка: `clip.top.mp4` -> (`clip`, `top`)
+    let tagged = |path: &Path| {
+        let stem = Path::new(path.file_stem()?);
+        let tag = stem.extension()?.to_str()?.to_ascii_lowercase();
+        Some((stem.file_stem()?.to_os_string(), tag))
+    };
+    let partner = |path: &Path, tag: &str| {
+        let (base, _) = tagged(path)?;
+        files.iter().find(|other| {
+            other.parent() == path.parent() && tagged(other).is_some_and(|(other_base, other_tag)| other_base == base && other_tag == tag)
+        })
+    };
+    
+    let mut pairs = Vec::new();
+    let mut unpaired = Vec::new();
+    for file in files {
+        match tagged(file) {
+            Some((_, tag)) if tag == top_tag => match partner(file, bottom_tag) {
+                Some(bottom) => pairs.push((file.clone(), bottom.clone())),
+                None => unpaired.push(file.clone()),
+            },
+            Some((_, tag)) if tag == bottom_tag && partner(file, top_tag).is_some() => {}
+            _ => unpaired.push(file.clone()),
+        }
+    }
+    
+    (pairs, unpaired)
+}
+
+/// Создает задачи разделенного экрана: верхний исходник - основной вход задачи, нижний - второй
+///
+/// Имена выходов, субтитры и тайминги слов берутся по имени без метки `top`:
+/// пара `clip.top.mp4` + `clip.bottom.mp4` дает `clip-short.mp4` и подхватывает `clip.srt`.
+pub fn create_split_tasks(pairs: Vec<(PathBuf, PathBuf)>, output_dir: &Path, variants: &[OutputVariant]) -> Vec<FileTask> {
+    pairs
+        .into_iter()
+        .map(|(top, bottom)| {
+            let task = FileTask {
+                split_input: Some(bottom),
+                ..FileTask::new(top, Vec::new())
+            };
+            let base = task.output_base();
+            
+            FileTask {
+                outputs: task_output_paths(&base, output_dir, variants, &[]),
+                subtitles: find_subtitle_file(&base),
+                word_timings: find_word_timings_file(&base),
+                ..task
+            }
+        })
+        .collect()
+}
+
+/// Разворачивает задачу в отдельные задачи для каждого фрагмента исходника
+///
+/// Части получают метки `part01`, `part02`, ... в имени выходных файлов.
+pub fn create_segment_tasks(
+    task: &FileTask,
+    segments: &[TimeRange],
+    output_dir: &Path,
+    variants: &[OutputVariant],
+) -> Vec<FileTask> {
+    let width = segments.len().to_string().len().max(2);
+    
+    segments
+        .iter()
+        .enumerate()
+        .map(|(index, segment)| {
+            let part = format!("part{:0width$}", index + 1, width = width);
+            let outputs = task_output_paths(&task.output_base(), output_dir, variants, &[&part]);
+            
+            FileTask {
+                outputs,
+                segment: Some(*segment),
+                part: Some(part),
+                ..task.clone()
+            }
+        })
+        .collect()
+}
+
+/// Разворачивает задачу в отдельные задачи для каждой языковой дорожки
+///
+/// `tracks` - пары (номер звуковой дорожки, тег языка). Тег языка добавляется
+/// к метке части и к имени выходных файлов: `talk-short-part01-eng.mp4`.
+pub fn create_language_tasks(
+    task: &FileTask,
+    tracks: &[(usize, String)],
+    output_dir: &Path,
+    variants: &[OutputVariant],
+) -> Vec<FileTask> {
+    tracks
+        .iter()
+        .map(|(audio_track, language)| {
+            let mut tags: Vec<&str> = task.part.as_deref().into_iter().collect();
+            tags.push(language);
+            
+            FileTask {
+                outputs: task_output_paths(&task.output_base(), output_dir, variants, &tags),
+                part: Some(tags.join("-")),
+                audio_track: Some(*audio_track),
+                ..task.clone()
+            }
+        })
+        .collect()
+}
+
+/// Строит пути выходных файлов задачи для всех форматов
+///
+/// `tags` (метка части, язык) идут в имени перед меткой формата.
+fn task_output_paths(
+    input_path: &Path,
+    output_dir: &Path,
+    variants: &[OutputVariant],
+    tags: &[&str],
+) -> Vec<PathBuf> {
+    variants
+        .iter()
+        .map(|variant| {
+            let mut tags: Vec<&str> = tags.to_vec();
+            if variants.len() > 1 {
+                tags.push(variant.label.as_str());
+            }
+            generate_output_path(input_path, output_dir, &tags)
         })
         .collect()
 }
@@ -259,8 +644,129 @@ mod tests {
         let input = PathBuf::from("/input/video.mp4");
         let output_dir = PathBuf::from("/output");
         
-        let result = generate_output_path(&input, &output_dir);
+        let result = generate_output_path(&input, &output_dir, &[]);
         assert_eq!(result, PathBuf::from("/output/video-short.mp4"));
+        
+        let result = generate_output_path(&input, &output_dir, &["4x5"]);
+        assert_eq!(result, PathBuf::from("/output/video-short-4x5.mp4"));
+    }
+    
+    #[test]
+    fn test_create_file_tasks_with_variants() {
+        let output_dir = PathBuf::from("/output");
+        let files = vec![PathBuf::from("/input/a.mp4")];
+        
+        let single = vec![OutputVariant { label: "720x1280".to_string(), resolution: crate::config::DEFAULT_RESOLUTION }];
+        let tasks = create_file_tasks(files.clone(), &output_dir, &single);
+        assert_eq!(tasks[0].outputs, vec![PathBuf::from("/output/a-short.mp4")]);
+        
+        let multiple = vec![
+            OutputVariant { label: "9x16".to_string(), resolution: crate::config::Resolution::new(1080, 1920) },
+            OutputVariant { label: "1x1".to_string(), resolution: crate::config::Resolution::new(1080, 1080) },
+        ];
+        let tasks = create_file_tasks(files, &output_dir, &multiple);
+        assert_eq!(tasks[0].outputs, vec![
+            PathBuf::from("/output/a-short-9x16.mp4"),
+            PathBuf::from("/output/a-short-1x1.mp4"),
+        ]);
+        assert_eq!(tasks[0].primary_output(), Path::new("/output/a-short-9x16.mp4"));
+    }
+    
+    #[test]
+    fn test_create_segment_tasks() {
+        let output_dir = PathBuf::from("/output");
+        let variants = vec![OutputVariant { label: "720x1280".to_string(), resolution: crate::config::DEFAULT_RESOLUTION }];
+        let task = create_file_tasks(vec![PathBuf::from("/input/talk.mp4")], &output_dir, &variants).remove(0);
+        
+        let segments = vec![TimeRange::new(0.0, 60.0), TimeRange::new(60.0, 120.0), TimeRange::new(120.0, 150.0)];
+        let parts = create_segment_tasks(&task, &segments, &output_dir, &variants);
+        
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].outputs, vec![PathBuf::from("/output/talk-short-part01.mp4")]);
+        assert_eq!(parts[2].outputs, vec![PathBuf::from("/output/talk-short-part03.mp4")]);
+        assert_eq!(parts[1].segment, Some(TimeRange::new(60.0, 120.0)));
+        assert_eq!(parts[1].display_name(), "talk.mp4 [part02]");
+        assert_eq!(parts[2].segment.map(|s| s.duration()), Some(30.0));
+        
+        let many: Vec<TimeRange> = (0..120).map(|i| TimeRange::new(i as f64, i as f64 + 1.0)).collect();
+        let parts = create_segment_tasks(&task, &many, &output_dir, &variants);
+        assert_eq!(parts[0].part.as_deref(), Some("part001"));
+    }
+    
+    #[test]
+    fn test_create_language_tasks() {
+        let output_dir = PathBuf::from("/output");
+        let variants = vec![
+            OutputVariant { label: "9x16".to_string(), resolution: crate::config::Resolution::new(1080, 1920) },
+            OutputVariant { label: "1x1".to_string(), resolution: crate::config::Resolution::new(1080, 1080) },
+        ];
+        let task = create_file_tasks(vec![PathBuf::from("/input/talk.mp4")], &output_dir, &variants).remove(0);
+        let tracks = vec![(0, "eng".to_string()), (2, "rus".to_string())];
+        
+        let tasks = create_language_tasks(&task, &tracks, &output_dir, &variants);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[1].audio_track, Some(2));
+        assert_eq!(
+            tasks[1].outputs,
+            vec![PathBuf::from("/output/talk-short-rus-9x16.mp4"), PathBuf::from("/output/talk-short-rus-1x1.mp4")]
+        );
+        assert_eq!(tasks[0].display_name(), "talk.mp4 [eng]");
+        
+        // Язык добавляется после метки части
+        let part = create_segment_tasks(&task, &[TimeRange::new(0.0, 30.0), TimeRange::new(30.0, 60.0)], &output_dir, &variants)
+            .remove(1);
+        let tasks = create_language_tasks(&part, &tracks, &output_dir, &variants);
+        assert_eq!(tasks[0].outputs[0], PathBuf::from("/output/talk-short-part02-eng-9x16.mp4"));
+        assert_eq!(tasks[0].part.as_deref(), Some("part02-eng"));
+        assert_eq!(tasks[0].segment, Some(TimeRange::new(30.0, 60.0)));
+    }
+    
+    #[test]
+    fn test_split_screen_pairs() {
+        let files: Vec<PathBuf> = [
+            "/in/stream.bottom.mp4",
+            "/in/stream.TOP.mp4",
+            "/in/day1/stream.top.mp4",
+            "/in/solo.top.mp4",
+            "/in/talk.mp4",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        
+        let (pairs, unpaired) = pair_split_inputs(&files);
+        assert_eq!(pairs, vec![(files[1].clone(), files[0].clone())]);
+        assert_eq!(unpaired, vec![files[2].clone(), files[3].clone(), files[4].clone()]);
+        
+        // Выходы и части называются по имени без метки
+        let output_dir = PathBuf::from("/output");
+        let variants = vec![OutputVariant { label: "720x1280".to_string(), resolution: crate::config::DEFAULT_RESOLUTION }];
+        let task = create_split_tasks(pairs, &output_dir, &variants).remove(0);
+        assert_eq!(task.input, files[1]);
+        assert_eq!(task.split_input.as_ref(), Some(&files[0]));
+        assert_eq!(task.outputs, vec![PathBuf::from("/output/stream-short.mp4")]);
+        let parts = create_segment_tasks(&task, &[TimeRange::new(0.0, 30.0), TimeRange::new(30.0, 60.0)], &output_dir, &variants);
+        assert_eq!(parts[1].outputs, vec![PathBuf::from("/output/stream-short-part02.mp4")]);
+        
+        // Пары из списка могут называться как угодно
+        let task = create_split_tasks(vec![(PathBuf::from("/in/cam.mp4"), PathBuf::from("/in/game.mp4"))], &output_dir, &variants);
+        assert_eq!(task[0].outputs, vec![PathBuf::from("/output/cam-short.mp4")]);
+    }
+    
+    #[test]
+    fn test_parse_time_spec() {
+        assert_eq!(parse_time_spec("90"), Some(90.0));
+        assert_eq!(parse_time_spec("12.5"), Some(12.5));
+        assert_eq!(parse_time_spec("60s"), Some(60.0));
+        assert_eq!(parse_time_spec("1m30s"), Some(90.0));
+        assert_eq!(parse_time_spec("1h2m"), Some(3720.0));
+        assert_eq!(parse_time_spec("01:30"), Some(90.0));
+        assert_eq!(parse_time_spec("00:01:30.250"), Some(90.25));
+        assert_eq!(parse_time_spec(""), None);
+        assert_eq!(parse_time_spec("abc"), None);
+        assert_eq!(parse_time_spec("1:75"), None);
+        assert_eq!(parse_time_spec("-5"), None);
+        assert_eq!(parse_time_spec("5x"), None);
     }
     
     #[test]
@@ -303,4 +809,53 @@ mod tests {
             assert!(is_supported_video_file(path));
         }
     }
+    
+    #[test]
+    fn test_find_and_assign_music() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["b-track.MP3", "a-track.m4a", "cover.jpg", "c-track.wav"] {
+            File::create(temp_dir.path().join(name)).unwrap();
+        }
+        
+        let tracks = find_music_files(temp_dir.path()).unwrap();
+        let names: Vec<_> = tracks.iter().map(|t| t.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, vec!["a-track.m4a", "b-track.MP3", "c-track.wav"]);
+        
+        let single = temp_dir.path().join("a-track.m4a");
+        assert_eq!(find_music_files(&single).unwrap(), vec![single.clone()]);
+        
+        // Треки раздаются по кругу
+        let mut tasks: Vec<FileTask> = (0..4)
+            .map(|i| FileTask::new(PathBuf::from(format!("{}.mp4", i)), vec![PathBuf::from("out.mp4")]))
+            .collect();
+        assign_music(&mut tasks, &tracks[..2]);
+        let assigned: Vec<_> = tasks.iter().map(|t| t.music.clone().unwrap()).collect();
+        assert_eq!(assigned, vec![tracks[0].clone(), tracks[1].clone(), tracks[0].clone(), tracks[1].clone()]);
+    }
+    
+    #[test]
+    fn test_find_subtitle_file() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["talk.mp4", "talk.vtt", "talk.srt", "interview.mp4", "interview.VTT", "broll.mp4"] {
+            File::create(temp_dir.path().join(name)).unwrap();
+        }
+        
+        // SRT важнее VTT, регистр расширения не важен
+        let talk = temp_dir.path().join("talk.mp4");
+        assert_eq!(find_subtitle_file(&talk), Some(temp_dir.path().join("talk.srt")));
+        let interview = temp_dir.path().join("interview.mp4");
+        assert_eq!(find_subtitle_file(&interview), Some(temp_dir.path().join("interview.VTT")));
+        assert_eq!(find_subtitle_file(&temp_dir.path().join("broll.mp4")), None);
+        
+        File::create(temp_dir.path().join("talk.words.json")).unwrap();
+        assert_eq!(find_word_timings_file(&talk), Some(temp_dir.path().join("talk.words.json")));
+        assert_eq!(find_word_timings_file(&interview), None);
+        
+        // Задачи наследуют субтитры исходника, включая части
+        let variants = vec![OutputVariant { label: "720x1280".to_string(), resolution: crate::config::DEFAULT_RESOLUTION }];
+        let task = create_file_tasks(vec![talk], temp_dir.path(), &variants).remove(0);
+        assert_eq!(task.subtitles, Some(temp_dir.path().join("talk.srt")));
+        let parts = create_segment_tasks(&task, &[TimeRange::new(0.0, 10.0)], temp_dir.path(), &variants);
+        assert_eq!(parts[0].subtitles, task.subtitles);
+    }
 }
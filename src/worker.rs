@@ -2,10 +2,18 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{Semaphore, mpsc};
 use tokio::task::JoinHandle;
-use tracing::{info, error, debug};
+use tracing::{info, warn, error, debug};
 
+use crate::analysis::{detect_crop, detect_dominant_color, measure_loudnorm, parse_loudnorm_stats, track_motion, LoudnessResult};
+use crate::captions::{remove_karaoke_files, write_karaoke_files};
+use crate::config::{BackgroundMode, Resolution, SourceCrop};
 use crate::utils::FileTask;
-use crate::ffmpeg::{FfmpegCommand, execute_ffmpeg_command, FfmpegExecutionResult};
+use crate::ffmpeg::{
+    FfmpegCommand, execute_ffmpeg_command, estimate_output_size, needs_background, voice_source, FfmpegExecutionResult,
+    RenderSettings,
+};
+use crate::probe::{probe_media, MediaInfo};
+use crate::transcribe::transcribe_task;
 use crate::logger::{FileProcessingLogger, ProcessingSummary};
 use crate::error::Result;
 
@@ -14,12 +22,17 @@ use crate::error::Result;
 pub enum TaskResult {
     Success {
         input: std::path::PathBuf,
-        output: std::path::PathBuf,
+        /// Метка части, если исходник нарезан на несколько шортсов
+        part: Option<String>,
+        outputs: Vec<std::path::PathBuf>,
         duration: Duration,
         ffmpeg_result: FfmpegExecutionResult,
+        /// Громкость до и после нормализации (`--loudnorm`)
+        loudness: Option<LoudnessResult>,
     },
     Failure {
         input: std::path::PathBuf,
+        part: Option<String>,
         error: String,
         duration: Duration,
     },
@@ -52,16 +65,18 @@ impl TaskResult {
 pub struct WorkerPool {
     semaphore: Arc<Semaphore>,
     max_workers: usize,
+    settings: Arc<RenderSettings>,
 }
 
 impl WorkerPool {
     /// Создает новый worker pool с указанным количеством воркеров
-    pub fn new(max_workers: usize) -> Self {
+    pub fn new(max_workers: usize, settings: RenderSettings) -> Self {
         info!("Creating worker pool with {} workers", max_workers);
         
         Self {
             semaphore: Arc::new(Semaphore::new(max_workers)),
             max_workers,
+            settings: Arc::new(settings),
         }
     }
     
@@ -84,6 +99,7 @@ impl WorkerPool {
         
         for (index, task) in tasks.into_iter().enumerate() {
             let semaphore = Arc::clone(&self.semaphore);
+            let settings = Arc::clone(&self.settings);
             let tx = tx.clone();
             
             let handle = tokio::spawn(async move {
@@ -91,9 +107,9 @@ impl WorkerPool {
                 let _permit = semaphore.acquire().await.unwrap();
                 
                 debug!("Starting task {}/{} for: {}", 
-                       index + 1, total_tasks, task.input_filename());
+                       index + 1, total_tasks, task.display_name());
                 
-                let result = process_single_file(task).await;
+                let result = process_single_file(task, &settings).await;
                 
                 if let Err(e) = tx.send(result) {
                     error!("Failed to send task result: {}", e);
@@ -177,6 +193,11 @@ impl ProcessingResults {
         }
     }
     
+    /// Добавляет неудачи, случившиеся до запуска пула (например, при анализе входных файлов)
+    pub fn add_failures(&mut self, failures: Vec<TaskResult>) {
+        self.failed.extend(failures);
+    }
+    
     /// Возвращает общее количество задач
     pub fn total_count(&self) -> usize {
         self.successful.len() + self.failed.len()
@@ -197,14 +218,14 @@ impl ProcessingResults {
         let mut summary = ProcessingSummary::new();
         
         for result in &self.successful {
-            if let TaskResult::Success { input, output, duration, .. } = result {
-                summary.add_success(input.clone(), output.clone(), *duration);
+            if let TaskResult::Success { input, part, outputs, duration, loudness, .. } = result {
+                summary.add_success(input.clone(), part.clone(), outputs.clone(), *duration, *loudness);
             }
         }
         
         for result in &self.failed {
-            if let TaskResult::Failure { input, error, .. } = result {
-                summary.add_failure(input.clone(), error.clone());
+            if let TaskResult::Failure { input, part, error, .. } = result {
+                summary.add_failure(input.clone(), part.clone(), error.clone());
             }
         }
         
@@ -213,10 +234,144 @@ impl ProcessingResults {
     }
 }
 
+/// Предварительные проходы перед рендером: заполняют задачу результатами анализа
+///
+/// Ошибка - готовый текст для отчета, задача с ней не рендерится.
+/// Необязательные улучшения (поиск черных полос) при сбое только пишут предупреждение.
+async fn prepare_task(
+    task: &mut FileTask,
+    media_info: &MediaInfo,
+    settings: &RenderSettings,
+    filename: &str,
+) -> std::result::Result<(), String> {
+    // Звук клипа: исходник и дорожка; смесь двух исходников разделенного экрана - `None`
+    let voice = voice_source(task, media_info, settings).map(|(input, track, media)| (input.to_path_buf(), track, media.has_audio()));
+    
+    // Первый проход loudnorm: измеряем громкость того же фрагмента, что пойдет в рендер
+    // (смесь разделенного экрана нормализуется за один проход)
+    if let Some(target) = &settings.loudnorm {
+        if let Some((input, track, true)) = &voice {
+            let clip_duration = task.segment.map_or(media_info.duration, |segment| segment.duration());
+            let stats = measure_loudnorm(input, task.segment, *track, target, clip_duration)
+                .await
+                .map_err(|e| format!("Loudness measurement failed: {}", e))?;
+            match stats {
+                Some(stats) => {
+                    info!("LOUDNESS: {} measured {:.1} LUFS, true peak {:.1} dBTP", filename, stats.input_i, stats.input_tp);
+                    task.loudnorm = Some(stats);
+                }
+                None => warn!("Skipping loudness normalization for {}: audio is silent", filename),
+            }
+            // Музыка подмешивается к уже нормализованному голосу и в цель громкости не входит
+            if task.loudnorm.is_some() && settings.music.is_some() {
+                info!("LOUDNESS: {} normalizing the voice only; music is mixed in afterwards", filename);
+            }
+        }
+    }
+    
+    // Черные полосы: обрезка из списка нарезки или быстрый проход cropdetect по клипу
+    let full_frame = Resolution::new(media_info.width(), media_info.height());
+    match task.crop {
+        Some(SourceCrop::Rect(crop)) if !crop.fits(full_frame) => {
+            return Err(format!("Crop {} does not fit the {} frame", crop, full_frame));
+        }
+        Some(SourceCrop::Rect(crop)) => info!("CROP: {} crop={} (cut list)", filename, crop),
+        Some(SourceCrop::Full) => info!("CROP: {} full frame (cut list)", filename),
+        None if settings.autocrop => {
+            // Обрезка полос необязательна: при сбое прохода клип рендерится с кадром целиком
+            task.crop = Some(match detect_crop(&task.input, task.segment, full_frame, media_info.duration).await {
+                Ok(Some(crop)) => {
+                    info!("CROP: {} crop={} (detected)", filename, crop);
+                    SourceCrop::Rect(crop)
+                }
+                Ok(None) => {
+                    info!("CROP: {} full frame, no black bars", filename);
+                    SourceCrop::Full
+                }
+                Err(e) => {
+                    warn!("Crop detection failed for {}: {}; keeping the full frame", filename, e);
+                    SourceCrop::Full
+                }
+            });
+        }
+        None => {}
+    }
+    
+    // Нижний исходник разделенного экрана проходит тот же поиск полос
+    if let (true, Some(split_input), Some(split_media)) = (settings.autocrop, &task.split_input, &task.split_media_info) {
+        let split_frame = Resolution::new(split_media.width(), split_media.height());
+        match detect_crop(split_input, task.segment, split_frame, split_media.duration).await {
+            Ok(Some(crop)) => {
+                info!("CROP: {} bottom input crop={} (detected)", filename, crop);
+                task.split_crop = Some(crop);
+            }
+            Ok(None) => info!("CROP: {} bottom input full frame, no black bars", filename),
+            Err(e) => warn!("Crop detection failed for the bottom input of {}: {}; keeping its full frame", filename, e),
+        }
+    }
+    
+    // Преобладающий цвет фона берется из кадра в середине клипа
+    let source = task.crop_rect().map_or(full_frame, |crop| crop.resolution());
+    let targets: Vec<Resolution> = settings.variants.iter().map(|variant| variant.resolution).collect();
+    if settings.background == BackgroundMode::Dominant && task.split_input.is_none() && needs_background(source, &targets) {
+        let color = detect_dominant_color(&task.input, task.segment, task.crop_rect(), media_info.duration)
+            .await
+            .map_err(|e| format!("Dominant color detection failed: {}", e))?;
+        match color {
+            Some(color) => {
+                info!("BACKGROUND: {} dominant color #{:06X}", filename, color);
+                task.background_color = Some(color);
+            }
+            None => warn!("No probe frame for {}: using blurred background", filename),
+        }
+    }
+    
+    // Окно кадра следует за движением: траектория считается по уменьшенным кадрам клипа
+    if settings.reframe && task.split_input.is_none() && needs_background(source, &targets) {
+        let track = track_motion(&task.input, task.segment, source, task.crop_rect(), media_info.duration)
+            .await
+            .map_err(|e| format!("Motion tracking failed: {}", e))?;
+        match track {
+            Some(track) => {
+                info!("REFRAME: {} crop path with {} keyframes", filename, track.points.len());
+                task.reframe = Some(track);
+            }
+            None => warn!("No frames to track in {}: using blurred background", filename),
+        }
+    }
+    
+    // Распознаем речь для клипов без файла субтитров; результат кэшируется рядом с выходом.
+    // В разделенном экране речь берется из исходника, чей звук идет в клип (для смеси - из верхнего)
+    if let Some(transcriber) = &settings.transcriber {
+        let has_words = settings.karaoke.is_some() && task.word_timings.is_some();
+        let (speech_input, speech_track, has_speech) =
+            voice.unwrap_or((task.input.clone(), task.audio_track.unwrap_or(0), media_info.has_audio()));
+        if task.subtitles.is_none() && !has_words && has_speech {
+            let speech_task = FileTask { input: speech_input, audio_track: Some(speech_track), ..task.clone() };
+            let transcript = transcribe_task(&speech_task, media_info, transcriber.as_ref())
+                .await
+                .map_err(|e| format!("Transcription failed: {}", e))?;
+            task.transcript = Some(transcript);
+        }
+    }
+    
+    // Пословные субтитры: ASS для каждого выхода из таймингов слов
+    if let Some(preset) = &settings.karaoke {
+        let files = write_karaoke_files(task, preset, &settings.variants, settings.subtitles.safe_zone)
+            .map_err(|e| format!("Karaoke captions failed: {}", e))?;
+        if files.is_empty() {
+            warn!("No word timings for {}: karaoke captions skipped", filename);
+        }
+        task.karaoke = files;
+    }
+    
+    Ok(())
+}
+
 /// Обрабатывает один файл
-async fn process_single_file(task: FileTask) -> TaskResult {
+async fn process_single_file(mut task: FileTask, settings: &RenderSettings) -> TaskResult {
     let start_time = Instant::now();
-    let filename = task.input_filename();
+    let filename = task.display_name();
     
     // Создаем логгер для этого файла
     let logger = FileProcessingLogger::start_processing(&filename);
@@ -226,10 +381,11 @@ async fn process_single_file(task: FileTask) -> TaskResult {
         let duration = start_time.elapsed();
         let error_msg = format!("Task validation failed: {}", e);
         
-        logger.log_error(&task.input, &task.output, &error_msg);
+        logger.log_error(&task.input, task.primary_output(), &error_msg);
         
         return TaskResult::Failure {
             input: task.input,
+            part: task.part,
             error: error_msg,
             duration,
         };
@@ -240,40 +396,105 @@ async fn process_single_file(task: FileTask) -> TaskResult {
         let duration = start_time.elapsed();
         let error_msg = format!("Input file validation failed: {}", e);
         
-        logger.log_error(&task.input, &task.output, &error_msg);
+        logger.log_error(&task.input, task.primary_output(), &error_msg);
         
         return TaskResult::Failure {
             input: task.input,
+            part: task.part,
+            error: error_msg,
+            duration,
+        };
+    }
+    
+    // Анализируем входной файл через ffprobe, если это еще не сделано
+    let media_info = match task.media_info.take() {
+        Some(media_info) => media_info,
+        None => match probe_media(&task.input).await {
+            Ok(media_info) => media_info,
+            Err(e) => {
+                let duration = start_time.elapsed();
+                let error_msg = format!("Media probe failed: {}", e);
+                
+                logger.log_error(&task.input, task.primary_output(), &error_msg);
+                
+                return TaskResult::Failure {
+                    input: task.input,
+                    part: task.part,
+                    error: error_msg,
+                    duration,
+                };
+            }
+        },
+    };
+    logger.log_media_info(&media_info);
+    debug!("Estimated output size for {}: {}", 
+           filename, crate::utils::format_file_size(estimate_output_size(&media_info)));
+    
+    // Предварительные проходы: громкость, черные полосы, фон, движение, речь и субтитры
+    if let Err(error_msg) = prepare_task(&mut task, &media_info, settings, &filename).await {
+        let duration = start_time.elapsed();
+        
+        logger.log_error(&task.input, task.primary_output(), &error_msg);
+        
+        return TaskResult::Failure {
+            input: task.input,
+            part: task.part,
             error: error_msg,
             duration,
         };
     }
     
     // Создаем FFmpeg команду
-    let ffmpeg_cmd = FfmpegCommand::new(task.input.clone(), task.output.clone());
+    let ffmpeg_cmd = match FfmpegCommand::new(&task, &media_info, settings) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            let duration = start_time.elapsed();
+            let error_msg = e.to_string();
+            remove_karaoke_files(&task.karaoke);
+            
+            logger.log_error(&task.input, task.primary_output(), &error_msg);
+            
+            return TaskResult::Failure {
+                input: task.input,
+                part: task.part,
+                error: error_msg,
+                duration,
+            };
+        }
+    };
+    task.media_info = Some(media_info);
     logger.log_ffmpeg_command(ffmpeg_cmd.display_string());
     
-    // Выполняем FFmpeg команду
-    match execute_ffmpeg_command(ffmpeg_cmd).await {
+    // Выполняем FFmpeg команду; ASS пословных субтитров нужен только на время рендера
+    let execution = execute_ffmpeg_command(ffmpeg_cmd).await;
+    remove_karaoke_files(&task.karaoke);
+    match execution {
         Ok(ffmpeg_result) => {
             let duration = start_time.elapsed();
             
             if ffmpeg_result.success {
-                logger.log_success(&task.input, &task.output);
+                logger.log_success(&task.input, &task.outputs);
+                
+                let loudness = task.loudnorm.as_ref().map(|measured| {
+                    LoudnessResult::from_passes(measured, parse_loudnorm_stats(&ffmpeg_result.stderr).as_ref())
+                });
                 
                 TaskResult::Success {
                     input: task.input,
-                    output: task.output,
+                    part: task.part,
+                    outputs: task.outputs,
                     duration,
                     ffmpeg_result,
+                    loudness,
                 }
             } else {
                 let error_msg = format!("FFmpeg execution failed: {}", 
                                        ffmpeg_result.error_details().unwrap_or_else(|| "Unknown error".to_string()));
-                logger.log_error(&task.input, &task.output, &error_msg);
+                logger.log_error(&task.input, task.primary_output(), &error_msg);
                 
                 TaskResult::Failure {
                     input: task.input,
+                    part: task.part,
                     error: error_msg,
                     duration,
                 }
@@ -283,10 +504,11 @@ async fn process_single_file(task: FileTask) -> TaskResult {
             let duration = start_time.elapsed();
             let error_msg = format!("FFmpeg error: {}", e);
             
-            logger.log_error(&task.input, &task.output, &error_msg);
+            logger.log_error(&task.input, task.primary_output(), &error_msg);
             
             TaskResult::Failure {
                 input: task.input,
+                part: task.part,
                 error: error_msg,
                 duration,
             }
@@ -369,7 +591,7 @@ mod tests {
     
     #[test]
     fn test_worker_pool_creation() {
-        let pool = WorkerPool::new(4);
+        let pool = WorkerPool::new(4, RenderSettings::default());
         assert_eq!(pool.max_workers(), 4);
         assert_eq!(pool.available_permits(), 4);
     }
@@ -378,7 +600,8 @@ mod tests {
     fn test_processing_results() {
         let successful_result = TaskResult::Success {
             input: PathBuf::from("input.mp4"),
-            output: PathBuf::from("output.mp4"),
+            part: None,
+            outputs: vec![PathBuf::from("output.mp4")],
             duration: Duration::from_secs(10),
             ffmpeg_result: crate::ffmpeg::FfmpegExecutionResult {
                 success: true,
@@ -388,10 +611,12 @@ mod tests {
                 duration: Duration::from_secs(10),
                 command: "ffmpeg...".to_string(),
             },
+            loudness: None,
         };
         
         let failed_result = TaskResult::Failure {
             input: PathBuf::from("input2.mp4"),
+            part: Some("part01".to_string()),
             error: "Test error".to_string(),
             duration: Duration::from_secs(5),
         };
@@ -426,7 +651,8 @@ mod tests {
     fn test_task_result() {
         let result = TaskResult::Success {
             input: PathBuf::from("test.mp4"),
-            output: PathBuf::from("test-short.mp4"),
+            part: None,
+            outputs: vec![PathBuf::from("test-short.mp4")],
             duration: Duration::from_secs(5),
             ffmpeg_result: crate::ffmpeg::FfmpegExecutionResult {
                 success: true,
@@ -436,6 +662,7 @@ mod tests {
                 duration: Duration::from_secs(5),
                 command: "ffmpeg...".to_string(),
             },
+            loudness: None,
         };
         
         assert!(result.is_success());